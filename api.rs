@@ -1,14 +1,22 @@
 // src/api.rs
 use actix_web::web;
 use actix_web::web::route;
-use crate::handlers::{auth_handler, rfq_handler, quote_handler, order_handler, auth_middleware::Auth, company_handler, user_handler, analytics_handler, payment_handler, admin_handler, capability_handler, notification_handler, ws_handler};
+use crate::handlers::{auth_handler, rfq_handler, quote_handler, order_handler, auth_middleware::Auth, admin_middleware::AdminAuth, company_handler, user_handler, analytics_handler, payment_handler, admin_handler, capability_handler, notification_handler, ws_handler, shipment_handler, dispute_handler, invoice_handler};
+use crate::models::user::{ADMIN_PERM_COMPANY_VERIFICATION, ADMIN_PERM_DISPUTE_RESOLUTION, ADMIN_PERM_TAXONOMY, ADMIN_PERM_USER_MANAGEMENT};
 
 pub fn config(cfg: &mut web::ServiceConfig) {
     // 公开路由，不需要登录
     cfg.service(
         web::scope("/api/auth")
             .route("/register", web::post().to(auth_handler::register))
-            .route("/login", web::post().to(auth_handler::login)),
+            .route("/login", web::post().to(auth_handler::login))
+            .route("/login/2fa", web::post().to(auth_handler::login_mfa))
+            .route("/refresh", web::post().to(auth_handler::refresh))
+            .route("/forgot-password", web::post().to(auth_handler::forgot_password))
+            .route("/reset-password", web::post().to(auth_handler::reset_password))
+            .route("/verify-email", web::post().to(auth_handler::verify_email))
+            .route("/invitations/{token}", web::get().to(auth_handler::get_invitation))
+            .route("/accept-invitation", web::post().to(auth_handler::accept_invitation)),
     );
 
     // 受保护的RFQ路由
@@ -29,7 +37,13 @@ pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/api/quotes")
             .wrap(Auth)
-            .route("/{quote_id}/accept", web::post().to(quote_handler::post_accept_quote)),
+            .route("/{quote_id}/accept", web::post().to(quote_handler::post_accept_quote))
+            .route("/{quote_id}/negotiations", web::post().to(quote_handler::post_counter_offer))
+            .route("/{quote_id}/negotiations", web::get().to(quote_handler::get_negotiations))
+            .route("/{quote_id}/negotiations/{negotiation_id}/respond", web::post().to(quote_handler::post_respond_negotiation))
+            .route("/{quote_id}/attachments", web::post().to(quote_handler::post_quote_attachments))
+            .route("/{quote_id}/attachments", web::get().to(quote_handler::get_quote_attachments))
+            .route("/{quote_id}/attachments/{attachment_id}", web::get().to(quote_handler::download_quote_attachment)),
     );
 
     // 受保护的Order路由
@@ -37,9 +51,50 @@ pub fn config(cfg: &mut web::ServiceConfig) {
         web::scope("/api/orders")
             .wrap(Auth)
             .route("", web::get().to(order_handler::get_orders))
+            .route("/{order_id}", web::get().to(order_handler::get_order_detail))
             .route("/{order_id}/status", web::patch().to(order_handler::patch_order_status))
+            .route("/{order_id}/history", web::get().to(order_handler::get_order_history))
+            .route("/{order_id}/confirm", web::post().to(order_handler::post_confirm_order))
+            .route("/{order_id}/decline", web::post().to(order_handler::post_decline_order))
+            .route("/{order_id}/shipments", web::post().to(shipment_handler::post_shipment))
+            .route("/{order_id}/shipments", web::get().to(shipment_handler::get_shipments))
+            .route("/{order_id}/shipments/{shipment_id}/packing-list", web::get().to(shipment_handler::download_packing_list))
+            .route("/{order_id}/shipments/{shipment_id}/receipt", web::post().to(shipment_handler::post_goods_receipt))
+            .route("/{order_id}/cancellation-requests", web::post().to(order_handler::post_cancellation_request))
+            .route("/{order_id}/cancellation-requests", web::get().to(order_handler::get_cancellation_requests))
+            .route("/{order_id}/cancellation-requests/{request_id}/respond", web::post().to(order_handler::post_respond_cancellation))
+            .route("/{order_id}/change-orders", web::post().to(order_handler::post_change_order))
+            .route("/{order_id}/change-orders", web::get().to(order_handler::get_change_orders))
+            .route("/{order_id}/change-orders/{change_request_id}/respond", web::post().to(order_handler::post_respond_change_order))
+            .route("/{order_id}/versions", web::get().to(order_handler::get_order_versions))
+            .route("/{order_id}/documents", web::get().to(order_handler::get_order_documents))
+            .route("/{order_id}/documents/purchase-order", web::get().to(order_handler::download_purchase_order))
+            .route("/{order_id}/documents/invoices/{invoice_id}", web::get().to(order_handler::download_invoice))
+            .route("/{order_id}/invoices", web::post().to(invoice_handler::post_invoice))
+            .route("/{order_id}/invoices", web::get().to(invoice_handler::get_order_invoices))
+            .route("/{order_id}/disputes", web::post().to(dispute_handler::post_dispute))
+            .route("/{order_id}/disputes", web::get().to(dispute_handler::get_disputes))
+            .route("/{order_id}/disputes/{dispute_id}/messages", web::post().to(dispute_handler::post_dispute_message))
+            .route("/{order_id}/disputes/{dispute_id}/messages", web::get().to(dispute_handler::get_dispute_messages))
+            .route("/{order_id}/disputes/{dispute_id}/evidence", web::post().to(dispute_handler::post_dispute_evidence))
+            .route("/{order_id}/disputes/{dispute_id}/evidence", web::get().to(dispute_handler::get_dispute_evidence))
+            .route("/{order_id}/disputes/{dispute_id}/evidence/{evidence_id}", web::get().to(dispute_handler::download_dispute_evidence))
+            .route("/{order_id}/rmas", web::post().to(dispute_handler::post_rma))
+            .route("/{order_id}/rmas", web::get().to(dispute_handler::get_rmas))
+            .route("/{order_id}/rmas/{rma_id}/decision", web::put().to(dispute_handler::put_rma_decision))
+            .route("/{order_id}/rmas/{rma_id}/close", web::put().to(dispute_handler::put_close_rma))
         // --- 新增 ---
-            .route("/{order_id}/create-checkout-session", web::post().to(payment_handler::create_session)),
+            .route("/{order_id}/create-checkout-session", web::post().to(payment_handler::create_session))
+            .route("/{order_id}/payments", web::get().to(payment_handler::get_order_payments))
+            .route("/{order_id}/refunds", web::post().to(payment_handler::post_refund)),
+    );
+
+    cfg.service(
+        web::scope("/api/invoices")
+            .wrap(Auth)
+            .route("", web::get().to(invoice_handler::get_invoices))
+            .route("/{invoice_id}", web::get().to(invoice_handler::get_invoice))
+            .route("/{invoice_id}/checkout-session", web::post().to(payment_handler::create_invoice_session)),
     );
 
     // --- 新增受保护的User路由 ---
@@ -47,7 +102,14 @@ pub fn config(cfg: &mut web::ServiceConfig) {
         web::scope("/api/users")
             .wrap(Auth)
             .route("/me", web::get().to(user_handler::get_me)) // GET /api/users/me
-            .route("/me/password", web::put().to(user_handler::update_password)), // PUT /api/users/me/password
+            .route("/me/password", web::put().to(user_handler::update_password)) // PUT /api/users/me/password
+            .route("/me/verification-email", web::post().to(user_handler::resend_verification))
+            .route("/me/logout", web::post().to(user_handler::logout))
+            .route("/me/logout-all", web::post().to(user_handler::logout_all))
+            .route("/me/2fa/setup", web::post().to(user_handler::post_totp_setup))
+            .route("/me/2fa/enable", web::post().to(user_handler::post_totp_enable))
+            .route("/me/2fa/disable", web::post().to(user_handler::post_totp_disable))
+            .route("/me/2fa/recovery-codes", web::post().to(user_handler::post_recovery_codes)),
     );
 
     // --- 新增受保护的Company路由 ---
@@ -55,7 +117,17 @@ pub fn config(cfg: &mut web::ServiceConfig) {
         web::scope("/api/companies")
             .wrap(Auth) // 查看和修改都需要登录
             .route("/{company_id}", web::get().to(company_handler::get_profile))
-            .route("/{company_id}", web::put().to(company_handler::update_profile)),
+            .route("/{company_id}", web::put().to(company_handler::update_profile))
+            .route("/{company_id}/members", web::get().to(company_handler::get_members))
+            .route("/{company_id}/members/{user_id}/role", web::put().to(company_handler::update_member_role))
+            .route("/{company_id}/invitations", web::post().to(company_handler::post_invitation))
+            .route("/{company_id}/invitations", web::get().to(company_handler::get_invitations))
+            .route("/{company_id}/invitations/{invitation_id}", web::delete().to(company_handler::delete_invitation))
+            .route("/{company_id}/invitations/{invitation_id}/resend", web::post().to(company_handler::post_resend_invitation))
+            .route("/{company_id}/api-keys", web::post().to(company_handler::post_api_key))
+            .route("/{company_id}/api-keys", web::get().to(company_handler::get_api_keys))
+            .route("/{company_id}/api-keys/{key_id}", web::delete().to(company_handler::delete_api_key))
+            .route("/{company_id}/audit-logs", web::get().to(company_handler::get_audit_logs)),
     );
 
     // --- 新增受保护的Analytics路由 ---
@@ -82,15 +154,61 @@ pub fn config(cfg: &mut web::ServiceConfig) {
         web::scope("/api/stripe")
             .route("/webhook", web::post().to(payment_handler::handle_webhook)),
     );
+    // 与支付渠道无关的Webhook入口，同样不经过Auth
+    cfg.service(
+        web::scope("/api/payments")
+            .route("/webhook", web::post().to(payment_handler::handle_webhook)),
+    );
     // --- admin --
+    // Auth 确认已登录，AdminAuth 再从数据库检查管理员身份和具体权限
     cfg.service(
         web::scope("/api/admin")
-            .wrap(Auth) // 使用普通Auth中间件确保用户已登录
-            .route("/companies", web::get().to(admin_handler::get_all_companies))
-            .route("/companies/{id}/verify", web::put().to(admin_handler::put_verify_company))
-        // --- 新增 ---
-            .route("/users", web::get().to(admin_handler::get_all_users))
-            .route("/users/{id}/status", web::put().to(admin_handler::put_update_user_status)),
+            .wrap(Auth)
+            .service(
+                web::resource("/users/{id}/permissions")
+                    .wrap(AdminAuth::super_admin())
+                    .route(web::get().to(admin_handler::get_user_permissions))
+                    .route(web::put().to(admin_handler::put_user_permissions)),
+            )
+            .service(
+                web::scope("/companies")
+                    .wrap(AdminAuth::permission(ADMIN_PERM_COMPANY_VERIFICATION))
+                    .route("", web::get().to(admin_handler::get_all_companies))
+                    .route("/{id}/verify", web::put().to(admin_handler::put_verify_company)),
+            )
+            .service(
+                web::scope("/users")
+                    .wrap(AdminAuth::permission(ADMIN_PERM_USER_MANAGEMENT))
+                    .route("", web::get().to(admin_handler::get_all_users))
+                    .route("/{id}/status", web::put().to(admin_handler::put_update_user_status))
+                    .route("/{id}/unlock", web::put().to(admin_handler::put_unlock_user))
+                    .route("/{id}/2fa/reset", web::put().to(admin_handler::put_reset_user_2fa)),
+            )
+            .service(
+                web::scope("/disputes")
+                    .wrap(AdminAuth::permission(ADMIN_PERM_DISPUTE_RESOLUTION))
+                    .route("", web::get().to(admin_handler::get_open_disputes))
+                    .route("/{id}/resolve", web::put().to(admin_handler::put_resolve_dispute)),
+            )
+            .service(
+                web::scope("/capabilities")
+                    .wrap(AdminAuth::permission(ADMIN_PERM_TAXONOMY))
+                    .route("", web::post().to(admin_handler::post_capability))
+                    .route("/{id}", web::put().to(admin_handler::put_capability))
+                    .route("/{id}", web::delete().to(admin_handler::delete_capability)),
+            )
+            .service(
+                web::resource("/audit-logs")
+                    .wrap(AdminAuth::super_admin())
+                    .route(web::get().to(admin_handler::get_audit_logs)),
+            )
+            .service(
+                web::scope("/fx-rates")
+                    .wrap(AdminAuth::super_admin())
+                    .route("", web::get().to(admin_handler::get_fx_rates))
+                    .route("", web::post().to(admin_handler::post_fx_rate))
+                    .route("/import", web::post().to(admin_handler::post_import_fx_rates)),
+            ),
     );
     // ---Capabilities
 
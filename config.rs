@@ -4,15 +4,45 @@ use dotenv::dotenv;
 use sqlx::{MySql, MySqlPool, Pool};
 use std::env;
 
+#[derive(Clone)]
 pub struct Config {
     pub database_url: String,
+    // 供应商需要在多少小时内确认订单，超时的订单会被标记
+    pub order_confirmation_sla_hours: i64,
+    // 逾期发票每隔多少天提醒一次采购方
+    pub invoice_reminder_interval_days: i64,
+    // 距离发货截止日期还剩多少天时将未发货的订单标记为有延误风险
+    pub delivery_at_risk_days: i64,
+    // 支付渠道：stripe 或 mock（本地模拟，不产生真实付款）
+    pub payment_provider: String,
 }
 
 impl Config {
     pub fn from_env() -> Self {
         //dotenv().ok();
         let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-        Self { database_url }
+        let order_confirmation_sla_hours = env::var("ORDER_CONFIRMATION_SLA_HOURS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(48);
+        let invoice_reminder_interval_days = env::var("INVOICE_REMINDER_INTERVAL_DAYS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(7);
+        let delivery_at_risk_days = env::var("DELIVERY_AT_RISK_DAYS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(3);
+        let payment_provider = env::var("PAYMENT_PROVIDER")
+            .map(|v| v.to_lowercase())
+            .unwrap_or_else(|_| "stripe".to_string());
+        Self {
+            database_url,
+            order_confirmation_sla_hours,
+            invoice_reminder_interval_days,
+            delivery_at_risk_days,
+            payment_provider,
+        }
     }
 
     pub async fn db_pool(&self) -> Pool<MySql> {
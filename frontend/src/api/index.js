@@ -48,10 +48,15 @@ export const updateCompanyProfile = (id, data) => apiClient.put(`/companies/${id
 
 
 // --- Order API ---
-export const getOrders = () => apiClient.get('/orders');
+export const getOrders = (params) => apiClient.get('/orders', { params });
 export const updateOrderStatus = (orderId, status) => {
     return apiClient.patch(`/orders/${orderId}/status`, { status });
 };
+export const confirmOrder = (orderId, data) => apiClient.post(`/orders/${orderId}/confirm`, data);
+export const declineOrder = (orderId, reason) => apiClient.post(`/orders/${orderId}/decline`, { reason });
+// formData 包含 quantity_shipped、carrier、tracking_number、ship_date，可附带 packing_list 文件
+export const createShipment = (orderId, formData) => apiClient.post(`/orders/${orderId}/shipments`, formData);
+export const requestOrderCancellation = (orderId, reason) => apiClient.post(`/orders/${orderId}/cancellation-requests`, { reason });
 export const getChatHistory = (rfqId) => apiClient.get(`/rfqs/${rfqId}/messages`);
 
 export const getMyProfile = () => apiClient.get('/users/me');
@@ -71,6 +71,11 @@ function BuyerAnalytics() {
                 <div style={{ padding: '1rem', border: '1px solid #eee', borderRadius: 'var(--border-radius)' }}>
                     <h4>Total Spent</h4>
                     <p style={{ fontSize: '2rem', fontWeight: 'bold' }}>${parseFloat(stats?.total_spent || 0).toFixed(2)}</p>
+                    {stats?.unconverted_orders > 0 && (
+                        <small style={{ color: '#888' }}>
+                            {stats.unconverted_orders} order(s) not included: no exchange rate to {stats.currency}
+                        </small>
+                    )}
                 </div>
                 <div style={{ padding: '1rem', border: '1px solid #eee', borderRadius: 'var(--border-radius)' }}>
                     <h4>Suppliers Worked With</h4>
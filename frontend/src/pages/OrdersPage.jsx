@@ -4,9 +4,29 @@ import React, { useState, useEffect, useCallback } from 'react';
 import { useAuth } from '../context/AuthContext';
 import * as api from '../api';
 
+// 与后端 order_service::ORDER_TRANSITIONS 保持一致：[当前状态, 目标状态, 可操作的一方]。
+// 收货、完成验收和争议需要填写更多信息，在订单详情中操作，这里只列出可以在列表中直接完成的操作
+const ORDER_ACTIONS = [
+    ['PENDING_CONFIRMATION', 'CONFIRMED', 'SUPPLIER', 'Confirm order'],
+    ['PENDING_CONFIRMATION', 'DECLINED', 'SUPPLIER', 'Decline order'],
+    ['CONFIRMED', 'IN_PRODUCTION', 'SUPPLIER', 'Start production'],
+    ['IN_PRODUCTION', 'SHIPPED', 'SUPPLIER', 'Record shipment'],
+    ['DELIVERED', 'COMPLETED', 'BUYER', 'Mark completed'],
+    ['PENDING_CONFIRMATION', 'CANCELLED', 'BOTH', 'Request cancellation'],
+    ['CONFIRMED', 'CANCELLED', 'BOTH', 'Request cancellation'],
+    ['IN_PRODUCTION', 'CANCELLED', 'BOTH', 'Request cancellation'],
+];
+
+const actionsFor = (status, companyType) =>
+    ORDER_ACTIONS.filter(([from, , party]) => from === status && (party === companyType || party === 'BOTH'));
+
+const today = () => new Date().toISOString().slice(0, 10);
+
 function OrdersPage() {
     const { user } = useAuth();
     const [orders, setOrders] = useState([]);
+    const [page, setPage] = useState(1);
+    const [pagination, setPagination] = useState({ total: 0, page_size: 20 });
     const [isLoading, setIsLoading] = useState(true);
     const [error, setError] = useState('');
 
@@ -14,29 +34,73 @@ function OrdersPage() {
         setIsLoading(true);
         setError('');
         try {
-            const response = await api.getOrders();
-            setOrders(response.data);
+            const response = await api.getOrders({ page });
+            setOrders(response.data.orders);
+            setPagination({ total: response.data.total, page_size: response.data.page_size });
         } catch (err) {
             console.error("Failed to fetch orders", err);
             setError("Could not load orders.");
         } finally {
             setIsLoading(false);
         }
-    }, []);
+    }, [page]);
+
+    const totalPages = Math.max(1, Math.ceil(pagination.total / pagination.page_size));
 
     useEffect(() => {
         fetchOrders();
     }, [fetchOrders]);
 
-    const handleStatusChange = async (orderId, newStatus) => {
+    // 确认、拒绝、发货和取消各有专门的接口，其余状态通过通用的状态接口修改
+    const performAction = async (order, target) => {
+        switch (target) {
+            case 'CONFIRMED': {
+                const date = window.prompt('Committed ship date (YYYY-MM-DD):', today());
+                if (!date) return false;
+                await api.confirmOrder(order.id, { committed_ship_date: date });
+                return true;
+            }
+            case 'DECLINED': {
+                const reason = window.prompt('Reason for declining this order:');
+                if (!reason) return false;
+                await api.declineOrder(order.id, reason);
+                return true;
+            }
+            case 'SHIPPED': {
+                const quantity = window.prompt('Quantity shipped:', order.quantity);
+                if (!quantity) return false;
+                const carrier = window.prompt('Carrier:');
+                if (!carrier) return false;
+                const formData = new FormData();
+                formData.append('quantity_shipped', quantity);
+                formData.append('carrier', carrier);
+                formData.append('tracking_number', window.prompt('Tracking number (optional):') || '');
+                formData.append('ship_date', today());
+                await api.createShipment(order.id, formData);
+                return true;
+            }
+            case 'CANCELLED': {
+                const reason = window.prompt('Reason for cancelling this order:');
+                if (!reason) return false;
+                await api.requestOrderCancellation(order.id, reason);
+                alert('Cancellation requested. The other party must approve it.');
+                return true;
+            }
+            default:
+                await api.updateOrderStatus(order.id, target);
+                return true;
+        }
+    };
+
+    const handleAction = async (order, target) => {
         try {
-            await api.updateOrderStatus(orderId, newStatus);
-            alert('Order status updated!');
-            // 重新获取订单列表以显示最新状态
-            fetchOrders();
+            if (await performAction(order, target)) {
+                // 重新获取订单列表以显示最新状态
+                fetchOrders();
+            }
         } catch (err) {
-            console.error('Failed to update status', err);
-            alert('Failed to update status.');
+            console.error('Failed to update order', err);
+            alert(err.response?.data || 'Failed to update order.');
         }
     };
 
@@ -54,7 +118,7 @@ function OrdersPage() {
                     <th>{user.company_type === 'BUYER' ? 'Supplier' : 'Buyer'}</th>
                     <th>Amount</th>
                     <th>Status</th>
-                    {user.company_type === 'SUPPLIER' && <th>Update Status</th>}
+                    <th>Actions</th>
                 </tr>
                 </thead>
                 <tbody>
@@ -70,25 +134,32 @@ function OrdersPage() {
                             <td>{user.company_type === 'BUYER' ? order.supplier_name : order.buyer_name}</td>
                             <td>${order.total_amount}</td>
                             <td>{order.status}</td>
-                            {user.company_type === 'SUPPLIER' && (
-                                <td>
+                            <td>
+                                {actionsFor(order.status, user.company_type).length > 0 ? (
                                     <select
-                                        defaultValue={order.status}
-                                        onChange={(e) => handleStatusChange(order.id, e.target.value)}
-                                        disabled={order.status === 'COMPLETED'}
+                                        value=""
+                                        onChange={(e) => handleAction(order, e.target.value)}
                                     >
-                                        <option value="PENDING_CONFIRMATION" disabled>Pending Confirmation</option>
-                                        <option value="IN_PRODUCTION">In Production</option>
-                                        <option value="SHIPPED">Shipped</option>
-                                        <option value="COMPLETED">Completed</option>
+                                        <option value="" disabled>Choose an action</option>
+                                        {actionsFor(order.status, user.company_type).map(([, target, , label]) => (
+                                            <option key={target} value={target}>{label}</option>
+                                        ))}
                                     </select>
-                                </td>
-                            )}
+                                ) : '—'}
+                            </td>
                         </tr>
                     ))
                 )}
                 </tbody>
             </table>
+            <div style={{display: 'flex', justifyContent: 'space-between', alignItems: 'center', marginTop: '1rem'}}>
+                <span>{pagination.total} order(s)</span>
+                <div style={{display: 'flex', gap: '0.5rem', alignItems: 'center'}}>
+                    <button onClick={() => setPage(page - 1)} disabled={page <= 1}>Previous</button>
+                    <span>Page {page} of {totalPages}</span>
+                    <button onClick={() => setPage(page + 1)} disabled={page >= totalPages}>Next</button>
+                </div>
+            </div>
             {/* 简单的CSS样式，可以移到 index.css */}
             <style jsx>{`
                 th, td {
@@ -1,5 +1,16 @@
 // src/handlers/admin_handler.rs
-use crate::{errors::AppError, models::user::Claims, services::admin_service};
+use crate::{
+    errors::AppError,
+    models::{
+        audit::AuditLogQuery,
+        capability::CapabilityDto,
+        dispute::ResolveDisputeDto,
+        fx::{FxRateDto, FxRateQuery},
+        user::{Claims, UpdateAdminPermissionsDto},
+    },
+    services::{admin_service, audit_service, capability_service, chat_server::ChatServer, dispute_service, fx_service, mfa_service},
+};
+use actix::Addr;
 use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Responder};
 use sqlx::MySqlPool;
 use serde::Deserialize; // <-- 导入
@@ -9,29 +20,23 @@ pub struct UserStatusUpdate {
     is_active: bool,
 }
 
-// 权限检查辅助函数
-fn check_admin(req: &HttpRequest) -> Result<Claims, AppError> {
-    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
-    if !claims.is_admin {
-        return Err(AppError::BadRequest("Administrator privileges required.".to_string()));
-    }
-    Ok(claims)
+// 管理员身份和权限已由 AdminAuth 中间件检查，这里只取出当前用户
+fn admin_claims(req: &HttpRequest) -> Result<Claims, AppError> {
+    req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)
 }
 
-pub async fn get_all_companies(pool: web::Data<MySqlPool>, req: HttpRequest) -> Result<impl Responder, AppError> {
-    check_admin(&req)?;
+pub async fn get_all_companies(pool: web::Data<MySqlPool>) -> Result<impl Responder, AppError> {
     let companies = admin_service::list_all_companies(pool.get_ref()).await?;
     Ok(HttpResponse::Ok().json(companies))
 }
 
 pub async fn put_verify_company(pool: web::Data<MySqlPool>, company_id: web::Path<i32>, req: HttpRequest) -> Result<impl Responder, AppError> {
-    check_admin(&req)?;
-    admin_service::verify_company(pool.get_ref(), company_id.into_inner()).await?;
+    let claims = admin_claims(&req)?;
+    admin_service::verify_company(pool.get_ref(), company_id.into_inner(), &claims).await?;
     Ok(HttpResponse::Ok().json(serde_json::json!({ "message": "Company verified successfully" })))
 }
 
-pub async fn get_all_users(pool: web::Data<MySqlPool>, req: HttpRequest) -> Result<impl Responder, AppError> {
-    check_admin(&req)?;
+pub async fn get_all_users(pool: web::Data<MySqlPool>) -> Result<impl Responder, AppError> {
     let users = admin_service::list_all_users(pool.get_ref()).await?;
     Ok(HttpResponse::Ok().json(users))
 }
@@ -42,7 +47,129 @@ pub async fn put_update_user_status(
     dto: web::Json<UserStatusUpdate>,
     req: HttpRequest,
 ) -> Result<impl Responder, AppError> {
-    check_admin(&req)?;
-    admin_service::update_user_status(pool.get_ref(), user_id.into_inner(), dto.is_active).await?;
+    let claims = admin_claims(&req)?;
+    admin_service::update_user_status(pool.get_ref(), user_id.into_inner(), dto.is_active, &claims).await?;
     Ok(HttpResponse::Ok().json(serde_json::json!({ "message": "User status updated successfully" })))
-}
\ No newline at end of file
+}
+
+/// 解除因多次登录失败导致的账号锁定
+pub async fn put_unlock_user(
+    pool: web::Data<MySqlPool>,
+    user_id: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = admin_claims(&req)?;
+    admin_service::unlock_user(pool.get_ref(), user_id.into_inner(), &claims).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "message": "User account unlocked" })))
+}
+
+/// 重置用户的两步验证（用户丢失设备且没有恢复码时）
+pub async fn put_reset_user_2fa(
+    pool: web::Data<MySqlPool>,
+    user_id: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = admin_claims(&req)?;
+    mfa_service::admin_reset_totp(pool.get_ref(), user_id.into_inner(), &claims).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "message": "Two-factor authentication reset" })))
+}
+
+/// 查看用户的管理权限
+pub async fn get_user_permissions(pool: web::Data<MySqlPool>, user_id: web::Path<i32>) -> Result<impl Responder, AppError> {
+    let permissions = admin_service::get_user_permissions(pool.get_ref(), user_id.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(permissions))
+}
+
+/// 授予或收回管理权限（仅超级管理员）
+pub async fn put_user_permissions(
+    pool: web::Data<MySqlPool>,
+    user_id: web::Path<i32>,
+    dto: web::Json<UpdateAdminPermissionsDto>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = admin_claims(&req)?;
+    let permissions = admin_service::set_user_permissions(pool.get_ref(), user_id.into_inner(), dto.into_inner(), &claims).await?;
+    Ok(HttpResponse::Ok().json(permissions))
+}
+
+pub async fn get_open_disputes(pool: web::Data<MySqlPool>) -> Result<impl Responder, AppError> {
+    let disputes = dispute_service::list_open_disputes(pool.get_ref()).await?;
+    Ok(HttpResponse::Ok().json(disputes))
+}
+
+pub async fn put_resolve_dispute(
+    pool: web::Data<MySqlPool>,
+    chat_server: web::Data<Addr<ChatServer>>,
+    dispute_id: web::Path<i32>,
+    dto: web::Json<ResolveDisputeDto>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = admin_claims(&req)?;
+    dispute_service::resolve_dispute(pool.get_ref(), chat_server.get_ref(), dispute_id.into_inner(), dto.into_inner(), &claims).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "message": "Dispute resolved successfully" })))
+}
+
+pub async fn get_fx_rates(
+    pool: web::Data<MySqlPool>,
+    query: web::Query<FxRateQuery>,
+) -> Result<impl Responder, AppError> {
+    let rates = fx_service::list_rates(pool.get_ref(), query.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(rates))
+}
+
+pub async fn post_fx_rate(
+    pool: web::Data<MySqlPool>,
+    dto: web::Json<FxRateDto>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = admin_claims(&req)?;
+    fx_service::upsert_rate(pool.get_ref(), dto.into_inner(), &claims).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "message": "Exchange rate saved successfully" })))
+}
+
+/// 导入汇率CSV，请求体为CSV文本
+/// POST /api/admin/fx-rates/import
+pub async fn post_import_fx_rates(
+    pool: web::Data<MySqlPool>,
+    body: String,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = admin_claims(&req)?;
+    let imported = fx_service::import_rates(pool.get_ref(), &body, &claims).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "imported": imported })))
+}
+
+pub async fn post_capability(
+    pool: web::Data<MySqlPool>,
+    dto: web::Json<CapabilityDto>,
+) -> Result<impl Responder, AppError> {
+    let capability = capability_service::create_capability(pool.get_ref(), dto.into_inner()).await?;
+    Ok(HttpResponse::Created().json(capability))
+}
+
+pub async fn put_capability(
+    pool: web::Data<MySqlPool>,
+    capability_id: web::Path<i32>,
+    dto: web::Json<CapabilityDto>,
+) -> Result<impl Responder, AppError> {
+    let capability = capability_service::update_capability(pool.get_ref(), capability_id.into_inner(), dto.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(capability))
+}
+
+pub async fn delete_capability(
+    pool: web::Data<MySqlPool>,
+    capability_id: web::Path<i32>,
+) -> Result<impl Responder, AppError> {
+    capability_service::delete_capability(pool.get_ref(), capability_id.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "message": "Capability deleted" })))
+}
+
+/// 查询全平台审计日志，可按操作者、公司、操作、对象和日期筛选
+/// GET /api/admin/audit-logs?actor_user_id=&company_id=&action=&entity_type=&entity_id=&from=&to=
+pub async fn get_audit_logs(
+    pool: web::Data<MySqlPool>,
+    query: web::Query<AuditLogQuery>,
+) -> Result<impl Responder, AppError> {
+    let logs = audit_service::list_audit_logs(pool.get_ref(), query.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(logs))
+}
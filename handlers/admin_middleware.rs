@@ -1,13 +1,94 @@
 // src/handlers/admin_middleware.rs
-use actix_web::{dev::{Service, ServiceRequest, ServiceResponse, Transform}, Error, HttpMessage, HttpResponse};
+use crate::{errors::AppError, models::user::Claims, services::admin_service};
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    web, Error, HttpMessage,
+};
 use futures::future::{ok, Ready};
-use crate::{errors::AppError, models::user::Claims};
-//use std::{future::{Future, ReadyOrNot}, pin::Pin};
+use sqlx::MySqlPool;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
 
-pub struct AdminAuth;
+/// 管理后台的中间件，必须放在 Auth 中间件之内使用。
+/// 每次请求都从数据库重新读取管理员身份和权限，撤销后立即生效
+pub struct AdminAuth {
+    // None 表示只有超级管理员 (is_admin) 可以访问
+    permission: Option<&'static str>,
+}
 
-// ... (这里需要完整的中间件实现代码，为了简洁，我们直接展示最终版本)
-// ... (它会复用Auth中间件的逻辑，但增加一步 is_admin 的检查)
+impl AdminAuth {
+    /// 只允许超级管理员访问
+    pub fn super_admin() -> Self {
+        AdminAuth { permission: None }
+    }
 
-// 为了简化，我们直接在 handler 中检查，但中间件是更专业的做法。
-// 我们先继续，如果需要，再回头实现完整的中间件。
\ No newline at end of file
+    /// 超级管理员或拥有该权限的运营人员可以访问
+    pub fn permission(permission: &'static str) -> Self {
+        AdminAuth { permission: Some(permission) }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for AdminAuth
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = AdminAuthMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(AdminAuthMiddleware { service: Rc::new(service), permission: self.permission })
+    }
+}
+
+pub struct AdminAuthMiddleware<S> {
+    service: Rc<S>,
+    permission: Option<&'static str>,
+}
+
+impl<S, B> Service<ServiceRequest> for AdminAuthMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    actix_web::dev::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let claims = req.extensions().get::<Claims>().cloned();
+        let pool = req.app_data::<web::Data<MySqlPool>>().cloned();
+        let service = Rc::clone(&self.service);
+        let permission = self.permission;
+
+        Box::pin(async move {
+            let (Some(mut claims), Some(pool)) = (claims, pool) else {
+                return Err(AppError::AuthError.into());
+            };
+            // API密钥不能访问管理后台
+            if claims.api_key_scopes.is_some() {
+                return Err(AppError::BadRequest("Administrator privileges required.".to_string()).into());
+            }
+
+            let (is_admin, permissions) = admin_service::load_admin_access(pool.get_ref(), claims.sub).await?;
+            let allowed = is_admin || permission.is_some_and(|p| permissions.iter().any(|granted| granted == p));
+            if !allowed {
+                return Err(AppError::BadRequest("Administrator privileges required.".to_string()).into());
+            }
+
+            // 以数据库中的身份为准，令牌签发后被取消的管理员不再被当作管理员
+            claims.is_admin = is_admin;
+            req.extensions_mut().insert(claims);
+
+            service.call(req).await
+        })
+    }
+}
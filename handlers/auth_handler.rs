@@ -1,7 +1,8 @@
 use crate::errors::AppError;
-use crate::models::user::{LoginDto, LoginResponse, RegisterDto};
-use crate::services::auth_service;
-use actix_web::{web, HttpResponse, Responder};
+use crate::models::invitation::AcceptInvitationDto;
+use crate::models::user::{ForgotPasswordDto, LoginDto, MfaLoginDto, RefreshTokenDto, RegisterDto, ResetPasswordDto, VerifyEmailDto};
+use crate::services::{auth_service, invitation_service, mfa_service};
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
 use sqlx::MySqlPool;
 
 /// 处理注册请求的API端点
@@ -13,11 +14,78 @@ pub async fn register(
     Ok(HttpResponse::Created().json("Registration successful"))
 }
 
-/// 处理登录请求的API端点
+/// 处理登录请求的API端点。开启了两步验证的用户会收到 mfa_token，需要再调用 /login/2fa
 pub async fn login(
     pool: web::Data<MySqlPool>,
     dto: web::Json<LoginDto>,
+    req: HttpRequest,
 ) -> Result<impl Responder, AppError> {
-    let token = auth_service::login_user(pool.get_ref(), dto.into_inner()).await?;
-    Ok(HttpResponse::Ok().json(LoginResponse::new(token)))
-}
\ No newline at end of file
+    let ip_address = req.connection_info().realip_remote_addr().unwrap_or("unknown").to_string();
+    let response = auth_service::login_user(pool.get_ref(), dto.into_inner(), &ip_address).await?;
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// 登录第二步：提交验证码或恢复码
+pub async fn login_mfa(
+    pool: web::Data<MySqlPool>,
+    dto: web::Json<MfaLoginDto>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let ip_address = req.connection_info().realip_remote_addr().unwrap_or("unknown").to_string();
+    let response = mfa_service::complete_login(pool.get_ref(), dto.into_inner(), &ip_address).await?;
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// 用刷新令牌换取新的令牌对
+pub async fn refresh(
+    pool: web::Data<MySqlPool>,
+    dto: web::Json<RefreshTokenDto>,
+) -> Result<impl Responder, AppError> {
+    let response = auth_service::refresh_session(pool.get_ref(), dto.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// 申请重置密码，向邮箱发送重置链接
+pub async fn forgot_password(
+    pool: web::Data<MySqlPool>,
+    dto: web::Json<ForgotPasswordDto>,
+) -> Result<impl Responder, AppError> {
+    auth_service::request_password_reset(pool.get_ref(), dto.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "message": "If the email is registered, a reset link has been sent." })))
+}
+
+/// 使用重置链接中的令牌设置新密码
+pub async fn reset_password(
+    pool: web::Data<MySqlPool>,
+    dto: web::Json<ResetPasswordDto>,
+) -> Result<impl Responder, AppError> {
+    auth_service::reset_password(pool.get_ref(), dto.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "message": "Password has been reset. Please log in again." })))
+}
+
+/// 验证注册邮箱
+pub async fn verify_email(
+    pool: web::Data<MySqlPool>,
+    dto: web::Json<VerifyEmailDto>,
+) -> Result<impl Responder, AppError> {
+    auth_service::verify_email(pool.get_ref(), dto.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "message": "Email verified successfully" })))
+}
+
+/// 查看邀请详情，前端在接受邀请页面展示
+pub async fn get_invitation(
+    pool: web::Data<MySqlPool>,
+    token: web::Path<String>,
+) -> Result<impl Responder, AppError> {
+    let preview = invitation_service::get_invitation_preview(pool.get_ref(), &token.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(preview))
+}
+
+/// 接受邀请并直接登录
+pub async fn accept_invitation(
+    pool: web::Data<MySqlPool>,
+    dto: web::Json<AcceptInvitationDto>,
+) -> Result<impl Responder, AppError> {
+    let response = invitation_service::accept_invitation(pool.get_ref(), dto.into_inner()).await?;
+    Ok(HttpResponse::Created().json(response))
+}
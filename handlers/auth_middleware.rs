@@ -1,20 +1,22 @@
 // src/handlers/auth_middleware.rs
 
-use crate::{errors::AppError, utils::auth_utils};
+use crate::{errors::AppError, services::{api_key_service, auth_service}};
 use actix_web::{
     dev::{Service, ServiceRequest, ServiceResponse, Transform},
-    Error, HttpMessage,
+    web, Error, HttpMessage,
 };
 use futures::future::{ok, Ready};
+use sqlx::MySqlPool;
 use std::future::Future;
 use std::pin::Pin;
+use std::rc::Rc;
 
 // 中间件工厂
 pub struct Auth;
 
 impl<S, B> Transform<S, ServiceRequest> for Auth
 where
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
     S::Future: 'static,
     B: 'static,
 {
@@ -25,18 +27,18 @@ where
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
-        ok(AuthMiddleware { service })
+        ok(AuthMiddleware { service: Rc::new(service) })
     }
 }
 
 // 中间件服务
 pub struct AuthMiddleware<S> {
-    service: S,
+    service: Rc<S>,
 }
 
 impl<S, B> Service<ServiceRequest> for AuthMiddleware<S>
 where
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
     S::Future: 'static,
     B: 'static,
 {
@@ -47,46 +49,56 @@ where
     actix_web::dev::forward_ready!(service);
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
-        // 从请求头中提取 "Authorization"
-        let auth_header = req.headers().get("Authorization");
+        // 系统集成使用 "X-API-Key" 请求头，用户使用 "Authorization: Bearer <token>"
+        let api_key = req
+            .headers()
+            .get("X-API-Key")
+            .and_then(|header_value| header_value.to_str().ok())
+            .map(|key| key.trim().to_string());
+        let token = req
+            .headers()
+            .get("Authorization")
+            .and_then(|header_value| header_value.to_str().ok())
+            .and_then(|auth_str| auth_str.strip_prefix("Bearer "))
+            .map(|token| token.to_string());
+        let pool = req.app_data::<web::Data<MySqlPool>>().cloned();
+        let ip_address = req.connection_info().realip_remote_addr().map(str::to_string);
+        let service = Rc::clone(&self.service);
 
-        // 验证token
-        match auth_header {
-            Some(header_value) => {
-                // 期望的格式是 "Bearer <token>"
-                if let Ok(auth_str) = header_value.to_str() {
-                    if auth_str.starts_with("Bearer ") {
-                        let token = &auth_str[7..];
-                        match auth_utils::validate_jwt(token) {
-                            Ok(claims) => {
-                                // 验证成功，将用户信息 (claims) 存入请求的扩展中，方便后续处理器使用
-                                req.extensions_mut().insert(claims);
-                            }
-                            Err(_) => {
-                                // token无效或过期
-                                let err = AppError::AuthError;
-                                return Box::pin(async move { Err(err.into()) });
-                            }
+        Box::pin(async move {
+            let Some(pool) = pool else {
+                return Err(AppError::AuthError.into());
+            };
+
+            let mut claims = match (api_key, token) {
+                (Some(api_key), _) => {
+                    let claims = api_key_service::authenticate_api_key(pool.get_ref(), &api_key).await?;
+                    // API密钥只能访问其权限范围内的资源
+                    let scopes = claims.api_key_scopes.as_deref().unwrap_or_default();
+                    match api_key_service::required_scope(req.method().as_str(), req.path()) {
+                        Some(required) if api_key_service::has_scope(scopes, &required) => {}
+                        Some(required) => {
+                            return Err(AppError::BadRequest(format!("This API key is missing the '{}' scope.", required)).into());
+                        }
+                        None => {
+                            return Err(AppError::BadRequest("This endpoint is not available to API keys.".to_string()).into());
                         }
-                    } else {
-                        // Header格式不正确
-                        let err = AppError::AuthError;
-                        return Box::pin(async move { Err(err.into()) });
                     }
+                    claims
                 }
-            }
-            None => {
-                // 缺少 Authorization header
-                let err = AppError::AuthError;
-                return Box::pin(async move { Err(err.into()) });
-            }
-        }
+                // 验证token，并确认会话有效、用户修改密码后旧令牌已失效
+                (None, Some(token)) => auth_service::authenticate(pool.get_ref(), &token).await?,
+                // 缺少认证信息或格式不正确
+                (None, None) => return Err(AppError::AuthError.into()),
+            };
 
-        // 将请求传递给下一个服务
-        let fut = self.service.call(req);
-        Box::pin(async move {
-            let res = fut.await?;
-            Ok(res)
+            claims.ip_address = ip_address;
+
+            // 验证成功，将用户信息 (claims) 存入请求的扩展中，方便后续处理器使用
+            req.extensions_mut().insert(claims);
+
+            // 将请求传递给下一个服务
+            service.call(req).await
         })
     }
-}
\ No newline at end of file
+}
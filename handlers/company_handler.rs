@@ -2,8 +2,8 @@
 
 use crate::{
     errors::AppError,
-    models::{company::UpdateCompanyDto, user::Claims},
-    services::company_service,
+    models::{api_key::CreateApiKeyDto, audit::AuditLogQuery, company::UpdateCompanyDto, invitation::CreateInvitationDto, user::{Claims, UpdateRoleDto}},
+    services::{api_key_service, audit_service, company_service, invitation_service},
 };
 use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Responder};
 use sqlx::MySqlPool;
@@ -26,4 +26,114 @@ pub async fn update_profile(
     let id = company_id.into_inner();
     company_service::update_company_profile(pool.get_ref(), id, dto.into_inner(), &claims).await?;
     Ok(HttpResponse::Ok().json(serde_json::json!({ "message": "Profile updated successfully" })))
-}
\ No newline at end of file
+}
+
+pub async fn get_members(
+    pool: web::Data<MySqlPool>,
+    company_id: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let members = company_service::list_members(pool.get_ref(), company_id.into_inner(), &claims).await?;
+    Ok(HttpResponse::Ok().json(members))
+}
+
+pub async fn update_member_role(
+    pool: web::Data<MySqlPool>,
+    path: web::Path<(i32, i32)>,
+    dto: web::Json<UpdateRoleDto>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let (company_id, user_id) = path.into_inner();
+    company_service::update_member_role(pool.get_ref(), company_id, user_id, dto.into_inner(), &claims).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "message": "Role updated successfully" })))
+}
+
+pub async fn post_invitation(
+    pool: web::Data<MySqlPool>,
+    company_id: web::Path<i32>,
+    dto: web::Json<CreateInvitationDto>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let invitation_id = invitation_service::create_invitation(pool.get_ref(), company_id.into_inner(), dto.into_inner(), &claims).await?;
+    Ok(HttpResponse::Created().json(serde_json::json!({ "invitation_id": invitation_id })))
+}
+
+pub async fn get_invitations(
+    pool: web::Data<MySqlPool>,
+    company_id: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let invitations = invitation_service::list_invitations(pool.get_ref(), company_id.into_inner(), &claims).await?;
+    Ok(HttpResponse::Ok().json(invitations))
+}
+
+pub async fn delete_invitation(
+    pool: web::Data<MySqlPool>,
+    path: web::Path<(i32, i32)>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let (company_id, invitation_id) = path.into_inner();
+    invitation_service::revoke_invitation(pool.get_ref(), company_id, invitation_id, &claims).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "message": "Invitation revoked" })))
+}
+
+pub async fn post_resend_invitation(
+    pool: web::Data<MySqlPool>,
+    path: web::Path<(i32, i32)>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let (company_id, invitation_id) = path.into_inner();
+    invitation_service::resend_invitation(pool.get_ref(), company_id, invitation_id, &claims).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "message": "Invitation sent" })))
+}
+
+/// 创建API密钥，响应中的完整密钥只返回这一次
+pub async fn post_api_key(
+    pool: web::Data<MySqlPool>,
+    company_id: web::Path<i32>,
+    dto: web::Json<CreateApiKeyDto>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let created = api_key_service::create_api_key(pool.get_ref(), company_id.into_inner(), dto.into_inner(), &claims).await?;
+    Ok(HttpResponse::Created().json(created))
+}
+
+pub async fn get_api_keys(
+    pool: web::Data<MySqlPool>,
+    company_id: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let keys = api_key_service::list_api_keys(pool.get_ref(), company_id.into_inner(), &claims).await?;
+    Ok(HttpResponse::Ok().json(keys))
+}
+
+pub async fn delete_api_key(
+    pool: web::Data<MySqlPool>,
+    path: web::Path<(i32, i32)>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let (company_id, key_id) = path.into_inner();
+    api_key_service::revoke_api_key(pool.get_ref(), company_id, key_id, &claims).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "message": "API key revoked" })))
+}
+
+/// 公司所有者查看本公司的审计日志
+pub async fn get_audit_logs(
+    pool: web::Data<MySqlPool>,
+    company_id: web::Path<i32>,
+    query: web::Query<AuditLogQuery>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let logs = audit_service::list_company_audit_logs(pool.get_ref(), company_id.into_inner(), query.into_inner(), &claims).await?;
+    Ok(HttpResponse::Ok().json(logs))
+}
@@ -0,0 +1,164 @@
+// src/handlers/dispute_handler.rs
+
+use crate::{
+    errors::AppError,
+    models::{
+        dispute::{CreateRmaDto, DisputeMessageDto, OpenDisputeDto, RmaDecisionDto},
+        user::Claims,
+    },
+    services::{chat_server::ChatServer, dispute_service, rma_service},
+};
+use actix::Addr;
+use actix_files::NamedFile;
+use actix_multipart::Multipart;
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Responder};
+use sqlx::MySqlPool;
+
+/// 采购方对订单发起争议
+/// POST /api/orders/{order_id}/disputes
+pub async fn post_dispute(
+    pool: web::Data<MySqlPool>,
+    chat_server: web::Data<Addr<ChatServer>>,
+    order_id: web::Path<i32>,
+    dto: web::Json<OpenDisputeDto>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let dispute_id = dispute_service::open_dispute(pool.get_ref(), chat_server.get_ref(), order_id.into_inner(), dto.into_inner(), &claims).await?;
+    Ok(HttpResponse::Created().json(serde_json::json!({ "dispute_id": dispute_id })))
+}
+
+/// GET /api/orders/{order_id}/disputes
+pub async fn get_disputes(
+    pool: web::Data<MySqlPool>,
+    order_id: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let disputes = dispute_service::get_disputes_for_order(pool.get_ref(), order_id.into_inner(), &claims).await?;
+    Ok(HttpResponse::Ok().json(disputes))
+}
+
+/// POST /api/orders/{order_id}/disputes/{dispute_id}/messages
+pub async fn post_dispute_message(
+    pool: web::Data<MySqlPool>,
+    path: web::Path<(i32, i32)>,
+    dto: web::Json<DisputeMessageDto>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let (order_id, dispute_id) = path.into_inner();
+    let message_id = dispute_service::post_dispute_message(pool.get_ref(), order_id, dispute_id, dto.into_inner(), &claims).await?;
+    Ok(HttpResponse::Created().json(serde_json::json!({ "message_id": message_id })))
+}
+
+/// GET /api/orders/{order_id}/disputes/{dispute_id}/messages
+pub async fn get_dispute_messages(
+    pool: web::Data<MySqlPool>,
+    path: web::Path<(i32, i32)>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let (order_id, dispute_id) = path.into_inner();
+    let messages = dispute_service::get_dispute_messages(pool.get_ref(), order_id, dispute_id, &claims).await?;
+    Ok(HttpResponse::Ok().json(messages))
+}
+
+/// POST /api/orders/{order_id}/disputes/{dispute_id}/evidence
+pub async fn post_dispute_evidence(
+    pool: web::Data<MySqlPool>,
+    path: web::Path<(i32, i32)>,
+    payload: Multipart,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let (order_id, dispute_id) = path.into_inner();
+    let ids = dispute_service::upload_dispute_evidence(pool.get_ref(), order_id, dispute_id, &claims, payload).await?;
+    Ok(HttpResponse::Created().json(serde_json::json!({ "evidence_ids": ids })))
+}
+
+/// GET /api/orders/{order_id}/disputes/{dispute_id}/evidence
+pub async fn get_dispute_evidence(
+    pool: web::Data<MySqlPool>,
+    path: web::Path<(i32, i32)>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let (order_id, dispute_id) = path.into_inner();
+    let evidence = dispute_service::get_dispute_evidence(pool.get_ref(), order_id, dispute_id, &claims).await?;
+    Ok(HttpResponse::Ok().json(evidence))
+}
+
+/// GET /api/orders/{order_id}/disputes/{dispute_id}/evidence/{evidence_id}
+pub async fn download_dispute_evidence(
+    pool: web::Data<MySqlPool>,
+    path: web::Path<(i32, i32, i32)>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let (order_id, dispute_id, evidence_id) = path.into_inner();
+    let evidence = dispute_service::get_evidence_file(pool.get_ref(), order_id, dispute_id, evidence_id, &claims).await?;
+
+    let file = NamedFile::open_async(&evidence.stored_path)
+        .await?
+        .set_content_disposition(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(evidence.original_filename)],
+        });
+    Ok(file)
+}
+
+/// 采购方申请退货
+/// POST /api/orders/{order_id}/rmas
+pub async fn post_rma(
+    pool: web::Data<MySqlPool>,
+    chat_server: web::Data<Addr<ChatServer>>,
+    order_id: web::Path<i32>,
+    dto: web::Json<CreateRmaDto>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let rma_id = rma_service::create_rma(pool.get_ref(), chat_server.get_ref(), order_id.into_inner(), dto.into_inner(), &claims).await?;
+    Ok(HttpResponse::Created().json(serde_json::json!({ "rma_id": rma_id })))
+}
+
+/// GET /api/orders/{order_id}/rmas
+pub async fn get_rmas(
+    pool: web::Data<MySqlPool>,
+    order_id: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let rmas = rma_service::get_rmas_for_order(pool.get_ref(), order_id.into_inner(), &claims).await?;
+    Ok(HttpResponse::Ok().json(rmas))
+}
+
+/// 供应商对退货申请作出决定
+/// PUT /api/orders/{order_id}/rmas/{rma_id}/decision
+pub async fn put_rma_decision(
+    pool: web::Data<MySqlPool>,
+    chat_server: web::Data<Addr<ChatServer>>,
+    path: web::Path<(i32, i32)>,
+    dto: web::Json<RmaDecisionDto>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let (order_id, rma_id) = path.into_inner();
+    rma_service::decide_rma(pool.get_ref(), chat_server.get_ref(), order_id, rma_id, dto.into_inner(), &claims).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "message": "RMA decision recorded" })))
+}
+
+/// 供应商关闭已处理完成的退货
+/// PUT /api/orders/{order_id}/rmas/{rma_id}/close
+pub async fn put_close_rma(
+    pool: web::Data<MySqlPool>,
+    chat_server: web::Data<Addr<ChatServer>>,
+    path: web::Path<(i32, i32)>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let (order_id, rma_id) = path.into_inner();
+    rma_service::close_rma(pool.get_ref(), chat_server.get_ref(), order_id, rma_id, &claims).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "message": "RMA closed" })))
+}
@@ -0,0 +1,58 @@
+// src/handlers/invoice_handler.rs
+
+use crate::{
+    errors::AppError,
+    models::{invoice::{CreateInvoiceDto, InvoiceQuery}, user::Claims},
+    services::{chat_server::ChatServer, invoice_service},
+};
+use actix::Addr;
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Responder};
+use sqlx::MySqlPool;
+
+/// 供应商为订单开具发票
+/// POST /api/orders/{order_id}/invoices
+pub async fn post_invoice(
+    pool: web::Data<MySqlPool>,
+    chat_server: web::Data<Addr<ChatServer>>,
+    order_id: web::Path<i32>,
+    dto: web::Json<CreateInvoiceDto>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let invoice_id = invoice_service::create_invoice(pool.get_ref(), chat_server.get_ref(), order_id.into_inner(), dto.into_inner(), &claims).await?;
+    Ok(HttpResponse::Created().json(serde_json::json!({ "invoice_id": invoice_id })))
+}
+
+/// GET /api/orders/{order_id}/invoices
+pub async fn get_order_invoices(
+    pool: web::Data<MySqlPool>,
+    order_id: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let invoices = invoice_service::get_invoices_for_order(pool.get_ref(), order_id.into_inner(), &claims).await?;
+    Ok(HttpResponse::Ok().json(invoices))
+}
+
+/// 当前公司的应收/应付发票，可按状态过滤
+/// GET /api/invoices?status=OVERDUE
+pub async fn get_invoices(
+    pool: web::Data<MySqlPool>,
+    query: web::Query<InvoiceQuery>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let invoices = invoice_service::get_invoices_for_company(pool.get_ref(), &claims, query.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(invoices))
+}
+
+/// GET /api/invoices/{invoice_id}
+pub async fn get_invoice(
+    pool: web::Data<MySqlPool>,
+    invoice_id: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let invoice = invoice_service::get_invoice(pool.get_ref(), invoice_id.into_inner(), &claims).await?;
+    Ok(HttpResponse::Ok().json(invoice))
+}
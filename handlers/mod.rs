@@ -9,9 +9,12 @@ pub(crate) mod company_handler;
 pub mod user_handler;
 pub(crate) mod analytics_handler;
 pub mod payment_handler;
-mod admin_middleware;
+pub mod admin_middleware;
 pub mod admin_handler;
 pub mod capability_handler;
 pub mod notification_handler;
 pub mod ws_handler;
+pub mod shipment_handler;
+pub mod dispute_handler;
+pub mod invoice_handler;
 // 新增
\ No newline at end of file
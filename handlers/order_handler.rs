@@ -2,28 +2,223 @@
 
 use crate::{
     errors::AppError,
-    models::{order::UpdateOrderStatusDto, user::Claims},
-    services::order_service,
+    models::{order::{CancellationRequestDto, ChangeRequestDto, ConfirmOrderDto, DeclineOrderDto, RespondCancellationDto, RespondChangeRequestDto, OrderFilterParams, UpdateOrderStatusDto}, user::Claims},
+    services::{change_order_service, chat_server::ChatServer, document_service, order_service},
 };
+use actix::Addr;
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
 use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Responder};
 use sqlx::MySqlPool;
 
 pub async fn get_orders(
     pool: web::Data<MySqlPool>,
+    params: web::Query<OrderFilterParams>,
     req: HttpRequest,
 ) -> Result<impl Responder, AppError> {
     let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
-    let orders = order_service::get_orders_for_user(pool.get_ref(), &claims).await?;
+    let orders = order_service::get_orders_for_user(pool.get_ref(), &claims, params.into_inner()).await?;
     Ok(HttpResponse::Ok().json(orders))
 }
 
+/// 订单详情
+/// GET /api/orders/{order_id}
+pub async fn get_order_detail(
+    pool: web::Data<MySqlPool>,
+    order_id: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let detail = order_service::get_order_detail(pool.get_ref(), order_id.into_inner(), &claims).await?;
+    Ok(HttpResponse::Ok().json(detail))
+}
+
 pub async fn patch_order_status(
     pool: web::Data<MySqlPool>,
+    chat_server: web::Data<Addr<ChatServer>>,
     order_id: web::Path<i32>,
     dto: web::Json<UpdateOrderStatusDto>,
     req: HttpRequest,
 ) -> Result<impl Responder, AppError> {
     let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
-    order_service::update_order_status(pool.get_ref(), order_id.into_inner(), dto.into_inner(), &claims).await?;
+    order_service::update_order_status(pool.get_ref(), chat_server.get_ref(), order_id.into_inner(), dto.into_inner(), &claims).await?;
     Ok(HttpResponse::Ok().json(serde_json::json!({ "message": "Order status updated successfully" })))
-}
\ No newline at end of file
+}
+
+pub async fn get_order_history(
+    pool: web::Data<MySqlPool>,
+    order_id: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let history = order_service::get_status_history(pool.get_ref(), order_id.into_inner(), &claims).await?;
+    Ok(HttpResponse::Ok().json(history))
+}
+
+/// 供应商确认订单并提交承诺发货日期
+/// POST /api/orders/{order_id}/confirm
+pub async fn post_confirm_order(
+    pool: web::Data<MySqlPool>,
+    chat_server: web::Data<Addr<ChatServer>>,
+    order_id: web::Path<i32>,
+    dto: web::Json<ConfirmOrderDto>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    order_service::confirm_order(pool.get_ref(), chat_server.get_ref(), order_id.into_inner(), dto.into_inner(), &claims).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "message": "Order confirmed successfully" })))
+}
+
+/// 供应商拒绝订单，RFQ 将重新开放
+/// POST /api/orders/{order_id}/decline
+pub async fn post_decline_order(
+    pool: web::Data<MySqlPool>,
+    chat_server: web::Data<Addr<ChatServer>>,
+    order_id: web::Path<i32>,
+    dto: web::Json<DeclineOrderDto>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    order_service::decline_order(pool.get_ref(), chat_server.get_ref(), order_id.into_inner(), dto.into_inner(), &claims).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "message": "Order declined" })))
+}
+
+/// 订单一方发起取消申请
+/// POST /api/orders/{order_id}/cancellation-requests
+pub async fn post_cancellation_request(
+    pool: web::Data<MySqlPool>,
+    chat_server: web::Data<Addr<ChatServer>>,
+    order_id: web::Path<i32>,
+    dto: web::Json<CancellationRequestDto>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let request_id = order_service::request_cancellation(pool.get_ref(), chat_server.get_ref(), order_id.into_inner(), dto.into_inner(), &claims).await?;
+    Ok(HttpResponse::Created().json(serde_json::json!({ "cancellation_request_id": request_id })))
+}
+
+/// 获取订单的取消申请记录
+/// GET /api/orders/{order_id}/cancellation-requests
+pub async fn get_cancellation_requests(
+    pool: web::Data<MySqlPool>,
+    order_id: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let requests = order_service::get_cancellation_requests(pool.get_ref(), order_id.into_inner(), &claims).await?;
+    Ok(HttpResponse::Ok().json(requests))
+}
+
+/// 对方同意或拒绝取消申请
+/// POST /api/orders/{order_id}/cancellation-requests/{request_id}/respond
+pub async fn post_respond_cancellation(
+    pool: web::Data<MySqlPool>,
+    chat_server: web::Data<Addr<ChatServer>>,
+    path: web::Path<(i32, i32)>,
+    dto: web::Json<RespondCancellationDto>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let (order_id, request_id) = path.into_inner();
+    order_service::respond_to_cancellation(pool.get_ref(), chat_server.get_ref(), order_id, request_id, dto.into_inner(), &claims).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "message": "Cancellation response recorded" })))
+}
+
+/// 订单一方提出变更单（数量、单价、交期）
+/// POST /api/orders/{order_id}/change-orders
+pub async fn post_change_order(
+    pool: web::Data<MySqlPool>,
+    chat_server: web::Data<Addr<ChatServer>>,
+    order_id: web::Path<i32>,
+    dto: web::Json<ChangeRequestDto>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let change_request_id = change_order_service::request_change(pool.get_ref(), chat_server.get_ref(), order_id.into_inner(), dto.into_inner(), &claims).await?;
+    Ok(HttpResponse::Created().json(serde_json::json!({ "change_request_id": change_request_id })))
+}
+
+/// 获取订单的变更单记录
+/// GET /api/orders/{order_id}/change-orders
+pub async fn get_change_orders(
+    pool: web::Data<MySqlPool>,
+    order_id: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let requests = change_order_service::get_change_requests(pool.get_ref(), order_id.into_inner(), &claims).await?;
+    Ok(HttpResponse::Ok().json(requests))
+}
+
+/// 对方批准或拒绝变更单
+/// POST /api/orders/{order_id}/change-orders/{change_request_id}/respond
+pub async fn post_respond_change_order(
+    pool: web::Data<MySqlPool>,
+    chat_server: web::Data<Addr<ChatServer>>,
+    path: web::Path<(i32, i32)>,
+    dto: web::Json<RespondChangeRequestDto>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let (order_id, change_request_id) = path.into_inner();
+    change_order_service::respond_to_change(pool.get_ref(), chat_server.get_ref(), order_id, change_request_id, dto.into_inner(), &claims).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "message": "Change order response recorded" })))
+}
+
+/// 获取订单的版本历史
+/// GET /api/orders/{order_id}/versions
+pub async fn get_order_versions(
+    pool: web::Data<MySqlPool>,
+    order_id: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let versions = change_order_service::get_order_versions(pool.get_ref(), order_id.into_inner(), &claims).await?;
+    Ok(HttpResponse::Ok().json(versions))
+}
+
+/// 列出订单可下载的文档
+/// GET /api/orders/{order_id}/documents
+pub async fn get_order_documents(
+    pool: web::Data<MySqlPool>,
+    order_id: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let documents = document_service::list_order_documents(pool.get_ref(), order_id.into_inner(), &claims).await?;
+    Ok(HttpResponse::Ok().json(documents))
+}
+
+/// 下载采购订单PDF
+/// GET /api/orders/{order_id}/documents/purchase-order
+pub async fn download_purchase_order(
+    pool: web::Data<MySqlPool>,
+    order_id: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let (filename, bytes) = document_service::render_purchase_order_document(pool.get_ref(), order_id.into_inner(), &claims).await?;
+    Ok(pdf_response(filename, bytes))
+}
+
+/// 下载发票PDF
+/// GET /api/orders/{order_id}/documents/invoices/{invoice_id}
+pub async fn download_invoice(
+    pool: web::Data<MySqlPool>,
+    path: web::Path<(i32, i32)>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let (order_id, invoice_id) = path.into_inner();
+    let (filename, bytes) = document_service::render_invoice_document(pool.get_ref(), order_id, invoice_id, &claims).await?;
+    Ok(pdf_response(filename, bytes))
+}
+
+fn pdf_response(filename: String, bytes: Vec<u8>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("application/pdf")
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(filename)],
+        })
+        .body(bytes)
+}
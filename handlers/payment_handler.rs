@@ -4,50 +4,83 @@ use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Responder};
 use sqlx::MySqlPool;
 use crate::{
     errors::AppError,
-    models::{user::Claims, payment::CheckoutSessionResponse},
-    services::payment_service,
+    models::{user::Claims, payment::{CheckoutSessionResponse, CreateRefundDto}},
+    services::{chat_server::ChatServer, payment_service, payment_provider::PaymentProvider},
 };
-use std::env;
-use stripe::{EventObject, EventType, Webhook};
+use actix::Addr;
 
-// create_session 函数保持不变
 pub async fn create_session(
     pool: web::Data<MySqlPool>,
+    provider: web::Data<dyn PaymentProvider>,
     order_id: web::Path<i32>,
     req: HttpRequest,
 ) -> Result<impl Responder, AppError> {
     let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
-    let session_id = payment_service::create_checkout_session(pool.get_ref(), order_id.into_inner(), &claims).await?;
-    Ok(HttpResponse::Ok().json(CheckoutSessionResponse { session_id }))
+    let session = payment_service::create_checkout_session(pool.get_ref(), provider.get_ref(), order_id.into_inner(), &claims).await?;
+    Ok(HttpResponse::Ok().json(CheckoutSessionResponse { session_id: session.id, checkout_url: session.url }))
 }
 
-// 【关键修复】使用 async-stripe 的API处理Webhook
+/// 为发票创建结账会话
+/// POST /api/invoices/{invoice_id}/checkout-session
+pub async fn create_invoice_session(
+    pool: web::Data<MySqlPool>,
+    provider: web::Data<dyn PaymentProvider>,
+    invoice_id: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let session = payment_service::create_invoice_checkout_session(pool.get_ref(), provider.get_ref(), invoice_id.into_inner(), &claims).await?;
+    Ok(HttpResponse::Ok().json(CheckoutSessionResponse { session_id: session.id, checkout_url: session.url }))
+}
+
+/// 处理支付渠道的Webhook，签名校验和事件解析由当前配置的支付渠道完成
+/// POST /api/payments/webhook
 pub async fn handle_webhook(
     pool: web::Data<MySqlPool>,
+    chat_server: web::Data<Addr<ChatServer>>,
+    provider: web::Data<dyn PaymentProvider>,
     payload: String,
     req: HttpRequest,
 ) -> Result<impl Responder, AppError> {
-    let signature = req.headers().get("Stripe-Signature").and_then(|h| h.to_str().ok()).unwrap_or_default();
-    let webhook_secret = env::var("STRIPE_WEBHOOK_SECRET").expect("STRIPE_WEBHOOK_SECRET must be set");
+    let signature = req.headers().get(provider.signature_header()).and_then(|h| h.to_str().ok()).unwrap_or_default();
+    let event = provider.verify_webhook(&payload, signature)?;
+    log::info!("Received payment event {} ({}) from {}", event.id, event.event_type, provider.name());
 
-    let event = Webhook::construct_event(&payload, signature, &webhook_secret)
-        .map_err(|e| AppError::BadRequest(format!("Invalid Stripe signature: {}", e)))?;
+    payment_service::process_webhook_event(pool.get_ref(), chat_server.get_ref(), provider.name(), &payload, event).await?;
 
-    // 检查事件类型
-    if event.type_ == EventType::CheckoutSessionCompleted {
-        // 使用 `if let` 模式匹配来安全地提取会话对象
-        if let EventObject::CheckoutSession(session) = event.data.object {
-            log::info!("Checkout session {} completed!", session.id);
-
-            let session_id = session.id.to_string();
+    Ok(HttpResponse::Ok())
+}
 
-            // 更新数据库
-            sqlx::query("UPDATE purchase_orders SET payment_status = 'PAID' WHERE stripe_session_id = ?")
-                .bind(session_id)
-                .execute(pool.get_ref())
-                .await?;
-        }
-    }
+/// 获取订单的付款计划、付款和退款记录
+/// GET /api/orders/{order_id}/payments
+pub async fn get_order_payments(
+    pool: web::Data<MySqlPool>,
+    order_id: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let summary = payment_service::get_order_payments(pool.get_ref(), order_id.into_inner(), &claims).await?;
+    Ok(HttpResponse::Ok().json(summary))
+}
 
-    Ok(HttpResponse::Ok())
-}
\ No newline at end of file
+/// 供应商退款
+/// POST /api/orders/{order_id}/refunds
+pub async fn post_refund(
+    pool: web::Data<MySqlPool>,
+    chat_server: web::Data<Addr<ChatServer>>,
+    provider: web::Data<dyn PaymentProvider>,
+    order_id: web::Path<i32>,
+    dto: web::Json<CreateRefundDto>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let refund_id = payment_service::refund_payment(
+        pool.get_ref(),
+        chat_server.get_ref(),
+        provider.get_ref(),
+        order_id.into_inner(),
+        dto.into_inner(),
+        &claims,
+    ).await?;
+    Ok(HttpResponse::Created().json(serde_json::json!({ "id": refund_id })))
+}
@@ -1,10 +1,13 @@
 use actix::Addr;
+use actix_files::NamedFile;
+use actix_multipart::Multipart;
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
 use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Responder};
 use sqlx::MySqlPool;
 
 use crate::{
     errors::AppError,
-    models::{quote::CreateQuoteDto, user::Claims},
+    models::{quote::{CounterOfferDto, CreateQuoteDto, RespondNegotiationDto}, user::Claims},
     services::{chat_server::ChatServer, quote_service},
 };
 
@@ -85,4 +88,105 @@ pub async fn post_accept_quote(
 
     // 返回 200 OK 和新创建的采购订单ID
     Ok(HttpResponse::Ok().json(serde_json::json!({ "purchase_order_id": po_id })))
-}
\ No newline at end of file
+}
+
+/// 采购方对某个报价发起还价
+/// POST /api/quotes/{quote_id}/negotiations
+pub async fn post_counter_offer(
+    pool: web::Data<MySqlPool>,
+    chat_server: web::Data<Addr<ChatServer>>,
+    quote_id: web::Path<i32>,
+    dto: web::Json<CounterOfferDto>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let round_id = quote_service::create_counter_offer(
+        pool.get_ref(),
+        chat_server.get_ref(),
+        quote_id.into_inner(),
+        dto.into_inner(),
+        &claims,
+    )
+        .await?;
+    Ok(HttpResponse::Created().json(serde_json::json!({ "negotiation_id": round_id })))
+}
+
+/// 获取某个报价的议价记录
+/// GET /api/quotes/{quote_id}/negotiations
+pub async fn get_negotiations(
+    pool: web::Data<MySqlPool>,
+    quote_id: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let rounds = quote_service::get_negotiations_for_quote(pool.get_ref(), quote_id.into_inner(), &claims).await?;
+    Ok(HttpResponse::Ok().json(rounds))
+}
+
+/// 回应一轮议价（接受 / 拒绝 / 再次还价）
+/// POST /api/quotes/{quote_id}/negotiations/{negotiation_id}/respond
+pub async fn post_respond_negotiation(
+    pool: web::Data<MySqlPool>,
+    chat_server: web::Data<Addr<ChatServer>>,
+    path: web::Path<(i32, i32)>,
+    dto: web::Json<RespondNegotiationDto>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let (quote_id, negotiation_id) = path.into_inner();
+    quote_service::respond_to_negotiation(
+        pool.get_ref(),
+        chat_server.get_ref(),
+        quote_id,
+        negotiation_id,
+        dto.into_inner(),
+        &claims,
+    )
+        .await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "message": "Negotiation response recorded" })))
+}
+
+/// 报价供应商上传报价附件
+/// POST /api/quotes/{quote_id}/attachments
+pub async fn post_quote_attachments(
+    pool: web::Data<MySqlPool>,
+    quote_id: web::Path<i32>,
+    payload: Multipart,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let ids = quote_service::upload_attachments_for_quote(pool.get_ref(), &claims, quote_id.into_inner(), payload).await?;
+    Ok(HttpResponse::Created().json(serde_json::json!({ "attachment_ids": ids })))
+}
+
+/// 获取报价附件列表
+/// GET /api/quotes/{quote_id}/attachments
+pub async fn get_quote_attachments(
+    pool: web::Data<MySqlPool>,
+    quote_id: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let attachments = quote_service::get_attachments_for_quote(pool.get_ref(), quote_id.into_inner(), &claims).await?;
+    Ok(HttpResponse::Ok().json(attachments))
+}
+
+/// 下载单个报价附件
+/// GET /api/quotes/{quote_id}/attachments/{attachment_id}
+pub async fn download_quote_attachment(
+    pool: web::Data<MySqlPool>,
+    path: web::Path<(i32, i32)>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let (quote_id, attachment_id) = path.into_inner();
+    let attachment = quote_service::get_quote_attachment(pool.get_ref(), quote_id, attachment_id, &claims).await?;
+
+    let file = NamedFile::open_async(&attachment.stored_path)
+        .await?
+        .set_content_disposition(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(attachment.original_filename)],
+        });
+    Ok(file)
+}
@@ -0,0 +1,94 @@
+// src/handlers/shipment_handler.rs
+
+use crate::{
+    errors::AppError,
+    models::{shipment::GoodsReceiptDto, user::Claims},
+    services::{chat_server::ChatServer, shipment_service},
+};
+use actix::Addr;
+use actix_files::NamedFile;
+use actix_multipart::Multipart;
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Responder};
+use sqlx::MySqlPool;
+
+/// 供应商登记一次发货（multipart：quantity_shipped, carrier, tracking_number, ship_date, packing_list）
+/// POST /api/orders/{order_id}/shipments
+pub async fn post_shipment(
+    pool: web::Data<MySqlPool>,
+    chat_server: web::Data<Addr<ChatServer>>,
+    order_id: web::Path<i32>,
+    payload: Multipart,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let shipment_id = shipment_service::create_shipment(
+        pool.get_ref(),
+        chat_server.get_ref(),
+        order_id.into_inner(),
+        &claims,
+        payload,
+    )
+        .await?;
+    Ok(HttpResponse::Created().json(serde_json::json!({ "shipment_id": shipment_id })))
+}
+
+/// 获取订单的发货记录
+/// GET /api/orders/{order_id}/shipments
+pub async fn get_shipments(
+    pool: web::Data<MySqlPool>,
+    order_id: web::Path<i32>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let shipments = shipment_service::get_shipments_for_order(pool.get_ref(), order_id.into_inner(), &claims).await?;
+    Ok(HttpResponse::Ok().json(shipments))
+}
+
+/// 下载某次发货的装箱单
+/// GET /api/orders/{order_id}/shipments/{shipment_id}/packing-list
+pub async fn download_packing_list(
+    pool: web::Data<MySqlPool>,
+    path: web::Path<(i32, i32)>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let (order_id, shipment_id) = path.into_inner();
+    let shipment = shipment_service::get_shipment(pool.get_ref(), order_id, shipment_id, &claims).await?;
+
+    let (filename, stored_path) = shipment
+        .packing_list_filename
+        .zip(shipment.packing_list_path)
+        .ok_or_else(|| AppError::BadRequest("This shipment has no packing list.".to_string()))?;
+
+    let file = NamedFile::open_async(&stored_path)
+        .await?
+        .set_content_disposition(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(filename)],
+        });
+    Ok(file)
+}
+
+/// 采购方对某次发货进行收货确认
+/// POST /api/orders/{order_id}/shipments/{shipment_id}/receipt
+pub async fn post_goods_receipt(
+    pool: web::Data<MySqlPool>,
+    chat_server: web::Data<Addr<ChatServer>>,
+    path: web::Path<(i32, i32)>,
+    dto: web::Json<GoodsReceiptDto>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let (order_id, shipment_id) = path.into_inner();
+    shipment_service::record_goods_receipt(
+        pool.get_ref(),
+        chat_server.get_ref(),
+        order_id,
+        shipment_id,
+        dto.into_inner(),
+        &claims,
+    )
+        .await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "message": "Goods receipt recorded" })))
+}
@@ -2,8 +2,8 @@
 
 use crate::{
     errors::AppError,
-    models::user::{ChangePasswordDto, Claims},
-    services::user_service,
+    models::user::{ChangePasswordDto, Claims, DisableTotpDto, RecoveryCodesResponse, TotpCodeDto},
+    services::{auth_service, mfa_service, user_service},
 };
 use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Responder};
 use sqlx::MySqlPool;
@@ -23,6 +23,77 @@ pub async fn update_password(
     req: HttpRequest,
 ) -> Result<impl Responder, AppError> {
     let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
-    user_service::change_password(pool.get_ref(), &claims, dto.into_inner()).await?;
-    Ok(HttpResponse::Ok().json(serde_json::json!({ "message": "Password updated successfully" })))
-}
\ No newline at end of file
+    let token = user_service::change_password(pool.get_ref(), &claims, dto.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "message": "Password updated successfully", "token": token })))
+}
+
+/// 重新发送邮箱验证链接
+pub async fn resend_verification(
+    pool: web::Data<MySqlPool>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    auth_service::resend_verification_email(pool.get_ref(), &claims).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "message": "Verification email sent" })))
+}
+
+/// 登出当前设备
+pub async fn logout(
+    pool: web::Data<MySqlPool>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    auth_service::logout(pool.get_ref(), &claims).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "message": "Logged out" })))
+}
+
+/// 登出所有设备
+pub async fn logout_all(
+    pool: web::Data<MySqlPool>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let revoked = auth_service::logout_all(pool.get_ref(), &claims).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "message": "Logged out of all devices", "sessions_revoked": revoked })))
+}
+
+/// 开始绑定验证器应用，返回密钥和扫码地址
+pub async fn post_totp_setup(
+    pool: web::Data<MySqlPool>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let setup = mfa_service::setup_totp(pool.get_ref(), &claims).await?;
+    Ok(HttpResponse::Ok().json(setup))
+}
+
+/// 确认绑定并开启两步验证，返回只展示一次的恢复码
+pub async fn post_totp_enable(
+    pool: web::Data<MySqlPool>,
+    dto: web::Json<TotpCodeDto>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let recovery_codes = mfa_service::enable_totp(pool.get_ref(), &claims, dto.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(RecoveryCodesResponse { recovery_codes }))
+}
+
+pub async fn post_totp_disable(
+    pool: web::Data<MySqlPool>,
+    dto: web::Json<DisableTotpDto>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    mfa_service::disable_totp(pool.get_ref(), &claims, dto.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "message": "Two-factor authentication disabled" })))
+}
+
+pub async fn post_recovery_codes(
+    pool: web::Data<MySqlPool>,
+    dto: web::Json<TotpCodeDto>,
+    req: HttpRequest,
+) -> Result<impl Responder, AppError> {
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(AppError::AuthError)?;
+    let recovery_codes = mfa_service::regenerate_recovery_codes(pool.get_ref(), &claims, dto.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(RecoveryCodesResponse { recovery_codes }))
+}
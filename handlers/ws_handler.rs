@@ -5,12 +5,12 @@ use std::time::{Duration, Instant};
 
 use crate::{
     errors::AppError,
-    models::user::Claims,
+    models::user::{Claims, ROLE_VIEWER},
     services::{
         chat_server::{self, ChatServer, ClientMessage, JoinRoom, LeaveRoom, ServerMessage},
-        notification_service::NotificationBuilder,
+        auth_service,
+        notification_service::{self, NotificationBuilder},
     },
-    utils::auth_utils,
 };
 use querystring::querify;
 use sqlx::MySqlPool;
@@ -128,14 +128,19 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WsSession {
                                     // 异步执行数据库操作和通知，避免阻塞Actor
                                     actix::spawn(async move {
                                         // 1. 查询发送者信息
-                                        let user_info: Result<(String, String), _> = sqlx::query_as(
-                                            "SELECT u.full_name, c.name as company_name FROM users u JOIN companies c ON u.company_id = c.id WHERE u.id = ?"
+                                        let user_info: Result<(String, String, String), _> = sqlx::query_as(
+                                            "SELECT u.full_name, c.name as company_name, u.role FROM users u JOIN companies c ON u.company_id = c.id WHERE u.id = ?"
                                         )
                                             .bind(current_user_id)
                                             .fetch_one(&pool)
                                             .await;
 
-                                        if let Ok((user_full_name, company_name)) = user_info {
+                                        if let Ok((user_full_name, company_name, role)) = user_info {
+                                            // 只读用户不能发言
+                                            if role == ROLE_VIEWER {
+                                                log::warn!("User #{} with role {} tried to post in RFQ #{}", current_user_id, role, rfq_id);
+                                                return;
+                                            }
                                             // 2. 将消息存入数据库
                                             if let Err(e) = sqlx::query(
                                                 "INSERT INTO chat_messages (rfq_id, user_id, user_full_name, company_name, message_text) VALUES (?, ?, ?, ?, ?)"
@@ -160,19 +165,24 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WsSession {
                                                 msg: message_to_save,
                                             });
 
-                                            // 4. 为房间内其他用户创建通知 (简化实现：只通知RFQ所有者)
-                                            let rfq_owner_id: Result<(i32,), _> = sqlx::query_as("SELECT u.id FROM rfqs r JOIN users u ON r.buyer_company_id = u.company_id WHERE r.id = ? LIMIT 1")
+                                            // 4. 为RFQ所属采购方公司的其他用户创建通知
+                                            let rfq_owner: Result<(i32,), _> = sqlx::query_as("SELECT buyer_company_id FROM rfqs WHERE id = ?")
                                                 .bind(rfq_id).fetch_one(&pool).await;
 
-                                            if let Ok((owner_id,)) = rfq_owner_id {
-                                                if owner_id != current_user_id { // 不给自己发通知
-                                                    let _ = NotificationBuilder::new(
-                                                        owner_id,
-                                                        format!("New message from {} in RFQ #{}", &user_full_name, rfq_id)
-                                                    )
-                                                        .with_link(format!("/rfqs/{}", rfq_id))
-                                                        .send(&pool, &chat_server_addr)
-                                                        .await;
+                                            if let Ok((buyer_company_id,)) = rfq_owner {
+                                                let recipients = notification_service::company_recipients(&pool, buyer_company_id)
+                                                    .await
+                                                    .unwrap_or_default();
+                                                for (owner_id, _) in recipients {
+                                                    if owner_id != current_user_id { // 不给自己发通知
+                                                        let _ = NotificationBuilder::new(
+                                                            owner_id,
+                                                            format!("New message from {} in RFQ #{}", &user_full_name, rfq_id)
+                                                        )
+                                                            .with_link(format!("/rfqs/{}", rfq_id))
+                                                            .send(&pool, &chat_server_addr)
+                                                            .await;
+                                                    }
                                                 }
                                             }
                                         } else {
@@ -210,7 +220,7 @@ pub async fn start_global_session(
         .map(|(_, v)| *v)
         .ok_or(AppError::AuthError)?;
 
-    let claims = auth_utils::validate_jwt(token).map_err(|_| AppError::AuthError)?;
+    let claims = auth_service::authenticate(pool.get_ref(), token).await?;
 
     let session = WsSession::new(claims.sub, chat_server_addr.get_ref().clone(), pool.get_ref().clone());
     ws::start(session, &req, stream)
@@ -47,6 +47,11 @@ async fn main() -> std::io::Result<()> {
     log::info!("🚀 Server starting at http://{}", server_addr);
     // --- 在 HttpServer::new 之前，启动ChatServer Actor ---
     let chat_server = ChatServer::default().start();
+    // --- 根据配置选择支付渠道 ---
+    let payment_provider = services::payment_provider::from_config(&config);
+
+    // --- 启动后台定时任务 ---
+    services::scheduler::start(pool.clone(), chat_server.clone(), config.clone(), payment_provider.clone());
 
     // 启动HTTP服务器
     HttpServer::new(move || {
@@ -62,6 +67,8 @@ async fn main() -> std::io::Result<()> {
             .app_data(web::Data::new(pool.clone()))
             // --- 将ChatServer的地址共享给所有处理器 ---
             .app_data(web::Data::new(chat_server.clone()))
+            // 共享当前配置的支付渠道
+            .app_data(web::Data::from(payment_provider.clone()))
             // 启用日志中间件
             .wrap(Logger::default())
             // 启用CORS中间件
@@ -0,0 +1,22 @@
+SET NAMES utf8mb4;
+
+-- ----------------------------
+-- Table structure for quote_negotiations
+-- ----------------------------
+CREATE TABLE `quote_negotiations` (
+  `id` int NOT NULL AUTO_INCREMENT,
+  `quote_id` int NOT NULL,
+  `proposed_by_company_id` int NOT NULL,
+  `proposer_type` enum('BUYER','SUPPLIER') COLLATE utf8mb4_unicode_ci NOT NULL,
+  `price` decimal(12,2) NOT NULL,
+  `lead_time_days` int NOT NULL,
+  `note` text COLLATE utf8mb4_unicode_ci,
+  `status` enum('PENDING','ACCEPTED','REJECTED','COUNTERED','WITHDRAWN') COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'PENDING',
+  `created_at` timestamp NULL DEFAULT CURRENT_TIMESTAMP,
+  `responded_at` timestamp NULL DEFAULT NULL,
+  PRIMARY KEY (`id`),
+  KEY `quote_id` (`quote_id`),
+  KEY `proposed_by_company_id` (`proposed_by_company_id`),
+  CONSTRAINT `quote_negotiations_ibfk_1` FOREIGN KEY (`quote_id`) REFERENCES `quotes` (`id`) ON DELETE CASCADE,
+  CONSTRAINT `quote_negotiations_ibfk_2` FOREIGN KEY (`proposed_by_company_id`) REFERENCES `companies` (`id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
@@ -0,0 +1,15 @@
+SET NAMES utf8mb4;
+
+-- ----------------------------
+-- Table structure for quote_attachments
+-- ----------------------------
+CREATE TABLE `quote_attachments` (
+  `id` int NOT NULL AUTO_INCREMENT,
+  `quote_id` int NOT NULL,
+  `original_filename` varchar(255) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `stored_path` varchar(255) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `created_at` timestamp NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (`id`),
+  KEY `quote_id` (`quote_id`),
+  CONSTRAINT `quote_attachments_ibfk_1` FOREIGN KEY (`quote_id`) REFERENCES `quotes` (`id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
@@ -0,0 +1,26 @@
+SET NAMES utf8mb4;
+
+-- ----------------------------
+-- 扩展采购订单状态机
+-- ----------------------------
+ALTER TABLE `purchase_orders`
+  MODIFY COLUMN `status` enum('PENDING_CONFIRMATION','CONFIRMED','IN_PRODUCTION','SHIPPED','DELIVERED','COMPLETED','CANCELLED','DISPUTED') COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'PENDING_CONFIRMATION';
+
+-- ----------------------------
+-- Table structure for order_status_history
+-- ----------------------------
+CREATE TABLE `order_status_history` (
+  `id` int NOT NULL AUTO_INCREMENT,
+  `order_id` int NOT NULL,
+  `from_status` varchar(32) COLLATE utf8mb4_unicode_ci DEFAULT NULL,
+  `to_status` varchar(32) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `actor_user_id` int NOT NULL,
+  `actor_company_id` int NOT NULL,
+  `comment` text COLLATE utf8mb4_unicode_ci,
+  `created_at` timestamp NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (`id`),
+  KEY `order_id` (`order_id`),
+  KEY `actor_user_id` (`actor_user_id`),
+  CONSTRAINT `order_status_history_ibfk_1` FOREIGN KEY (`order_id`) REFERENCES `purchase_orders` (`id`) ON DELETE CASCADE,
+  CONSTRAINT `order_status_history_ibfk_2` FOREIGN KEY (`actor_user_id`) REFERENCES `users` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
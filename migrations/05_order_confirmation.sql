@@ -0,0 +1,10 @@
+SET NAMES utf8mb4;
+
+-- ----------------------------
+-- 供应商确认/拒绝订单
+-- ----------------------------
+ALTER TABLE `purchase_orders`
+  MODIFY COLUMN `status` enum('PENDING_CONFIRMATION','CONFIRMED','DECLINED','IN_PRODUCTION','SHIPPED','DELIVERED','COMPLETED','CANCELLED','DISPUTED') COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'PENDING_CONFIRMATION',
+  ADD COLUMN `committed_ship_date` date DEFAULT NULL,
+  ADD COLUMN `confirmed_at` timestamp NULL DEFAULT NULL,
+  ADD COLUMN `confirmation_overdue` tinyint(1) NOT NULL DEFAULT '0';
@@ -0,0 +1,46 @@
+SET NAMES utf8mb4;
+
+-- ----------------------------
+-- 订单数量（授标时取自RFQ）
+-- ----------------------------
+ALTER TABLE `purchase_orders`
+  ADD COLUMN `quantity` int NOT NULL DEFAULT '0' AFTER `supplier_company_id`;
+
+UPDATE `purchase_orders` po JOIN `rfqs` r ON po.rfq_id = r.id SET po.quantity = r.quantity;
+
+-- ----------------------------
+-- Table structure for order_shipments
+-- ----------------------------
+CREATE TABLE `order_shipments` (
+  `id` int NOT NULL AUTO_INCREMENT,
+  `order_id` int NOT NULL,
+  `quantity_shipped` int NOT NULL,
+  `carrier` varchar(255) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `tracking_number` varchar(255) COLLATE utf8mb4_unicode_ci DEFAULT NULL,
+  `ship_date` date NOT NULL,
+  `packing_list_filename` varchar(255) COLLATE utf8mb4_unicode_ci DEFAULT NULL,
+  `packing_list_path` varchar(255) COLLATE utf8mb4_unicode_ci DEFAULT NULL,
+  `created_by_user_id` int NOT NULL,
+  `created_at` timestamp NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (`id`),
+  KEY `order_id` (`order_id`),
+  CONSTRAINT `order_shipments_ibfk_1` FOREIGN KEY (`order_id`) REFERENCES `purchase_orders` (`id`) ON DELETE CASCADE,
+  CONSTRAINT `order_shipments_ibfk_2` FOREIGN KEY (`created_by_user_id`) REFERENCES `users` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- ----------------------------
+-- Table structure for goods_receipts
+-- ----------------------------
+CREATE TABLE `goods_receipts` (
+  `id` int NOT NULL AUTO_INCREMENT,
+  `shipment_id` int NOT NULL,
+  `received_quantity` int NOT NULL,
+  `rejected_quantity` int NOT NULL DEFAULT '0',
+  `note` text COLLATE utf8mb4_unicode_ci,
+  `received_by_user_id` int NOT NULL,
+  `created_at` timestamp NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (`id`),
+  UNIQUE KEY `shipment_id` (`shipment_id`),
+  CONSTRAINT `goods_receipts_ibfk_1` FOREIGN KEY (`shipment_id`) REFERENCES `order_shipments` (`id`) ON DELETE CASCADE,
+  CONSTRAINT `goods_receipts_ibfk_2` FOREIGN KEY (`received_by_user_id`) REFERENCES `users` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
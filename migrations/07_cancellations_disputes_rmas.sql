@@ -0,0 +1,93 @@
+SET NAMES utf8mb4;
+
+-- ----------------------------
+-- Table structure for order_cancellation_requests
+-- ----------------------------
+CREATE TABLE `order_cancellation_requests` (
+  `id` int NOT NULL AUTO_INCREMENT,
+  `order_id` int NOT NULL,
+  `requested_by_company_id` int NOT NULL,
+  `requested_by_user_id` int NOT NULL,
+  `reason` text COLLATE utf8mb4_unicode_ci NOT NULL,
+  `status` enum('PENDING','APPROVED','REJECTED') COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'PENDING',
+  `responded_by_user_id` int DEFAULT NULL,
+  `created_at` timestamp NULL DEFAULT CURRENT_TIMESTAMP,
+  `responded_at` timestamp NULL DEFAULT NULL,
+  PRIMARY KEY (`id`),
+  KEY `order_id` (`order_id`),
+  CONSTRAINT `order_cancellation_requests_ibfk_1` FOREIGN KEY (`order_id`) REFERENCES `purchase_orders` (`id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- ----------------------------
+-- Table structure for order_disputes
+-- ----------------------------
+CREATE TABLE `order_disputes` (
+  `id` int NOT NULL AUTO_INCREMENT,
+  `order_id` int NOT NULL,
+  `opened_by_company_id` int NOT NULL,
+  `opened_by_user_id` int NOT NULL,
+  `reason` text COLLATE utf8mb4_unicode_ci NOT NULL,
+  `previous_order_status` varchar(32) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `status` enum('OPEN','RESOLVED') COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'OPEN',
+  `resolution` text COLLATE utf8mb4_unicode_ci,
+  `resolved_order_status` varchar(32) COLLATE utf8mb4_unicode_ci DEFAULT NULL,
+  `resolved_by_user_id` int DEFAULT NULL,
+  `created_at` timestamp NULL DEFAULT CURRENT_TIMESTAMP,
+  `resolved_at` timestamp NULL DEFAULT NULL,
+  PRIMARY KEY (`id`),
+  KEY `order_id` (`order_id`),
+  CONSTRAINT `order_disputes_ibfk_1` FOREIGN KEY (`order_id`) REFERENCES `purchase_orders` (`id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- ----------------------------
+-- Table structure for dispute_messages
+-- ----------------------------
+CREATE TABLE `dispute_messages` (
+  `id` int NOT NULL AUTO_INCREMENT,
+  `dispute_id` int NOT NULL,
+  `sender_user_id` int NOT NULL,
+  `sender_company_id` int NOT NULL,
+  `message` text COLLATE utf8mb4_unicode_ci NOT NULL,
+  `created_at` timestamp NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (`id`),
+  KEY `dispute_id` (`dispute_id`),
+  CONSTRAINT `dispute_messages_ibfk_1` FOREIGN KEY (`dispute_id`) REFERENCES `order_disputes` (`id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- ----------------------------
+-- Table structure for dispute_evidence
+-- ----------------------------
+CREATE TABLE `dispute_evidence` (
+  `id` int NOT NULL AUTO_INCREMENT,
+  `dispute_id` int NOT NULL,
+  `uploaded_by_user_id` int NOT NULL,
+  `original_filename` varchar(255) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `stored_path` varchar(255) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `created_at` timestamp NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (`id`),
+  KEY `dispute_id` (`dispute_id`),
+  CONSTRAINT `dispute_evidence_ibfk_1` FOREIGN KEY (`dispute_id`) REFERENCES `order_disputes` (`id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- ----------------------------
+-- Table structure for rmas
+-- ----------------------------
+CREATE TABLE `rmas` (
+  `id` int NOT NULL AUTO_INCREMENT,
+  `order_id` int NOT NULL,
+  `shipment_id` int DEFAULT NULL,
+  `return_quantity` int NOT NULL,
+  `reason` text COLLATE utf8mb4_unicode_ci NOT NULL,
+  `status` enum('REQUESTED','APPROVED','REJECTED','CLOSED') COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'REQUESTED',
+  `outcome` enum('CREDIT','REPLACEMENT') COLLATE utf8mb4_unicode_ci DEFAULT NULL,
+  `credit_amount` decimal(12,2) DEFAULT NULL,
+  `supplier_note` text COLLATE utf8mb4_unicode_ci,
+  `created_by_user_id` int NOT NULL,
+  `created_at` timestamp NULL DEFAULT CURRENT_TIMESTAMP,
+  `updated_at` timestamp NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+  PRIMARY KEY (`id`),
+  KEY `order_id` (`order_id`),
+  KEY `shipment_id` (`shipment_id`),
+  CONSTRAINT `rmas_ibfk_1` FOREIGN KEY (`order_id`) REFERENCES `purchase_orders` (`id`) ON DELETE CASCADE,
+  CONSTRAINT `rmas_ibfk_2` FOREIGN KEY (`shipment_id`) REFERENCES `order_shipments` (`id`) ON DELETE SET NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
@@ -0,0 +1,56 @@
+SET NAMES utf8mb4;
+
+-- ----------------------------
+-- 订单单价和版本号，变更单生效后版本号递增
+-- ----------------------------
+ALTER TABLE `purchase_orders`
+  ADD COLUMN `unit_price` decimal(12,4) NOT NULL DEFAULT '0.0000' AFTER `quantity`,
+  ADD COLUMN `version` int NOT NULL DEFAULT '1';
+
+UPDATE `purchase_orders` SET unit_price = total_amount / quantity WHERE quantity > 0;
+
+-- ----------------------------
+-- Table structure for order_change_requests
+-- ----------------------------
+CREATE TABLE `order_change_requests` (
+  `id` int NOT NULL AUTO_INCREMENT,
+  `order_id` int NOT NULL,
+  `base_version` int NOT NULL,
+  `requested_by_company_id` int NOT NULL,
+  `requested_by_user_id` int NOT NULL,
+  `new_quantity` int DEFAULT NULL,
+  `new_unit_price` decimal(12,4) DEFAULT NULL,
+  `new_ship_date` date DEFAULT NULL,
+  `reason` text COLLATE utf8mb4_unicode_ci NOT NULL,
+  `status` enum('PENDING','APPROVED','REJECTED') COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'PENDING',
+  `responded_by_user_id` int DEFAULT NULL,
+  `response_note` text COLLATE utf8mb4_unicode_ci,
+  `created_at` timestamp NULL DEFAULT CURRENT_TIMESTAMP,
+  `responded_at` timestamp NULL DEFAULT NULL,
+  PRIMARY KEY (`id`),
+  KEY `order_id` (`order_id`),
+  CONSTRAINT `order_change_requests_ibfk_1` FOREIGN KEY (`order_id`) REFERENCES `purchase_orders` (`id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- ----------------------------
+-- Table structure for purchase_order_versions
+-- 每个版本的订单条款快照；版本1为授标时的原始条款
+-- ----------------------------
+CREATE TABLE `purchase_order_versions` (
+  `id` int NOT NULL AUTO_INCREMENT,
+  `order_id` int NOT NULL,
+  `version` int NOT NULL,
+  `quantity` int NOT NULL,
+  `unit_price` decimal(12,4) NOT NULL,
+  `total_amount` decimal(12,2) NOT NULL,
+  `committed_ship_date` date DEFAULT NULL,
+  `change_request_id` int DEFAULT NULL,
+  `created_at` timestamp NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (`id`),
+  UNIQUE KEY `order_version` (`order_id`,`version`),
+  CONSTRAINT `purchase_order_versions_ibfk_1` FOREIGN KEY (`order_id`) REFERENCES `purchase_orders` (`id`) ON DELETE CASCADE,
+  CONSTRAINT `purchase_order_versions_ibfk_2` FOREIGN KEY (`change_request_id`) REFERENCES `order_change_requests` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+INSERT INTO `purchase_order_versions` (order_id, version, quantity, unit_price, total_amount, committed_ship_date, created_at)
+SELECT id, 1, quantity, unit_price, total_amount, committed_ship_date, created_at FROM `purchase_orders`;
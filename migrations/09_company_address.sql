@@ -0,0 +1,7 @@
+SET NAMES utf8mb4;
+
+-- ----------------------------
+-- 公司地址，用于采购订单和发票文档
+-- ----------------------------
+ALTER TABLE `companies`
+  ADD COLUMN `address` varchar(500) COLLATE utf8mb4_unicode_ci DEFAULT NULL;
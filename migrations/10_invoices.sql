@@ -0,0 +1,63 @@
+SET NAMES utf8mb4;
+
+-- ----------------------------
+-- Table structure for invoice_sequences
+-- 每个供应商独立、连续的发票编号
+-- ----------------------------
+CREATE TABLE `invoice_sequences` (
+  `supplier_company_id` int NOT NULL,
+  `last_number` int NOT NULL DEFAULT '0',
+  PRIMARY KEY (`supplier_company_id`),
+  CONSTRAINT `invoice_sequences_ibfk_1` FOREIGN KEY (`supplier_company_id`) REFERENCES `companies` (`id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- ----------------------------
+-- Table structure for invoices
+-- ----------------------------
+CREATE TABLE `invoices` (
+  `id` int NOT NULL AUTO_INCREMENT,
+  `order_id` int NOT NULL,
+  `supplier_company_id` int NOT NULL,
+  `buyer_company_id` int NOT NULL,
+  `shipment_id` int DEFAULT NULL,
+  `invoice_number` varchar(32) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `quantity` int NOT NULL,
+  `unit_price` decimal(12,4) NOT NULL,
+  `subtotal` decimal(12,2) NOT NULL,
+  `tax_amount` decimal(12,2) NOT NULL DEFAULT '0.00',
+  `total_amount` decimal(12,2) NOT NULL,
+  `payment_terms_days` int NOT NULL,
+  `issue_date` date NOT NULL,
+  `due_date` date NOT NULL,
+  `status` enum('ISSUED','PAID','OVERDUE') COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'ISSUED',
+  `notes` text COLLATE utf8mb4_unicode_ci,
+  `stripe_session_id` varchar(255) COLLATE utf8mb4_unicode_ci DEFAULT NULL,
+  `paid_at` timestamp NULL DEFAULT NULL,
+  `last_reminder_at` timestamp NULL DEFAULT NULL,
+  `created_by_user_id` int NOT NULL,
+  `created_at` timestamp NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (`id`),
+  UNIQUE KEY `supplier_invoice_number` (`supplier_company_id`,`invoice_number`),
+  UNIQUE KEY `shipment_id` (`shipment_id`),
+  KEY `order_id` (`order_id`),
+  KEY `buyer_company_id` (`buyer_company_id`),
+  KEY `status_due_date` (`status`,`due_date`),
+  CONSTRAINT `invoices_ibfk_1` FOREIGN KEY (`order_id`) REFERENCES `purchase_orders` (`id`) ON DELETE CASCADE,
+  CONSTRAINT `invoices_ibfk_2` FOREIGN KEY (`supplier_company_id`) REFERENCES `companies` (`id`),
+  CONSTRAINT `invoices_ibfk_3` FOREIGN KEY (`buyer_company_id`) REFERENCES `companies` (`id`),
+  CONSTRAINT `invoices_ibfk_4` FOREIGN KEY (`shipment_id`) REFERENCES `order_shipments` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- ----------------------------
+-- Table structure for invoice_tax_lines
+-- ----------------------------
+CREATE TABLE `invoice_tax_lines` (
+  `id` int NOT NULL AUTO_INCREMENT,
+  `invoice_id` int NOT NULL,
+  `description` varchar(255) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `rate` decimal(5,2) NOT NULL,
+  `amount` decimal(12,2) NOT NULL,
+  PRIMARY KEY (`id`),
+  KEY `invoice_id` (`invoice_id`),
+  CONSTRAINT `invoice_tax_lines_ibfk_1` FOREIGN KEY (`invoice_id`) REFERENCES `invoices` (`id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
@@ -0,0 +1,13 @@
+SET NAMES utf8mb4;
+
+-- ----------------------------
+-- 交期监控：授标时根据报价交期计算预计发货日期，定时任务标记有延误风险或已延误的订单
+-- ----------------------------
+ALTER TABLE `purchase_orders`
+  ADD COLUMN `expected_ship_date` date DEFAULT NULL AFTER `committed_ship_date`,
+  ADD COLUMN `delivery_risk` enum('ON_TRACK','AT_RISK','LATE') COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'ON_TRACK',
+  ADD COLUMN `shipped_date` date DEFAULT NULL,
+  ADD COLUMN `shipped_on_time` tinyint(1) DEFAULT NULL;
+
+UPDATE `purchase_orders` po JOIN `quotes` q ON po.quote_id = q.id
+SET po.expected_ship_date = DATE(po.created_at) + INTERVAL q.lead_time_days DAY;
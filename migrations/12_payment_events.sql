@@ -0,0 +1,29 @@
+SET NAMES utf8mb4;
+
+-- ----------------------------
+-- 付款状态：UNPAID / PROCESSING / PAID / FAILED，payment_error 记录最近一次付款失败的原因
+-- ----------------------------
+ALTER TABLE `purchase_orders`
+  MODIFY COLUMN `payment_status` varchar(20) COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'UNPAID',
+  ADD COLUMN `payment_error` varchar(255) COLLATE utf8mb4_unicode_ci DEFAULT NULL AFTER `payment_status`;
+
+-- ----------------------------
+-- 支付渠道Webhook事件日志。同一渠道的事件ID唯一，重复投递的事件不会被再次处理
+-- ----------------------------
+DROP TABLE IF EXISTS `payment_events`;
+CREATE TABLE `payment_events` (
+  `id` int NOT NULL AUTO_INCREMENT,
+  `provider` varchar(20) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `event_id` varchar(255) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `event_type` varchar(100) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `session_id` varchar(255) COLLATE utf8mb4_unicode_ci DEFAULT NULL,
+  `payload` mediumtext COLLATE utf8mb4_unicode_ci NOT NULL,
+  `status` enum('RECEIVED','PROCESSED','IGNORED','FAILED') COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'RECEIVED',
+  `error` text COLLATE utf8mb4_unicode_ci,
+  `attempts` int NOT NULL DEFAULT 1,
+  `received_at` timestamp NULL DEFAULT CURRENT_TIMESTAMP,
+  `processed_at` timestamp NULL DEFAULT NULL,
+  PRIMARY KEY (`id`),
+  UNIQUE KEY `provider_event` (`provider`, `event_id`),
+  KEY `session_id` (`session_id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
@@ -0,0 +1,75 @@
+SET NAMES utf8mb4;
+
+-- ----------------------------
+-- 付款计划：供应商在报价中要求的预付款比例，授标时带入订单
+-- ----------------------------
+ALTER TABLE `quotes`
+  ADD COLUMN `deposit_percent` decimal(5,2) NOT NULL DEFAULT 0.00 AFTER `lead_time_days`;
+
+ALTER TABLE `purchase_orders`
+  ADD COLUMN `deposit_percent` decimal(5,2) NOT NULL DEFAULT 0.00 AFTER `total_amount`,
+  ADD COLUMN `amount_paid` decimal(12,2) NOT NULL DEFAULT 0.00 AFTER `payment_error`,
+  ADD COLUMN `amount_refunded` decimal(12,2) NOT NULL DEFAULT 0.00 AFTER `amount_paid`;
+
+-- ----------------------------
+-- 付款记录：每个结账会话一条。DEPOSIT 预付款 / BALANCE 尾款 / FULL 全款 / INVOICE 按发票付款
+-- ----------------------------
+DROP TABLE IF EXISTS `order_payments`;
+CREATE TABLE `order_payments` (
+  `id` int NOT NULL AUTO_INCREMENT,
+  `order_id` int NOT NULL,
+  `invoice_id` int DEFAULT NULL,
+  `kind` enum('DEPOSIT','BALANCE','FULL','INVOICE') COLLATE utf8mb4_unicode_ci NOT NULL,
+  `amount` decimal(12,2) NOT NULL,
+  `refunded_amount` decimal(12,2) NOT NULL DEFAULT 0.00,
+  `status` enum('PENDING','PROCESSING','PAID','FAILED') COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'PENDING',
+  `provider` varchar(20) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `session_id` varchar(255) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `created_by_user_id` int DEFAULT NULL,
+  `created_at` timestamp NULL DEFAULT CURRENT_TIMESTAMP,
+  `paid_at` timestamp NULL DEFAULT NULL,
+  PRIMARY KEY (`id`),
+  UNIQUE KEY `session_id` (`session_id`),
+  KEY `order_id` (`order_id`),
+  KEY `invoice_id` (`invoice_id`),
+  CONSTRAINT `order_payments_ibfk_1` FOREIGN KEY (`order_id`) REFERENCES `purchase_orders` (`id`) ON DELETE CASCADE,
+  CONSTRAINT `order_payments_ibfk_2` FOREIGN KEY (`invoice_id`) REFERENCES `invoices` (`id`) ON DELETE SET NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- ----------------------------
+-- 退款记录。售后退货（RMA）产生的退款关联对应的RMA
+-- ----------------------------
+DROP TABLE IF EXISTS `order_refunds`;
+CREATE TABLE `order_refunds` (
+  `id` int NOT NULL AUTO_INCREMENT,
+  `order_id` int NOT NULL,
+  `payment_id` int NOT NULL,
+  `rma_id` int DEFAULT NULL,
+  `amount` decimal(12,2) NOT NULL,
+  `reason` text COLLATE utf8mb4_unicode_ci NOT NULL,
+  `provider_refund_id` varchar(255) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `created_by_user_id` int NOT NULL,
+  `created_at` timestamp NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (`id`),
+  KEY `order_id` (`order_id`),
+  KEY `payment_id` (`payment_id`),
+  KEY `rma_id` (`rma_id`),
+  CONSTRAINT `order_refunds_ibfk_1` FOREIGN KEY (`order_id`) REFERENCES `purchase_orders` (`id`) ON DELETE CASCADE,
+  CONSTRAINT `order_refunds_ibfk_2` FOREIGN KEY (`payment_id`) REFERENCES `order_payments` (`id`),
+  CONSTRAINT `order_refunds_ibfk_3` FOREIGN KEY (`rma_id`) REFERENCES `rmas` (`id`) ON DELETE SET NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- 已有的结账会话迁移为付款记录：先迁移发票的会话，再迁移订单整体付款的会话
+INSERT INTO `order_payments` (order_id, invoice_id, kind, amount, status, provider, session_id, paid_at)
+SELECT i.order_id, i.id, 'INVOICE', i.total_amount, IF(i.status = 'PAID', 'PAID', 'PENDING'), 'stripe', i.stripe_session_id, i.paid_at
+FROM `invoices` i WHERE i.stripe_session_id IS NOT NULL;
+
+INSERT INTO `order_payments` (order_id, kind, amount, status, provider, session_id, paid_at)
+SELECT po.id, 'FULL', po.total_amount, IF(po.payment_status = 'PAID', 'PAID', 'PENDING'), 'stripe', po.stripe_session_id,
+       IF(po.payment_status = 'PAID', po.created_at, NULL)
+FROM `purchase_orders` po
+WHERE po.stripe_session_id IS NOT NULL
+  AND NOT EXISTS (SELECT 1 FROM `invoices` i WHERE i.order_id = po.id AND i.stripe_session_id IS NOT NULL AND i.status = 'PAID');
+
+UPDATE `purchase_orders` po
+SET po.amount_paid = (SELECT COALESCE(SUM(p.amount), 0) FROM `order_payments` p WHERE p.order_id = po.id AND p.status = 'PAID');
@@ -0,0 +1,37 @@
+SET NAMES utf8mb4;
+
+-- ----------------------------
+-- 多币种：报价、订单、发票和付款记录各自的币种，以及公司的记账本位币
+-- ----------------------------
+ALTER TABLE `companies`
+  ADD COLUMN `base_currency` char(3) COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'USD';
+
+ALTER TABLE `quotes`
+  ADD COLUMN `currency` char(3) COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'USD' AFTER `price`;
+
+ALTER TABLE `purchase_orders`
+  ADD COLUMN `currency` char(3) COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'USD' AFTER `total_amount`;
+
+ALTER TABLE `invoices`
+  ADD COLUMN `currency` char(3) COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'USD' AFTER `total_amount`;
+
+ALTER TABLE `order_payments`
+  ADD COLUMN `currency` char(3) COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'USD' AFTER `amount`;
+
+-- ----------------------------
+-- 汇率表：1 单位 base_currency = rate 单位 quote_currency，自 effective_date 起生效。
+-- 由管理员手工维护或通过CSV导入
+-- ----------------------------
+DROP TABLE IF EXISTS `fx_rates`;
+CREATE TABLE `fx_rates` (
+  `id` int NOT NULL AUTO_INCREMENT,
+  `base_currency` char(3) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `quote_currency` char(3) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `rate` decimal(18,8) NOT NULL,
+  `effective_date` date NOT NULL,
+  `source` enum('MANUAL','IMPORT') COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'MANUAL',
+  `created_by_user_id` int DEFAULT NULL,
+  `created_at` timestamp NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (`id`),
+  UNIQUE KEY `pair_date` (`base_currency`, `quote_currency`, `effective_date`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
@@ -0,0 +1,13 @@
+SET NAMES utf8mb4;
+
+-- ----------------------------
+-- 公司内的用户角色。已有用户都是各自公司的唯一用户，设为 OWNER；
+-- 之后新加入的用户默认只有只读权限
+-- ----------------------------
+ALTER TABLE `users`
+  ADD COLUMN `role` enum('OWNER','PURCHASER','APPROVER','SALES','VIEWER') COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'OWNER' AFTER `full_name`;
+
+ALTER TABLE `users`
+  MODIFY COLUMN `role` enum('OWNER','PURCHASER','APPROVER','SALES','VIEWER') COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'VIEWER';
+
+CREATE INDEX `idx_users_company_role` ON `users` (`company_id`, `role`);
@@ -0,0 +1,27 @@
+SET NAMES utf8mb4;
+
+-- ----------------------------
+-- 团队邀请：公司所有者通过邮件邀请同事加入本公司。
+-- 令牌只保存 SHA-256 哈希；重新发送邀请会换一个新令牌并顺延有效期
+-- ----------------------------
+DROP TABLE IF EXISTS `company_invitations`;
+CREATE TABLE `company_invitations` (
+  `id` int NOT NULL AUTO_INCREMENT,
+  `company_id` int NOT NULL,
+  `email` varchar(255) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `role` enum('OWNER','PURCHASER','APPROVER','SALES','VIEWER') COLLATE utf8mb4_unicode_ci NOT NULL,
+  `token_hash` char(64) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `status` enum('PENDING','ACCEPTED','REVOKED') COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'PENDING',
+  `invited_by_user_id` int NOT NULL,
+  `accepted_user_id` int DEFAULT NULL,
+  `expires_at` timestamp NOT NULL,
+  `last_sent_at` timestamp NULL DEFAULT CURRENT_TIMESTAMP,
+  `accepted_at` timestamp NULL DEFAULT NULL,
+  `created_at` timestamp NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (`id`),
+  UNIQUE KEY `token_hash` (`token_hash`),
+  KEY `company_id` (`company_id`),
+  KEY `email` (`email`),
+  CONSTRAINT `company_invitations_ibfk_1` FOREIGN KEY (`company_id`) REFERENCES `companies` (`id`) ON DELETE CASCADE,
+  CONSTRAINT `company_invitations_ibfk_2` FOREIGN KEY (`invited_by_user_id`) REFERENCES `users` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
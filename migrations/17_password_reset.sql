@@ -0,0 +1,25 @@
+SET NAMES utf8mb4;
+
+-- ----------------------------
+-- 会话失效：JWT 中带有签发时的 token_version，修改或重置密码后版本号加一，
+-- 之前签发的所有令牌随即失效
+-- ----------------------------
+ALTER TABLE `users`
+  ADD COLUMN `token_version` int NOT NULL DEFAULT 0;
+
+-- ----------------------------
+-- 找回密码：通过邮件发送的一次性令牌，只保存 SHA-256 哈希
+-- ----------------------------
+DROP TABLE IF EXISTS `password_reset_tokens`;
+CREATE TABLE `password_reset_tokens` (
+  `id` int NOT NULL AUTO_INCREMENT,
+  `user_id` int NOT NULL,
+  `token_hash` char(64) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `expires_at` timestamp NOT NULL,
+  `used_at` timestamp NULL DEFAULT NULL,
+  `created_at` timestamp NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (`id`),
+  UNIQUE KEY `token_hash` (`token_hash`),
+  KEY `user_id` (`user_id`),
+  CONSTRAINT `password_reset_tokens_ibfk_1` FOREIGN KEY (`user_id`) REFERENCES `users` (`id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
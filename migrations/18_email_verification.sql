@@ -0,0 +1,23 @@
+SET NAMES utf8mb4;
+
+-- ----------------------------
+-- 邮箱验证。已有用户视为已验证
+-- ----------------------------
+ALTER TABLE `users`
+  ADD COLUMN `email_verified_at` timestamp NULL DEFAULT NULL;
+
+UPDATE `users` SET `email_verified_at` = NOW();
+
+DROP TABLE IF EXISTS `email_verification_tokens`;
+CREATE TABLE `email_verification_tokens` (
+  `id` int NOT NULL AUTO_INCREMENT,
+  `user_id` int NOT NULL,
+  `token_hash` char(64) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `expires_at` timestamp NOT NULL,
+  `used_at` timestamp NULL DEFAULT NULL,
+  `created_at` timestamp NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (`id`),
+  UNIQUE KEY `token_hash` (`token_hash`),
+  KEY `user_id` (`user_id`),
+  CONSTRAINT `email_verification_tokens_ibfk_1` FOREIGN KEY (`user_id`) REFERENCES `users` (`id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
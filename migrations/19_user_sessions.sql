@@ -0,0 +1,23 @@
+SET NAMES utf8mb4;
+
+-- ----------------------------
+-- 登录会话。访问令牌（JWT）有效期很短，过期后凭刷新令牌换取新的令牌对；
+-- 刷新令牌每次使用都会轮换，只保存 SHA-256 哈希。
+-- 上一个刷新令牌被再次使用说明可能已泄露，整个会话会被撤销
+-- ----------------------------
+DROP TABLE IF EXISTS `user_sessions`;
+CREATE TABLE `user_sessions` (
+  `id` int NOT NULL AUTO_INCREMENT,
+  `user_id` int NOT NULL,
+  `refresh_token_hash` char(64) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `previous_refresh_token_hash` char(64) COLLATE utf8mb4_unicode_ci DEFAULT NULL,
+  `expires_at` timestamp NOT NULL,
+  `last_used_at` timestamp NULL DEFAULT NULL,
+  `revoked_at` timestamp NULL DEFAULT NULL,
+  `created_at` timestamp NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (`id`),
+  UNIQUE KEY `refresh_token_hash` (`refresh_token_hash`),
+  KEY `previous_refresh_token_hash` (`previous_refresh_token_hash`),
+  KEY `user_id` (`user_id`),
+  CONSTRAINT `user_sessions_ibfk_1` FOREIGN KEY (`user_id`) REFERENCES `users` (`id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
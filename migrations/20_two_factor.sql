@@ -0,0 +1,39 @@
+SET NAMES utf8mb4;
+
+-- ----------------------------
+-- 两步验证（TOTP）。totp_secret 在开始绑定时生成，验证通过后才写入 totp_enabled_at；
+-- totp_last_step 记录最后一次使用的时间步长，防止同一个验证码被重复使用
+-- ----------------------------
+ALTER TABLE `users`
+  ADD COLUMN `totp_secret` varchar(64) COLLATE utf8mb4_unicode_ci DEFAULT NULL,
+  ADD COLUMN `totp_enabled_at` timestamp NULL DEFAULT NULL,
+  ADD COLUMN `totp_last_step` bigint DEFAULT NULL;
+
+-- 恢复码：手机丢失时代替验证码使用，每个只能用一次，只保存 SHA-256 哈希
+DROP TABLE IF EXISTS `user_recovery_codes`;
+CREATE TABLE `user_recovery_codes` (
+  `id` int NOT NULL AUTO_INCREMENT,
+  `user_id` int NOT NULL,
+  `code_hash` char(64) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `used_at` timestamp NULL DEFAULT NULL,
+  `created_at` timestamp NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (`id`),
+  KEY `user_id` (`user_id`),
+  CONSTRAINT `user_recovery_codes_ibfk_1` FOREIGN KEY (`user_id`) REFERENCES `users` (`id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- 登录的第二步：密码校验通过后签发的短期凭据，凭它和验证码完成登录
+DROP TABLE IF EXISTS `mfa_challenges`;
+CREATE TABLE `mfa_challenges` (
+  `id` int NOT NULL AUTO_INCREMENT,
+  `user_id` int NOT NULL,
+  `token_hash` char(64) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `attempts` int NOT NULL DEFAULT 0,
+  `expires_at` timestamp NOT NULL,
+  `used_at` timestamp NULL DEFAULT NULL,
+  `created_at` timestamp NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (`id`),
+  UNIQUE KEY `token_hash` (`token_hash`),
+  KEY `user_id` (`user_id`),
+  CONSTRAINT `mfa_challenges_ibfk_1` FOREIGN KEY (`user_id`) REFERENCES `users` (`id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
@@ -0,0 +1,25 @@
+SET NAMES utf8mb4;
+
+-- ----------------------------
+-- API 密钥：供ERP等系统集成调用，按公司划分，以创建者的身份和角色执行操作，
+-- 并且只能访问 scopes 中列出的资源。密钥只保存 SHA-256 哈希，key_prefix 用于界面展示
+-- ----------------------------
+DROP TABLE IF EXISTS `api_keys`;
+CREATE TABLE `api_keys` (
+  `id` int NOT NULL AUTO_INCREMENT,
+  `company_id` int NOT NULL,
+  `name` varchar(100) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `key_prefix` varchar(16) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `key_hash` char(64) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `scopes` varchar(255) COLLATE utf8mb4_unicode_ci NOT NULL, -- 逗号分隔，例如 rfq:write,orders:read
+  `created_by_user_id` int NOT NULL,
+  `expires_at` timestamp NULL DEFAULT NULL,
+  `last_used_at` timestamp NULL DEFAULT NULL,
+  `revoked_at` timestamp NULL DEFAULT NULL,
+  `created_at` timestamp NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (`id`),
+  UNIQUE KEY `key_hash` (`key_hash`),
+  KEY `company_id` (`company_id`),
+  CONSTRAINT `api_keys_ibfk_1` FOREIGN KEY (`company_id`) REFERENCES `companies` (`id`) ON DELETE CASCADE,
+  CONSTRAINT `api_keys_ibfk_2` FOREIGN KEY (`created_by_user_id`) REFERENCES `users` (`id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
@@ -0,0 +1,24 @@
+SET NAMES utf8mb4;
+
+-- ----------------------------
+-- 登录暴力破解防护。failed_login_count 记录连续失败次数，成功登录后清零；
+-- 达到阈值后在 locked_until 之前拒绝该账号登录，锁定时间随失败次数指数增长
+-- ----------------------------
+ALTER TABLE `users`
+  ADD COLUMN `failed_login_count` int NOT NULL DEFAULT 0,
+  ADD COLUMN `locked_until` timestamp NULL DEFAULT NULL;
+
+-- 每一次登录尝试（包括不存在的邮箱），用于按IP限流和安全审计
+DROP TABLE IF EXISTS `login_attempts`;
+CREATE TABLE `login_attempts` (
+  `id` bigint NOT NULL AUTO_INCREMENT,
+  `email` varchar(255) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `ip_address` varchar(45) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `user_id` int DEFAULT NULL,
+  `succeeded` tinyint(1) NOT NULL,
+  `created_at` timestamp NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (`id`),
+  KEY `ip_address_created_at` (`ip_address`,`created_at`),
+  KEY `user_id` (`user_id`),
+  CONSTRAINT `login_attempts_ibfk_1` FOREIGN KEY (`user_id`) REFERENCES `users` (`id`) ON DELETE SET NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
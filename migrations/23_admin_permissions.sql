@@ -0,0 +1,16 @@
+SET NAMES utf8mb4;
+
+-- ----------------------------
+-- 细分的管理权限。users.is_admin 为超级管理员，拥有全部权限；
+-- 客服等运营人员不需要 is_admin，只授予其工作所需的权限
+-- ----------------------------
+DROP TABLE IF EXISTS `admin_permissions`;
+CREATE TABLE `admin_permissions` (
+  `user_id` int NOT NULL,
+  `permission` enum('COMPANY_VERIFICATION','USER_MANAGEMENT','DISPUTE_RESOLUTION','TAXONOMY') COLLATE utf8mb4_unicode_ci NOT NULL,
+  `granted_by_user_id` int DEFAULT NULL,
+  `created_at` timestamp NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (`user_id`,`permission`),
+  CONSTRAINT `admin_permissions_ibfk_1` FOREIGN KEY (`user_id`) REFERENCES `users` (`id`) ON DELETE CASCADE,
+  CONSTRAINT `admin_permissions_ibfk_2` FOREIGN KEY (`granted_by_user_id`) REFERENCES `users` (`id`) ON DELETE SET NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
@@ -0,0 +1,35 @@
+SET NAMES utf8mb4;
+
+-- ----------------------------
+-- 平台审计日志，只允许追加。由服务层在业务事务内写入，与业务修改同时提交。
+-- company_id 为受影响的公司（公司所有者可以查看），actor_* 为操作者；
+-- 不使用外键，删除用户或公司后日志仍然保留
+-- ----------------------------
+DROP TABLE IF EXISTS `audit_logs`;
+CREATE TABLE `audit_logs` (
+  `id` bigint NOT NULL AUTO_INCREMENT,
+  `actor_user_id` int DEFAULT NULL,
+  `actor_company_id` int DEFAULT NULL,
+  `company_id` int DEFAULT NULL,
+  `action` varchar(64) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `entity_type` varchar(32) COLLATE utf8mb4_unicode_ci NOT NULL,
+  `entity_id` int NOT NULL,
+  `before_data` json DEFAULT NULL,
+  `after_data` json DEFAULT NULL,
+  `ip_address` varchar(45) COLLATE utf8mb4_unicode_ci DEFAULT NULL,
+  `created_at` timestamp NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (`id`),
+  KEY `actor_user_id_created_at` (`actor_user_id`,`created_at`),
+  KEY `company_id_created_at` (`company_id`,`created_at`),
+  KEY `entity` (`entity_type`,`entity_id`),
+  KEY `created_at` (`created_at`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- 禁止修改和删除已写入的日志
+DROP TRIGGER IF EXISTS `audit_logs_no_update`;
+CREATE TRIGGER `audit_logs_no_update` BEFORE UPDATE ON `audit_logs` FOR EACH ROW
+  SIGNAL SQLSTATE '45000' SET MESSAGE_TEXT = 'audit_logs is append-only';
+
+DROP TRIGGER IF EXISTS `audit_logs_no_delete`;
+CREATE TRIGGER `audit_logs_no_delete` BEFORE DELETE ON `audit_logs` FOR EACH ROW
+  SIGNAL SQLSTATE '45000' SET MESSAGE_TEXT = 'audit_logs is append-only';
@@ -0,0 +1,7 @@
+SET NAMES utf8mb4;
+
+-- ----------------------------
+-- EXPIRED：为同一期款项新建结账会话时，之前未付款的会话会在支付渠道失效，防止重复付款
+-- ----------------------------
+ALTER TABLE `order_payments`
+  MODIFY COLUMN `status` enum('PENDING','PROCESSING','PAID','FAILED','EXPIRED') COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'PENDING';
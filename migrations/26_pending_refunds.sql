@@ -0,0 +1,19 @@
+SET NAMES utf8mb4;
+
+-- ----------------------------
+-- 退款先以 PENDING 记录并提交，再调用支付渠道，成功后改为 SUCCEEDED。
+-- idempotency_key 随请求发给支付渠道，重试同一笔退款不会重复退款；
+-- 已有的退款记录都已在渠道完成
+-- ----------------------------
+ALTER TABLE `order_refunds`
+  ADD COLUMN `status` enum('PENDING','SUCCEEDED','FAILED') COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'SUCCEEDED' AFTER `reason`,
+  ADD COLUMN `idempotency_key` varchar(64) COLLATE utf8mb4_unicode_ci DEFAULT NULL AFTER `status`,
+  ADD COLUMN `error` text COLLATE utf8mb4_unicode_ci AFTER `provider_refund_id`,
+  ADD COLUMN `completed_at` timestamp NULL DEFAULT NULL,
+  MODIFY COLUMN `provider_refund_id` varchar(255) COLLATE utf8mb4_unicode_ci DEFAULT NULL,
+  ADD UNIQUE KEY `idempotency_key` (`idempotency_key`);
+
+UPDATE `order_refunds` SET `completed_at` = `created_at`;
+
+ALTER TABLE `order_refunds`
+  MODIFY COLUMN `status` enum('PENDING','SUCCEEDED','FAILED') COLLATE utf8mb4_unicode_ci NOT NULL DEFAULT 'PENDING';
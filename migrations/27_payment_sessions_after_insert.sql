@@ -0,0 +1,8 @@
+SET NAMES utf8mb4;
+
+-- ----------------------------
+-- 付款先以 PENDING 记录并提交，再调用支付渠道创建结账会话，成功后写回 session_id。
+-- 尚未写回 session_id 的记录表示结账会话正在创建中
+-- ----------------------------
+ALTER TABLE `order_payments`
+  MODIFY COLUMN `session_id` varchar(255) COLLATE utf8mb4_unicode_ci DEFAULT NULL;
@@ -13,19 +13,25 @@ mod decimal_as_string {
     }
 }
 
-#[derive(Debug, Serialize, FromRow)]
+// 金额均已按下单日期的汇率折算为公司的本位币 currency
+#[derive(Debug, Serialize)]
 pub struct BuyerStats {
     pub total_orders: i64, // 使用 i64 以防订单数非常多
     #[serde(with = "decimal_as_string")]
     pub total_spent: Decimal,
     pub distinct_suppliers: i64,
+    pub currency: String,
+    // 缺少汇率、无法折算的订单数，这些订单不计入金额
+    pub unconverted_orders: i64,
 }
 
-#[derive(Debug, Serialize, FromRow)]
+#[derive(Debug, Serialize)]
 pub struct SpendingBySupplier {
     pub supplier_name: String,
     #[serde(with = "decimal_as_string")]
     pub total: Decimal,
+    pub currency: String,
+    pub unconverted_orders: i64,
 }
 
 /// 用于供应方(Supplier)仪表盘的核心统计数据结构
@@ -33,6 +39,18 @@ pub struct SpendingBySupplier {
 pub struct SupplierStats {
     pub total_quotes_submitted: i64,
     pub accepted_quotes: i64,
+    // 已完成订单的收入，折算为本位币 currency
+    #[sqlx(skip)]
     #[serde(with = "decimal_as_string")]
     pub total_revenue: Decimal,
+    #[sqlx(skip)]
+    pub currency: String,
+    #[sqlx(skip)]
+    pub unconverted_orders: i64,
+    // 交期表现：已发货订单中按期发货的比例，以及当前延误的订单数
+    pub shipped_orders: i64,
+    pub on_time_shipments: i64,
+    #[sqlx(skip)]
+    pub on_time_rate: Option<f64>,
+    pub late_orders: i64,
 }
\ No newline at end of file
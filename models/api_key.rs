@@ -0,0 +1,50 @@
+// src/models/api_key.rs
+
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use chrono::{DateTime, Utc};
+
+// 可授予API密钥的权限范围：资源:read 只能查询，资源:write 可以查询和修改
+pub const API_KEY_SCOPES: &[&str] = &[
+    "rfq:read",
+    "rfq:write",
+    "quotes:read",
+    "quotes:write",
+    "orders:read",
+    "orders:write",
+    "invoices:read",
+    "invoices:write",
+    "capabilities:read",
+    "analytics:read",
+];
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct ApiKey {
+    pub id: i32,
+    pub company_id: i32,
+    pub name: String,
+    pub key_prefix: String,
+    pub scopes: String,
+    pub created_by_user_id: i32,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateApiKeyDto {
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub expires_in_days: Option<i64>,
+}
+
+/// 创建成功后返回完整密钥，只展示这一次
+#[derive(Debug, Serialize)]
+pub struct CreatedApiKeyResponse {
+    pub id: u64,
+    pub key: String,
+    pub key_prefix: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
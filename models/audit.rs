@@ -0,0 +1,42 @@
+// src/models/audit.rs
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct AuditLog {
+    pub id: i64,
+    pub actor_user_id: Option<i32>,
+    pub actor_company_id: Option<i32>,
+    pub company_id: Option<i32>,
+    pub action: String,
+    pub entity_type: String,
+    pub entity_id: i32,
+    pub before_data: Option<serde_json::Value>,
+    pub after_data: Option<serde_json::Value>,
+    pub ip_address: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 审计日志查询条件，日期为包含当天的闭区间
+#[derive(Debug, Deserialize)]
+pub struct AuditLogQuery {
+    pub actor_user_id: Option<i32>,
+    pub company_id: Option<i32>,
+    pub action: Option<String>,
+    pub entity_type: Option<String>,
+    pub entity_id: Option<i32>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub page: Option<u32>,
+    pub page_size: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditLogListResponse {
+    pub entries: Vec<AuditLog>,
+    pub total: i64,
+    pub page: u32,
+    pub page_size: u32,
+}
@@ -10,6 +10,13 @@ pub struct Capability {
     pub category: String,
 }
 
+/// 管理员新建或修改能力标签
+#[derive(Debug, Deserialize)]
+pub struct CapabilityDto {
+    pub name: String,
+    pub category: String,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct AddCompanyCapabilityDto {
     pub capability_id: i32,
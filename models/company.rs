@@ -10,13 +10,18 @@ pub struct CompanyProfile {
     pub name: String,
     pub company_type: String,
     pub city: Option<String>,
+    pub address: Option<String>,
     pub description: Option<String>,
+    // 记账本位币，统计报表按此币种折算
+    pub base_currency: String,
     pub created_at: DateTime<Utc>,
     pub is_verified: bool,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct UpdateCompanyDto {
-    // 目前只允许更新简介
+    // 目前只允许更新简介、地址和本位币
     pub description: String,
+    pub address: Option<String>,
+    pub base_currency: Option<String>,
 }
\ No newline at end of file
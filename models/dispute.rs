@@ -0,0 +1,106 @@
+// src/models/dispute.rs
+
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Decimal, FromRow};
+use chrono::{DateTime, Utc};
+
+// 用于自定义Decimal的序列化
+mod decimal_as_string {
+    use super::*;
+    pub fn serialize<S>(value: &Option<Decimal>, serializer: S) -> Result<S::Ok, S::Error>
+    where S: serde::Serializer,
+    {
+        match value {
+            Some(v) => serializer.serialize_str(&v.to_string()),
+            None => serializer.serialize_none(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct Dispute {
+    pub id: i32,
+    pub order_id: i32,
+    pub opened_by_company_id: i32,
+    pub opened_by_user_id: i32,
+    pub reason: String,
+    pub previous_order_status: String,
+    pub status: String, // OPEN / RESOLVED
+    pub resolution: Option<String>,
+    pub resolved_order_status: Option<String>,
+    pub resolved_by_user_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct DisputeMessage {
+    pub id: i32,
+    pub dispute_id: i32,
+    pub sender_user_id: i32,
+    pub sender_company_id: i32,
+    pub message: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct DisputeEvidence {
+    pub id: i32,
+    pub dispute_id: i32,
+    pub uploaded_by_user_id: i32,
+    pub original_filename: String,
+    #[serde(skip_serializing)] // 私有文件，只能通过下载接口获取
+    pub stored_path: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OpenDisputeDto {
+    pub reason: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DisputeMessageDto {
+    pub message: String,
+}
+
+/// 管理员裁决争议：给出结论并决定订单的最终状态
+#[derive(Debug, Deserialize)]
+pub struct ResolveDisputeDto {
+    pub resolution: String,
+    pub order_status: String,
+}
+
+/// 退货授权(RMA)记录
+#[derive(Debug, Serialize, FromRow)]
+pub struct Rma {
+    pub id: i32,
+    pub order_id: i32,
+    pub shipment_id: Option<i32>,
+    pub return_quantity: i32,
+    pub reason: String,
+    pub status: String, // REQUESTED / APPROVED / REJECTED / CLOSED
+    pub outcome: Option<String>, // CREDIT / REPLACEMENT
+    #[serde(with = "decimal_as_string")]
+    pub credit_amount: Option<Decimal>,
+    pub supplier_note: Option<String>,
+    pub created_by_user_id: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateRmaDto {
+    pub shipment_id: Option<i32>,
+    pub return_quantity: i32,
+    pub reason: String,
+}
+
+/// 供应商对RMA的处理决定
+#[derive(Debug, Deserialize)]
+pub struct RmaDecisionDto {
+    pub approve: bool,
+    pub outcome: Option<String>, // 同意时必填：CREDIT 或 REPLACEMENT
+    pub credit_amount: Option<f64>,
+    pub note: Option<String>,
+}
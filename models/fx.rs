@@ -0,0 +1,43 @@
+// src/models/fx.rs
+
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Decimal, FromRow};
+use chrono::{DateTime, NaiveDate, Utc};
+
+// 用于自定义Decimal的序列化
+mod decimal_as_string {
+    use super::*;
+    pub fn serialize<S>(value: &Decimal, serializer: S) -> Result<S::Ok, S::Error>
+    where S: serde::Serializer,
+    {
+        serializer.serialize_str(&value.to_string())
+    }
+}
+
+/// 汇率：1 单位 base_currency = rate 单位 quote_currency
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct FxRate {
+    pub id: i32,
+    pub base_currency: String,
+    pub quote_currency: String,
+    #[serde(with = "decimal_as_string")]
+    pub rate: Decimal,
+    pub effective_date: NaiveDate,
+    pub source: String, // MANUAL / IMPORT
+    pub created_at: DateTime<Utc>,
+}
+
+/// 管理员手工录入汇率，effective_date 缺省为今天
+#[derive(Debug, Deserialize)]
+pub struct FxRateDto {
+    pub base_currency: String,
+    pub quote_currency: String,
+    pub rate: f64,
+    pub effective_date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FxRateQuery {
+    pub base_currency: Option<String>,
+    pub quote_currency: Option<String>,
+}
@@ -0,0 +1,45 @@
+// src/models/invitation.rs
+
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use chrono::{DateTime, Utc};
+
+/// 公司成员邀请
+#[derive(Debug, Serialize, FromRow)]
+pub struct Invitation {
+    pub id: i32,
+    pub company_id: i32,
+    pub email: String,
+    pub role: String,
+    pub status: String, // PENDING / ACCEPTED / REVOKED
+    pub invited_by_user_id: i32,
+    pub accepted_user_id: Option<i32>,
+    pub expires_at: DateTime<Utc>,
+    pub last_sent_at: Option<DateTime<Utc>>,
+    pub accepted_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateInvitationDto {
+    pub email: String,
+    pub role: String,
+}
+
+/// 被邀请人打开邀请链接时看到的信息
+#[derive(Debug, Serialize, FromRow)]
+pub struct InvitationPreview {
+    pub company_name: String,
+    pub company_type: String,
+    pub email: String,
+    pub role: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// 接受邀请，在邀请方公司内创建账号
+#[derive(Debug, Deserialize)]
+pub struct AcceptInvitationDto {
+    pub token: String,
+    pub full_name: String,
+    pub password: String,
+}
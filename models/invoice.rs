@@ -0,0 +1,79 @@
+// src/models/invoice.rs
+
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Decimal, FromRow};
+use chrono::{DateTime, NaiveDate, Utc};
+
+// 用于自定义Decimal的序列化
+mod decimal_as_string {
+    use super::*;
+    pub fn serialize<S>(value: &Decimal, serializer: S) -> Result<S::Ok, S::Error>
+    where S: serde::Serializer,
+    {
+        serializer.serialize_str(&value.to_string())
+    }
+}
+
+/// 发票。可以针对某次发货开具部分发票，也可以针对订单剩余未开票的数量开具
+#[derive(Debug, Serialize, FromRow)]
+pub struct Invoice {
+    pub id: i32,
+    pub order_id: i32,
+    pub supplier_company_id: i32,
+    pub buyer_company_id: i32,
+    pub shipment_id: Option<i32>,
+    pub invoice_number: String,
+    pub quantity: i32,
+    #[serde(with = "decimal_as_string")]
+    pub unit_price: Decimal,
+    #[serde(with = "decimal_as_string")]
+    pub subtotal: Decimal,
+    #[serde(with = "decimal_as_string")]
+    pub tax_amount: Decimal,
+    #[serde(with = "decimal_as_string")]
+    pub total_amount: Decimal,
+    pub currency: String,
+    pub payment_terms_days: i32,
+    pub issue_date: NaiveDate,
+    pub due_date: NaiveDate,
+    pub status: String, // ISSUED / PAID / OVERDUE
+    pub notes: Option<String>,
+    pub paid_at: Option<DateTime<Utc>>,
+    pub last_reminder_at: Option<DateTime<Utc>>,
+    pub created_by_user_id: i32,
+    pub created_at: DateTime<Utc>,
+    #[sqlx(skip)]
+    pub tax_lines: Vec<InvoiceTaxLine>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct InvoiceTaxLine {
+    pub id: i32,
+    pub invoice_id: i32,
+    pub description: String,
+    #[serde(with = "decimal_as_string")]
+    pub rate: Decimal,
+    #[serde(with = "decimal_as_string")]
+    pub amount: Decimal,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TaxLineDto {
+    pub description: String,
+    pub rate: f64, // 百分比，例如 13.0 表示 13%
+}
+
+/// 供应商开具发票。指定 shipment_id 时只对该次发货开票
+#[derive(Debug, Deserialize)]
+pub struct CreateInvoiceDto {
+    pub shipment_id: Option<i32>,
+    pub payment_terms_days: Option<i32>,
+    #[serde(default)]
+    pub tax_lines: Vec<TaxLineDto>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InvoiceQuery {
+    pub status: Option<String>,
+}
@@ -9,4 +9,11 @@ pub(crate) mod analytics;
 pub(crate) mod payment;
 pub(crate) mod capability;
 pub(crate) mod notification;
+pub(crate) mod shipment;
+pub(crate) mod dispute;
+pub(crate) mod invoice;
+pub(crate) mod fx;
+pub(crate) mod invitation;
+pub(crate) mod api_key;
+pub(crate) mod audit;
 // <-- 新增
\ No newline at end of file
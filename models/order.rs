@@ -2,7 +2,8 @@
 
 use serde::{Deserialize, Serialize};
 use sqlx::{types::Decimal, FromRow};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
+use crate::models::{invoice::Invoice, payment::OrderPaymentSummary, quote::Quote, rfq::Rfq};
 
 // 用于自定义Decimal的序列化
 mod decimal_as_string {
@@ -14,9 +15,22 @@ mod decimal_as_string {
     }
 }
 
+mod option_decimal_as_string {
+    use super::*;
+    pub fn serialize<S>(value: &Option<Decimal>, serializer: S) -> Result<S::Ok, S::Error>
+    where S: serde::Serializer,
+    {
+        match value {
+            Some(v) => serializer.serialize_str(&v.to_string()),
+            None => serializer.serialize_none(),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, FromRow)]
 pub struct PurchaseOrder {
     pub id: i32,
+    pub quote_id: i32,
     pub rfq_id: i32,
     #[sqlx(default)] // 这个字段来自JOIN
     pub rfq_title: String,
@@ -26,14 +40,179 @@ pub struct PurchaseOrder {
     pub supplier_company_id: i32,
     #[sqlx(default)] // 这个字段来自JOIN
     pub supplier_name: String,
+    pub quantity: i32,
+    #[serde(with = "decimal_as_string")]
+    pub unit_price: Decimal,
     #[serde(with = "decimal_as_string")]
     pub total_amount: Decimal,
+    pub currency: String,
+    #[serde(with = "decimal_as_string")]
+    pub deposit_percent: Decimal,
+    pub version: i32,
     pub status: String,
     pub created_at: DateTime<Utc>,
-    pub payment_status: String,
+    pub payment_status: String, // UNPAID / PROCESSING / PARTIALLY_PAID / PAID / FAILED / PARTIALLY_REFUNDED / REFUNDED
+    pub payment_error: Option<String>,
+    #[serde(with = "decimal_as_string")]
+    pub amount_paid: Decimal,
+    #[serde(with = "decimal_as_string")]
+    pub amount_refunded: Decimal,
+    pub committed_ship_date: Option<NaiveDate>,
+    pub expected_ship_date: Option<NaiveDate>,
+    pub confirmed_at: Option<DateTime<Utc>>,
+    pub confirmation_overdue: bool,
+    pub delivery_risk: String, // ON_TRACK / AT_RISK / LATE
+    pub shipped_date: Option<NaiveDate>,
+    pub shipped_on_time: Option<bool>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct UpdateOrderStatusDto {
     pub status: String,
-}
\ No newline at end of file
+    pub comment: Option<String>,
+}
+
+/// 供应商确认订单时提交的承诺发货日期
+#[derive(Debug, Deserialize)]
+pub struct ConfirmOrderDto {
+    pub committed_ship_date: NaiveDate,
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeclineOrderDto {
+    pub reason: String,
+}
+
+/// 订单状态变更记录
+#[derive(Debug, Serialize, FromRow)]
+pub struct OrderStatusHistory {
+    pub id: i32,
+    pub order_id: i32,
+    pub from_status: Option<String>,
+    pub to_status: String,
+    pub actor_user_id: i32,
+    pub actor_company_id: i32,
+    pub comment: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 订单取消申请，需要对方同意后才会生效
+#[derive(Debug, Serialize, FromRow)]
+pub struct CancellationRequest {
+    pub id: i32,
+    pub order_id: i32,
+    pub requested_by_company_id: i32,
+    pub requested_by_user_id: i32,
+    pub reason: String,
+    pub status: String, // PENDING / APPROVED / REJECTED
+    pub responded_by_user_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub responded_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CancellationRequestDto {
+    pub reason: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RespondCancellationDto {
+    pub approve: bool,
+}
+
+/// 订单变更单（数量、单价、交期），需要对方批准后生效并生成新的订单版本
+#[derive(Debug, Serialize, FromRow)]
+pub struct ChangeRequest {
+    pub id: i32,
+    pub order_id: i32,
+    pub base_version: i32,
+    pub requested_by_company_id: i32,
+    pub requested_by_user_id: i32,
+    pub new_quantity: Option<i32>,
+    #[serde(with = "option_decimal_as_string")]
+    pub new_unit_price: Option<Decimal>,
+    pub new_ship_date: Option<NaiveDate>,
+    pub reason: String,
+    pub status: String, // PENDING / APPROVED / REJECTED
+    pub responded_by_user_id: Option<i32>,
+    pub response_note: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub responded_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangeRequestDto {
+    pub quantity: Option<i32>,
+    pub unit_price: Option<f64>,
+    pub committed_ship_date: Option<NaiveDate>,
+    pub reason: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RespondChangeRequestDto {
+    pub approve: bool,
+    pub note: Option<String>,
+}
+
+/// 订单某个版本的条款快照
+#[derive(Debug, Serialize, FromRow)]
+pub struct OrderVersion {
+    pub id: i32,
+    pub order_id: i32,
+    pub version: i32,
+    pub quantity: i32,
+    #[serde(with = "decimal_as_string")]
+    pub unit_price: Decimal,
+    #[serde(with = "decimal_as_string")]
+    pub total_amount: Decimal,
+    pub committed_ship_date: Option<NaiveDate>,
+    pub change_request_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 订单可下载的文档（采购订单、发票）
+#[derive(Debug, Serialize)]
+pub struct OrderDocument {
+    pub document_type: String,
+    pub number: String,
+    pub filename: String,
+    pub url: String,
+}
+
+/// 订单列表的筛选和分页参数
+#[derive(Debug, Deserialize)]
+pub struct OrderFilterParams {
+    pub status: Option<String>,
+    pub payment_status: Option<String>,
+    pub counterparty_id: Option<i32>,
+    pub created_from: Option<NaiveDate>,
+    pub created_to: Option<NaiveDate>,
+    pub min_amount: Option<f64>,
+    pub max_amount: Option<f64>,
+    pub page: Option<u32>,
+    pub page_size: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrderListResponse {
+    pub orders: Vec<PurchaseOrder>,
+    pub total: i64,
+    pub page: u32,
+    pub page_size: u32,
+}
+
+/// 订单详情：订单本身及其RFQ、报价、状态历史、变更记录和付款信息
+#[derive(Debug, Serialize)]
+pub struct OrderDetail {
+    #[serde(flatten)]
+    pub order: PurchaseOrder,
+    pub rfq: Rfq,
+    pub quote: Quote,
+    pub status_history: Vec<OrderStatusHistory>,
+    pub change_requests: Vec<ChangeRequest>,
+    pub versions: Vec<OrderVersion>,
+    pub invoices: Vec<Invoice>,
+    // 付款计划及付款、退款记录
+    pub payment_summary: OrderPaymentSummary,
+}
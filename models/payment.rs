@@ -1,7 +1,82 @@
 // src/models/payment.rs
 use serde::{Deserialize, Serialize};
+use sqlx::{types::Decimal, FromRow};
+use chrono::{DateTime, Utc};
+
+// 用于自定义Decimal的序列化
+mod decimal_as_string {
+    use super::*;
+    pub fn serialize<S>(value: &Decimal, serializer: S) -> Result<S::Ok, S::Error>
+    where S: serde::Serializer,
+    {
+        serializer.serialize_str(&value.to_string())
+    }
+}
 
 #[derive(Debug, Serialize)]
 pub struct CheckoutSessionResponse {
     pub session_id: String,
-}
\ No newline at end of file
+    // 支付渠道提供的付款页面地址
+    pub checkout_url: Option<String>,
+}
+
+/// 订单的一笔付款，每个结账会话对应一条
+#[derive(Debug, Serialize, FromRow)]
+pub struct OrderPayment {
+    pub id: i32,
+    pub order_id: i32,
+    pub invoice_id: Option<i32>,
+    pub kind: String, // DEPOSIT / BALANCE / FULL / INVOICE
+    #[serde(with = "decimal_as_string")]
+    pub amount: Decimal,
+    pub currency: String,
+    #[serde(with = "decimal_as_string")]
+    pub refunded_amount: Decimal,
+    pub status: String, // PENDING / PROCESSING / PAID / FAILED
+    pub provider: String,
+    pub created_at: DateTime<Utc>,
+    pub paid_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct OrderRefund {
+    pub id: i32,
+    pub order_id: i32,
+    pub payment_id: i32,
+    pub rma_id: Option<i32>,
+    #[serde(with = "decimal_as_string")]
+    pub amount: Decimal,
+    pub reason: String,
+    // PENDING 已记录、正在向支付渠道退款 / SUCCEEDED / FAILED
+    pub status: String,
+    pub provider_refund_id: Option<String>,
+    pub error: Option<String>,
+    pub created_by_user_id: i32,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+/// 供应商退款。不指定 payment_id 时从最近一笔足够退款的付款中退
+#[derive(Debug, Deserialize)]
+pub struct CreateRefundDto {
+    pub payment_id: Option<i32>,
+    pub rma_id: Option<i32>,
+    pub amount: f64,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrderPaymentSummary {
+    pub payment_status: String,
+    pub currency: String,
+    #[serde(with = "decimal_as_string")]
+    pub deposit_percent: Decimal,
+    #[serde(with = "decimal_as_string")]
+    pub amount_due: Decimal,
+    #[serde(with = "decimal_as_string")]
+    pub amount_paid: Decimal,
+    #[serde(with = "decimal_as_string")]
+    pub amount_refunded: Decimal,
+    pub payments: Vec<OrderPayment>,
+    pub refunds: Vec<OrderRefund>,
+}
@@ -20,7 +20,11 @@ pub struct Quote {
     pub supplier_company_id: i32,
     #[serde(with = "decimal_as_string")]
     pub price: Decimal,
+    pub currency: String,
     pub lead_time_days: i32,
+    // 要求采购方预付的比例（百分比），0 表示无需预付款
+    #[serde(with = "decimal_as_string")]
+    pub deposit_percent: Decimal,
     pub notes: Option<String>,
     pub status: String,
     pub created_at: DateTime<Utc>,
@@ -32,6 +36,50 @@ pub struct Quote {
 #[derive(Debug, Deserialize)]
 pub struct CreateQuoteDto {
     pub price: f64,
+    // 缺省为供应商的本位币
+    pub currency: Option<String>,
     pub lead_time_days: i32,
+    pub deposit_percent: Option<f64>,
     pub notes: Option<String>,
-}
\ No newline at end of file
+}
+/// 报价的一轮议价记录（采购方还价 / 供应方回应）
+#[derive(Debug, Serialize, FromRow)]
+pub struct QuoteNegotiation {
+    pub id: i32,
+    pub quote_id: i32,
+    pub proposed_by_company_id: i32,
+    pub proposer_type: String, // "BUYER" or "SUPPLIER"
+    #[serde(with = "decimal_as_string")]
+    pub price: Decimal,
+    pub lead_time_days: i32,
+    pub note: Option<String>,
+    pub status: String, // PENDING / ACCEPTED / REJECTED / COUNTERED / WITHDRAWN
+    pub created_at: DateTime<Utc>,
+    pub responded_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CounterOfferDto {
+    pub price: f64,
+    pub lead_time_days: i32,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RespondNegotiationDto {
+    pub action: String, // "ACCEPT", "REJECT" or "COUNTER"
+    // 仅在 action 为 COUNTER 时需要
+    pub price: Option<f64>,
+    pub lead_time_days: Option<i32>,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct QuoteAttachment {
+    pub id: i32,
+    pub quote_id: i32,
+    pub original_filename: String,
+    #[serde(skip_serializing)] // 私有文件，只能通过下载接口获取
+    pub stored_path: String,
+    pub created_at: DateTime<Utc>,
+}
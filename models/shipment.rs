@@ -0,0 +1,37 @@
+// src/models/shipment.rs
+
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use chrono::{DateTime, NaiveDate, Utc};
+
+/// 订单的一次发货记录（支持分批发货），收货信息通过 LEFT JOIN goods_receipts 得到
+#[derive(Debug, Serialize, FromRow)]
+pub struct Shipment {
+    pub id: i32,
+    pub order_id: i32,
+    pub quantity_shipped: i32,
+    pub carrier: String,
+    pub tracking_number: Option<String>,
+    pub ship_date: NaiveDate,
+    pub packing_list_filename: Option<String>,
+    #[serde(skip_serializing)] // 私有文件，只能通过下载接口获取
+    pub packing_list_path: Option<String>,
+    pub created_by_user_id: i32,
+    pub created_at: DateTime<Utc>,
+    #[sqlx(default)]
+    pub received_quantity: Option<i32>,
+    #[sqlx(default)]
+    pub rejected_quantity: Option<i32>,
+    #[sqlx(default)]
+    pub receipt_note: Option<String>,
+    #[sqlx(default)]
+    pub received_at: Option<DateTime<Utc>>,
+}
+
+/// 采购方对某次发货的收货确认
+#[derive(Debug, Deserialize)]
+pub struct GoodsReceiptDto {
+    pub received_quantity: i32,
+    pub rejected_quantity: i32,
+    pub note: Option<String>,
+}
@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
@@ -10,17 +11,61 @@ pub struct Company {
     pub company_type: String,
 }
 
-#[derive(Debug, FromRow)]
+#[derive(Debug, Clone, FromRow)]
 pub struct User {
     pub id: i32,
     pub company_id: i32,
     pub email: String,
     pub password_hash: String,
     pub full_name: Option<String>,
+    pub role: String,
     pub is_admin: bool,
     pub is_active: bool,
+    pub token_version: i32,
+    pub email_verified_at: Option<DateTime<Utc>>,
+    pub totp_enabled_at: Option<DateTime<Utc>>,
+    pub failed_login_count: i32,
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+// --- 公司内角色 ---
+// 一个公司可以有多个用户，每个用户在公司内有一个角色
+
+pub const ROLE_OWNER: &str = "OWNER";
+pub const ROLE_PURCHASER: &str = "PURCHASER";
+pub const ROLE_APPROVER: &str = "APPROVER";
+pub const ROLE_SALES: &str = "SALES";
+pub const ROLE_VIEWER: &str = "VIEWER";
+
+/// 日常业务操作：发布RFQ、报价、议价、发货、开票、争议和退货等
+pub const OPERATOR_ROLES: &[&str] = &[ROLE_OWNER, ROLE_PURCHASER, ROLE_SALES];
+/// 需要审批权限的操作：接受报价、付款、退款、审批变更和取消请求
+pub const APPROVER_ROLES: &[&str] = &[ROLE_OWNER, ROLE_APPROVER];
+
+/// 某类公司可以使用的角色。采购方没有销售角色，供应商没有采购员角色
+pub fn roles_for_company_type(company_type: &str) -> &'static [&'static str] {
+    if company_type == "SUPPLIER" {
+        &[ROLE_OWNER, ROLE_SALES, ROLE_APPROVER, ROLE_VIEWER]
+    } else {
+        &[ROLE_OWNER, ROLE_PURCHASER, ROLE_APPROVER, ROLE_VIEWER]
+    }
 }
 
+// --- 管理后台权限 ---
+// 超级管理员 (is_admin) 拥有全部权限，运营人员按需授予
+
+pub const ADMIN_PERM_COMPANY_VERIFICATION: &str = "COMPANY_VERIFICATION";
+pub const ADMIN_PERM_USER_MANAGEMENT: &str = "USER_MANAGEMENT";
+pub const ADMIN_PERM_DISPUTE_RESOLUTION: &str = "DISPUTE_RESOLUTION";
+pub const ADMIN_PERM_TAXONOMY: &str = "TAXONOMY";
+
+pub const ADMIN_PERMISSIONS: &[&str] = &[
+    ADMIN_PERM_COMPANY_VERIFICATION,
+    ADMIN_PERM_USER_MANAGEMENT,
+    ADMIN_PERM_DISPUTE_RESOLUTION,
+    ADMIN_PERM_TAXONOMY,
+];
+
 #[derive(Debug, Deserialize)]
 pub struct ChangePasswordDto {
     pub current_password: String,
@@ -40,6 +85,56 @@ pub struct RegisterDto {
     pub full_name: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ForgotPasswordDto {
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResetPasswordDto {
+    pub token: String,
+    pub new_password: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailDto {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RefreshTokenDto {
+    pub refresh_token: String,
+}
+
+/// 登录第二步。code 可以是验证器应用中的6位验证码，也可以是恢复码
+#[derive(Debug, Deserialize)]
+pub struct MfaLoginDto {
+    pub mfa_token: String,
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TotpSetupResponse {
+    pub secret: String,
+    pub provisioning_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TotpCodeDto {
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DisableTotpDto {
+    pub password: String,
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct LoginDto {
     pub email: String,
@@ -52,13 +147,33 @@ pub struct LoginDto {
 pub struct LoginResponse {
     pub token: String,
     pub token_type: String,
+    pub refresh_token: String,
+    pub expires_in: i64, // 访问令牌的有效秒数
+}
+
+/// 开启了两步验证的用户，密码校验通过后需要再提交验证码
+#[derive(Debug, Serialize)]
+pub struct MfaChallengeResponse {
+    pub mfa_required: bool,
+    pub mfa_token: String,
+    pub expires_in: i64,
+}
+
+/// 登录结果：直接登录成功，或需要完成两步验证
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum LoginResult {
+    Session(LoginResponse),
+    MfaRequired(MfaChallengeResponse),
 }
 
 impl LoginResponse {
-    pub fn new(token: String) -> Self {
+    pub fn new(token: String, refresh_token: String, expires_in: i64) -> Self {
         Self {
             token,
             token_type: "Bearer".to_string(),
+            refresh_token,
+            expires_in,
         }
     }
 }
@@ -71,7 +186,38 @@ pub struct UserProfileResponse {
     pub company_id: i32,
     #[sqlx(default)]
     pub company_name: String,
+    pub role: String,
     pub is_active: bool, // <-- 新增
+    pub email_verified_at: Option<DateTime<Utc>>,
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+/// 公司成员列表中的一项
+#[derive(Debug, Serialize, FromRow)]
+pub struct CompanyMember {
+    pub id: i32,
+    pub full_name: Option<String>,
+    pub email: String,
+    pub role: String,
+    pub is_active: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateRoleDto {
+    pub role: String,
+}
+
+/// 设置用户的管理权限（整体替换）
+#[derive(Debug, Deserialize)]
+pub struct UpdateAdminPermissionsDto {
+    pub permissions: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AdminPermissionsResponse {
+    pub user_id: i32,
+    pub is_admin: bool,
+    pub permissions: Vec<String>,
 }
 
 
@@ -83,6 +229,28 @@ pub struct Claims {
     pub sub: i32, // User ID
     pub company_id: i32,
     pub company_type: String,
+    pub role: String, // 公司内角色
     pub is_admin: bool, // <-- 新增
+    pub ver: i32, // 签发时用户的 token_version，修改密码后旧令牌失效
+    pub sid: i32, // 登录会话ID，会话被撤销（登出、禁用用户）后令牌失效
     pub exp: usize, // Expiration timestamp
+    // 不写入JWT，每次请求认证时从数据库读取，验证邮箱后立即生效
+    #[serde(default, skip_serializing)]
+    pub email_verified: bool,
+    // 同上，是否已开启两步验证
+    #[serde(default, skip_serializing)]
+    pub mfa_enabled: bool,
+    // 通过API密钥认证时为该密钥的权限范围，用户登录时为 None（不受限制）
+    #[serde(default, skip_serializing)]
+    pub api_key_scopes: Option<Vec<String>>,
+    // 请求的客户端IP，写入审计日志
+    #[serde(default, skip_serializing)]
+    pub ip_address: Option<String>,
+}
+
+impl Claims {
+    /// 当前用户是否拥有其中一个角色
+    pub fn has_role(&self, roles: &[&str]) -> bool {
+        roles.contains(&self.role.as_str())
+    }
 }
\ No newline at end of file
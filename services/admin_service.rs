@@ -1,26 +1,42 @@
 // src/services/admin_service.rs
 use crate::{errors::AppError, models::company::CompanyProfile};
 use sqlx::MySqlPool;
-use crate::models::user::UserProfileResponse;
+use crate::models::user::{AdminPermissionsResponse, Claims, UpdateAdminPermissionsDto, UserProfileResponse, ADMIN_PERMISSIONS};
+use crate::services::{audit_service::AuditEntry, auth_service};
 
 pub async fn list_all_companies(pool: &MySqlPool) -> Result<Vec<CompanyProfile>, AppError> {
-    let companies = sqlx::query_as("SELECT id, name, company_type, city, description, created_at, is_verified FROM companies ORDER BY created_at DESC")
+    let companies = sqlx::query_as("SELECT id, name, company_type, city, address, description, base_currency, created_at, is_verified FROM companies ORDER BY created_at DESC")
         .fetch_all(pool)
         .await?;
     Ok(companies)
 }
 
-pub async fn verify_company(pool: &MySqlPool, company_id: i32) -> Result<u64, AppError> {
+pub async fn verify_company(pool: &MySqlPool, company_id: i32, claims: &Claims) -> Result<u64, AppError> {
+    let mut tx = pool.begin().await?;
+    let current: Option<(bool,)> = sqlx::query_as("SELECT is_verified FROM companies WHERE id = ? FOR UPDATE")
+        .bind(company_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    let Some((was_verified,)) = current else {
+        return Ok(0);
+    };
     let result = sqlx::query("UPDATE companies SET is_verified = TRUE WHERE id = ?")
         .bind(company_id)
-        .execute(pool)
+        .execute(&mut *tx)
+        .await?;
+    AuditEntry::new(claims, "COMPANY_VERIFIED", "COMPANY", company_id)
+        .company(company_id)
+        .before(serde_json::json!({ "is_verified": was_verified }))
+        .after(serde_json::json!({ "is_verified": true }))
+        .record(&mut *tx)
         .await?;
+    tx.commit().await?;
     Ok(result.rows_affected())
 }
 
 pub async fn list_all_users(pool: &MySqlPool) -> Result<Vec<UserProfileResponse>, AppError> {
     let users = sqlx::query_as(
-        "SELECT u.id, u.full_name, u.email, u.company_id, u.is_active, c.name as company_name
+        "SELECT u.id, u.full_name, u.email, u.company_id, u.role, u.is_active, u.email_verified_at, u.locked_until, c.name as company_name
          FROM users u JOIN companies c ON u.company_id = c.id ORDER BY u.created_at DESC"
     )
         .fetch_all(pool)
@@ -28,11 +44,141 @@ pub async fn list_all_users(pool: &MySqlPool) -> Result<Vec<UserProfileResponse>
     Ok(users)
 }
 
-pub async fn update_user_status(pool: &MySqlPool, user_id: i32, is_active: bool) -> Result<u64, AppError> {
+pub async fn update_user_status(pool: &MySqlPool, user_id: i32, is_active: bool, claims: &Claims) -> Result<u64, AppError> {
+    ensure_can_manage_user(pool, user_id, claims).await?;
+    let mut tx = pool.begin().await?;
+    let (company_id, was_active): (i32, bool) = sqlx::query_as("SELECT company_id, is_active FROM users WHERE id = ? FOR UPDATE")
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
     let result = sqlx::query("UPDATE users SET is_active = ? WHERE id = ?")
         .bind(is_active)
         .bind(user_id)
-        .execute(pool)
+        .execute(&mut *tx)
+        .await?;
+    // 禁用用户时立即撤销其所有登录会话
+    if !is_active {
+        auth_service::revoke_sessions(&mut tx, user_id, None).await?;
+    }
+    AuditEntry::new(claims, "USER_STATUS_CHANGED", "USER", user_id)
+        .company(company_id)
+        .before(serde_json::json!({ "is_active": was_active }))
+        .after(serde_json::json!({ "is_active": is_active }))
+        .record(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(result.rows_affected())
+}
+
+/// 解除账号的登录锁定并清空失败次数
+pub async fn unlock_user(pool: &MySqlPool, user_id: i32, claims: &Claims) -> Result<u64, AppError> {
+    ensure_can_manage_user(pool, user_id, claims).await?;
+    let mut tx = pool.begin().await?;
+    let (company_id, failed_login_count): (i32, i32) = sqlx::query_as("SELECT company_id, failed_login_count FROM users WHERE id = ? FOR UPDATE")
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    let result = sqlx::query("UPDATE users SET failed_login_count = 0, locked_until = NULL WHERE id = ?")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    AuditEntry::new(claims, "USER_UNLOCKED", "USER", user_id)
+        .company(company_id)
+        .before(serde_json::json!({ "failed_login_count": failed_login_count }))
+        .record(&mut *tx)
         .await?;
+    tx.commit().await?;
     Ok(result.rows_affected())
-}
\ No newline at end of file
+}
+
+/// 读取用户的管理员身份和已授予的管理权限，AdminAuth 中间件每次请求都会调用
+/// （账号是否被禁用已由 Auth 中间件检查，这里不再过滤，以便管理被禁用的账号）
+pub(crate) async fn load_admin_access(pool: &MySqlPool, user_id: i32) -> Result<(bool, Vec<String>), AppError> {
+    let (is_admin,): (bool,) = sqlx::query_as("SELECT is_admin FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or(AppError::AuthError)?;
+    let permissions: Vec<(String,)> = sqlx::query_as("SELECT permission FROM admin_permissions WHERE user_id = ?")
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+    Ok((is_admin, permissions.into_iter().map(|(p,)| p).collect()))
+}
+
+/// 运营人员不能管理超级管理员或其他拥有管理权限的账号
+pub(crate) async fn ensure_can_manage_user(pool: &MySqlPool, user_id: i32, claims: &Claims) -> Result<(), AppError> {
+    if claims.is_admin {
+        return Ok(());
+    }
+    let (is_admin, permissions) = load_admin_access(pool, user_id)
+        .await
+        .map_err(|_| AppError::BadRequest("User not found".to_string()))?;
+    if is_admin || !permissions.is_empty() {
+        return Err(AppError::BadRequest("Only super administrators can manage administrator accounts.".to_string()));
+    }
+    Ok(())
+}
+
+pub async fn get_user_permissions(pool: &MySqlPool, user_id: i32) -> Result<AdminPermissionsResponse, AppError> {
+    let (is_admin, permissions) = load_admin_access(pool, user_id)
+        .await
+        .map_err(|_| AppError::BadRequest("User not found".to_string()))?;
+    Ok(AdminPermissionsResponse { user_id, is_admin, permissions })
+}
+
+/// 整体替换用户的管理权限（仅超级管理员）
+pub async fn set_user_permissions(
+    pool: &MySqlPool,
+    user_id: i32,
+    dto: UpdateAdminPermissionsDto,
+    claims: &Claims,
+) -> Result<AdminPermissionsResponse, AppError> {
+    let mut permissions: Vec<String> = Vec::new();
+    for permission in dto.permissions {
+        let permission = permission.trim().to_uppercase();
+        if !ADMIN_PERMISSIONS.contains(&permission.as_str()) {
+            return Err(AppError::BadRequest(format!(
+                "Unknown permission '{}'. Available permissions: {}",
+                permission,
+                ADMIN_PERMISSIONS.join(", ")
+            )));
+        }
+        if !permissions.contains(&permission) {
+            permissions.push(permission);
+        }
+    }
+
+    let mut tx = pool.begin().await?;
+    let (is_admin, company_id): (bool, i32) = sqlx::query_as("SELECT is_admin, company_id FROM users WHERE id = ? FOR UPDATE")
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("User not found".to_string()))?;
+    let previous: Vec<(String,)> = sqlx::query_as("SELECT permission FROM admin_permissions WHERE user_id = ?")
+        .bind(user_id)
+        .fetch_all(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM admin_permissions WHERE user_id = ?")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    for permission in &permissions {
+        sqlx::query("INSERT INTO admin_permissions (user_id, permission, granted_by_user_id) VALUES (?, ?, ?)")
+            .bind(user_id)
+            .bind(permission)
+            .bind(claims.sub)
+            .execute(&mut *tx)
+            .await?;
+    }
+    AuditEntry::new(claims, "ADMIN_PERMISSIONS_CHANGED", "USER", user_id)
+        .company(company_id)
+        .before(serde_json::json!({ "permissions": previous.into_iter().map(|(p,)| p).collect::<Vec<_>>() }))
+        .after(serde_json::json!({ "permissions": permissions }))
+        .record(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    log::warn!("Admin #{} set admin permissions of user #{} to [{}]", claims.sub, user_id, permissions.join(", "));
+    Ok(AdminPermissionsResponse { user_id, is_admin, permissions })
+}
@@ -4,8 +4,32 @@ use crate::{
     errors::AppError,
     models::{analytics::{BuyerStats, SpendingBySupplier, }, user::Claims},
 };
-use sqlx::MySqlPool;
 use crate::models::analytics::SupplierStats;
+use crate::services::fx_service::FxTable;
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::{types::Decimal, MySqlPool};
+use std::collections::{HashMap, HashSet};
+
+// 公司的本位币，统计金额都折算为此币种
+async fn base_currency(pool: &MySqlPool, company_id: i32) -> Result<String, AppError> {
+    let (currency,): (String,) = sqlx::query_as("SELECT base_currency FROM companies WHERE id = ?")
+        .bind(company_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(currency)
+}
+
+// 按下单日期的汇率折算订单金额。缺少汇率时返回 None，由调用方跳过该订单并计数，
+// 避免某个币种缺汇率导致整个报表无法显示
+fn convert_order(fx: &FxTable, amount: Decimal, from: &str, to: &str, on: NaiveDate) -> Option<Decimal> {
+    match fx.convert(amount, from, to, on) {
+        Ok(converted) => Some(converted),
+        Err(_) => {
+            log::warn!("No exchange rate from {} to {} on {}, order left out of analytics", from, to, on);
+            None
+        }
+    }
+}
 
 pub async fn get_buyer_dashboard_stats(pool: &MySqlPool, claims: &Claims) -> Result<BuyerStats, AppError> {
     // 权限检查
@@ -13,20 +37,35 @@ pub async fn get_buyer_dashboard_stats(pool: &MySqlPool, claims: &Claims) -> Res
         return Err(AppError::BadRequest("Analytics are only available for buyers.".to_string()));
     }
 
-    // 使用SQL聚合函数 COUNT, SUM, COUNT(DISTINCT)
-    let stats = sqlx::query_as(
-        "SELECT
-            COUNT(*) as total_orders,
-            COALESCE(SUM(total_amount), 0) as total_spent,
-            COUNT(DISTINCT supplier_company_id) as distinct_suppliers
-         FROM purchase_orders
-         WHERE buyer_company_id = ?"
+    let currency = base_currency(pool, claims.company_id).await?;
+    let fx = FxTable::load(pool).await?;
+
+    // 订单可能使用不同币种，按下单日期的汇率逐笔折算后再汇总
+    let orders: Vec<(i32, Decimal, String, DateTime<Utc>)> = sqlx::query_as(
+        "SELECT supplier_company_id, total_amount, currency, created_at FROM purchase_orders WHERE buyer_company_id = ?"
     )
         .bind(claims.company_id)
-        .fetch_one(pool)
+        .fetch_all(pool)
         .await?;
 
-    Ok(stats)
+    let mut total_spent = Decimal::ZERO;
+    let mut unconverted_orders = 0;
+    let mut suppliers = HashSet::new();
+    for (supplier_company_id, amount, order_currency, created_at) in &orders {
+        match convert_order(&fx, *amount, order_currency, &currency, created_at.date_naive()) {
+            Some(amount) => total_spent += amount,
+            None => unconverted_orders += 1,
+        }
+        suppliers.insert(*supplier_company_id);
+    }
+
+    Ok(BuyerStats {
+        total_orders: orders.len() as i64,
+        total_spent,
+        distinct_suppliers: suppliers.len() as i64,
+        currency,
+        unconverted_orders,
+    })
 }
 
 pub async fn get_buyer_spending_by_supplier(pool: &MySqlPool, claims: &Claims) -> Result<Vec<SpendingBySupplier>, AppError> {
@@ -34,21 +73,36 @@ pub async fn get_buyer_spending_by_supplier(pool: &MySqlPool, claims: &Claims) -
         return Err(AppError::BadRequest("Analytics are only available for buyers.".to_string()));
     }
 
-    // 使用 GROUP BY 和 JOIN 来按供应商分组统计支出
-    let spending_data = sqlx::query_as(
-        "SELECT
-            c.name as supplier_name,
-            SUM(po.total_amount) as total
+    let currency = base_currency(pool, claims.company_id).await?;
+    let fx = FxTable::load(pool).await?;
+
+    let orders: Vec<(i32, String, Decimal, String, DateTime<Utc>)> = sqlx::query_as(
+        "SELECT po.supplier_company_id, c.name, po.total_amount, po.currency, po.created_at
          FROM purchase_orders po
          JOIN companies c ON po.supplier_company_id = c.id
-         WHERE po.buyer_company_id = ?
-         GROUP BY po.supplier_company_id, c.name
-         ORDER BY total DESC"
+         WHERE po.buyer_company_id = ?"
     )
         .bind(claims.company_id)
         .fetch_all(pool)
         .await?;
 
+    // 按供应商分组统计支出
+    let mut by_supplier: HashMap<i32, SpendingBySupplier> = HashMap::new();
+    for (supplier_company_id, supplier_name, amount, order_currency, created_at) in orders {
+        let spending = by_supplier.entry(supplier_company_id).or_insert_with(|| SpendingBySupplier {
+            supplier_name,
+            total: Decimal::ZERO,
+            currency: currency.clone(),
+            unconverted_orders: 0,
+        });
+        match convert_order(&fx, amount, &order_currency, &currency, created_at.date_naive()) {
+            Some(amount) => spending.total += amount,
+            None => spending.unconverted_orders += 1,
+        }
+    }
+
+    let mut spending_data: Vec<SpendingBySupplier> = by_supplier.into_values().collect();
+    spending_data.sort_by_key(|s| std::cmp::Reverse(s.total));
     Ok(spending_data)
 }
 
@@ -58,18 +112,42 @@ pub async fn get_supplier_dashboard_stats(pool: &MySqlPool, claims: &Claims) ->
     }
 
     // 【关键修复】重写SQL查询，使其始终返回一行
-    let stats: SupplierStats = sqlx::query_as(
+    let mut stats: SupplierStats = sqlx::query_as(
         "SELECT
             (SELECT COUNT(*) FROM quotes WHERE supplier_company_id = ?) as total_quotes_submitted,
             (SELECT COUNT(*) FROM quotes WHERE supplier_company_id = ? AND status = 'ACCEPTED') as accepted_quotes,
-            (SELECT COALESCE(SUM(po.total_amount), 0) FROM purchase_orders po WHERE po.supplier_company_id = ? AND po.status = 'COMPLETED') as total_revenue
+            (SELECT COUNT(*) FROM purchase_orders WHERE supplier_company_id = ? AND shipped_on_time IS NOT NULL) as shipped_orders,
+            (SELECT COUNT(*) FROM purchase_orders WHERE supplier_company_id = ? AND shipped_on_time = TRUE) as on_time_shipments,
+            (SELECT COUNT(*) FROM purchase_orders WHERE supplier_company_id = ? AND delivery_risk = 'LATE'
+                AND status IN ('PENDING_CONFIRMATION', 'CONFIRMED', 'IN_PRODUCTION')) as late_orders
         "
     )
+        .bind(claims.company_id)
+        .bind(claims.company_id)
         .bind(claims.company_id)
         .bind(claims.company_id)
         .bind(claims.company_id)
         .fetch_one(pool)
         .await?;
 
+    stats.currency = base_currency(pool, claims.company_id).await?;
+    let fx = FxTable::load(pool).await?;
+    let completed: Vec<(Decimal, String, DateTime<Utc>)> = sqlx::query_as(
+        "SELECT total_amount, currency, created_at FROM purchase_orders WHERE supplier_company_id = ? AND status = 'COMPLETED'"
+    )
+        .bind(claims.company_id)
+        .fetch_all(pool)
+        .await?;
+    for (amount, order_currency, created_at) in completed {
+        match convert_order(&fx, amount, &order_currency, &stats.currency, created_at.date_naive()) {
+            Some(amount) => stats.total_revenue += amount,
+            None => stats.unconverted_orders += 1,
+        }
+    }
+
+    if stats.shipped_orders > 0 {
+        stats.on_time_rate = Some(stats.on_time_shipments as f64 / stats.shipped_orders as f64);
+    }
+
     Ok(stats)
 }
\ No newline at end of file
@@ -0,0 +1,235 @@
+// src/services/api_key_service.rs
+
+use crate::{
+    errors::AppError,
+    models::{
+        api_key::{ApiKey, CreateApiKeyDto, CreatedApiKeyResponse, API_KEY_SCOPES},
+        user::{Claims, ROLE_OWNER},
+    },
+    services::audit_service::AuditEntry,
+    utils::auth_utils,
+};
+use chrono::{Duration, Utc};
+use sqlx::{FromRow, MySqlPool};
+
+// 所有密钥都以此开头，便于在日志和代码仓库中识别泄露的密钥
+const API_KEY_PREFIX: &str = "sccp_";
+// 界面上展示的密钥前缀长度（含 "sccp_"）
+const DISPLAY_PREFIX_LEN: usize = 13;
+const MAX_EXPIRY_DAYS: i64 = 365;
+
+/// API密钥及其创建者，认证时使用
+#[derive(FromRow)]
+struct ApiKeyOwner {
+    key_id: i32,
+    company_id: i32,
+    company_type: String,
+    scopes: String,
+    user_id: i32,
+    role: String,
+    is_active: bool,
+    email_verified: bool,
+}
+
+/// 请求需要的权限范围，由路径中的资源和请求方法决定。
+/// 返回 None 表示API密钥不能访问该路径（账号、公司设置、管理后台等）
+pub fn required_scope(method: &str, path: &str) -> Option<String> {
+    let resource = match path.strip_prefix("/api/")?.split('/').next()? {
+        "rfqs" => "rfq",
+        "quotes" => "quotes",
+        "orders" => "orders",
+        "invoices" => "invoices",
+        "capabilities" => "capabilities",
+        "analytics" => "analytics",
+        _ => return None,
+    };
+    let access = if method == "GET" { "read" } else { "write" };
+    Some(format!("{}:{}", resource, access))
+}
+
+/// 密钥是否拥有某个权限范围。资源的 write 权限包含 read 权限
+pub fn has_scope(scopes: &[String], required: &str) -> bool {
+    if scopes.iter().any(|s| s == required) {
+        return true;
+    }
+    match required.strip_suffix(":read") {
+        Some(resource) => scopes.iter().any(|s| s.strip_suffix(":write") == Some(resource)),
+        None => false,
+    }
+}
+
+fn ensure_company_owner(claims: &Claims, company_id: i32) -> Result<(), AppError> {
+    if claims.company_id != company_id {
+        return Err(AppError::BadRequest("You can only manage API keys of your own company.".to_string()));
+    }
+    auth_utils::require_role(claims, &[ROLE_OWNER])
+}
+
+/// 公司所有者创建API密钥。密钥以创建者的身份执行操作
+pub async fn create_api_key(
+    pool: &MySqlPool,
+    company_id: i32,
+    dto: CreateApiKeyDto,
+    claims: &Claims,
+) -> Result<CreatedApiKeyResponse, AppError> {
+    ensure_company_owner(claims, company_id)?;
+
+    let name = dto.name.trim();
+    if name.is_empty() || name.len() > 100 {
+        return Err(AppError::BadRequest("API key name must be between 1 and 100 characters.".to_string()));
+    }
+    let mut scopes: Vec<String> = Vec::new();
+    for scope in &dto.scopes {
+        let scope = scope.trim().to_lowercase();
+        if !API_KEY_SCOPES.contains(&scope.as_str()) {
+            return Err(AppError::BadRequest(format!(
+                "Unknown scope '{}'. Available scopes: {}",
+                scope,
+                API_KEY_SCOPES.join(", ")
+            )));
+        }
+        if !scopes.contains(&scope) {
+            scopes.push(scope);
+        }
+    }
+    if scopes.is_empty() {
+        return Err(AppError::BadRequest("An API key needs at least one scope.".to_string()));
+    }
+    let expires_at = match dto.expires_in_days {
+        Some(days) if !(1..=MAX_EXPIRY_DAYS).contains(&days) => {
+            return Err(AppError::BadRequest(format!("Expiry must be between 1 and {} days.", MAX_EXPIRY_DAYS)));
+        }
+        Some(days) => Some(Utc::now() + Duration::days(days)),
+        None => None,
+    };
+
+    let key = format!("{}{}", API_KEY_PREFIX, auth_utils::generate_token());
+    let key_prefix = key[..DISPLAY_PREFIX_LEN].to_string();
+    let mut tx = pool.begin().await?;
+    let result = sqlx::query(
+        "INSERT INTO api_keys (company_id, name, key_prefix, key_hash, scopes, created_by_user_id, expires_at) VALUES (?, ?, ?, ?, ?, ?, ?)"
+    )
+        .bind(company_id)
+        .bind(name)
+        .bind(&key_prefix)
+        .bind(auth_utils::hash_token(&key))
+        .bind(scopes.join(","))
+        .bind(claims.sub)
+        .bind(expires_at)
+        .execute(&mut *tx)
+        .await?;
+    AuditEntry::new(claims, "API_KEY_CREATED", "API_KEY", result.last_insert_id() as i32)
+        .after(serde_json::json!({ "name": name, "key_prefix": key_prefix, "scopes": scopes, "expires_at": expires_at }))
+        .record(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(CreatedApiKeyResponse {
+        id: result.last_insert_id(),
+        key,
+        key_prefix,
+        scopes,
+        expires_at,
+    })
+}
+
+pub async fn list_api_keys(pool: &MySqlPool, company_id: i32, claims: &Claims) -> Result<Vec<ApiKey>, AppError> {
+    ensure_company_owner(claims, company_id)?;
+
+    let keys = sqlx::query_as(
+        "SELECT id, company_id, name, key_prefix, scopes, created_by_user_id, expires_at, last_used_at, revoked_at, created_at
+         FROM api_keys WHERE company_id = ? ORDER BY created_at DESC"
+    )
+        .bind(company_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(keys)
+}
+
+/// 撤销密钥，立即生效
+pub async fn revoke_api_key(pool: &MySqlPool, company_id: i32, key_id: i32, claims: &Claims) -> Result<(), AppError> {
+    ensure_company_owner(claims, company_id)?;
+
+    let mut tx = pool.begin().await?;
+    let result = sqlx::query("UPDATE api_keys SET revoked_at = NOW() WHERE id = ? AND company_id = ? AND revoked_at IS NULL")
+        .bind(key_id)
+        .bind(company_id)
+        .execute(&mut *tx)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::BadRequest("API key not found or already revoked.".to_string()));
+    }
+    AuditEntry::new(claims, "API_KEY_REVOKED", "API_KEY", key_id)
+        .record(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// 校验API密钥，返回代表该密钥的 Claims。创建者被禁用或已不在该公司时密钥同样失效
+pub async fn authenticate_api_key(pool: &MySqlPool, key: &str) -> Result<Claims, AppError> {
+    if !key.starts_with(API_KEY_PREFIX) {
+        return Err(AppError::AuthError);
+    }
+
+    let owner: ApiKeyOwner = sqlx::query_as(
+        "SELECT k.id as key_id, k.company_id, c.company_type, k.scopes, u.id as user_id, u.role, u.is_active,
+                u.email_verified_at IS NOT NULL as email_verified
+         FROM api_keys k
+         JOIN users u ON k.created_by_user_id = u.id AND u.company_id = k.company_id
+         JOIN companies c ON k.company_id = c.id
+         WHERE k.key_hash = ? AND k.revoked_at IS NULL AND (k.expires_at IS NULL OR k.expires_at > NOW())"
+    )
+        .bind(auth_utils::hash_token(key))
+        .fetch_optional(pool)
+        .await?
+        .ok_or(AppError::AuthError)?;
+    if !owner.is_active {
+        return Err(AppError::AuthError);
+    }
+
+    // 每分钟最多记录一次最近使用时间，避免每个请求都写库
+    sqlx::query(
+        "UPDATE api_keys SET last_used_at = NOW() WHERE id = ? AND (last_used_at IS NULL OR last_used_at < NOW() - INTERVAL 1 MINUTE)"
+    )
+        .bind(owner.key_id)
+        .execute(pool)
+        .await?;
+
+    Ok(Claims {
+        sub: owner.user_id,
+        company_id: owner.company_id,
+        company_type: owner.company_type,
+        role: owner.role,
+        is_admin: false,
+        ver: 0,
+        sid: 0,
+        exp: 0,
+        email_verified: owner.email_verified,
+        // 需要两步验证的操作（如接受报价）不能通过API密钥执行
+        mfa_enabled: false,
+        api_key_scopes: Some(owner.scopes.split(',').map(str::to_string).collect()),
+        ip_address: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_required_scope_and_has_scope() {
+        assert_eq!(required_scope("GET", "/api/orders/12").as_deref(), Some("orders:read"));
+        assert_eq!(required_scope("POST", "/api/rfqs").as_deref(), Some("rfq:write"));
+        assert_eq!(required_scope("PUT", "/api/quotes/3/accept").as_deref(), Some("quotes:write"));
+        assert_eq!(required_scope("GET", "/api/users/me"), None);
+        assert_eq!(required_scope("GET", "/api/admin/users"), None);
+
+        let scopes = vec!["rfq:write".to_string(), "orders:read".to_string()];
+        assert!(has_scope(&scopes, "rfq:write"));
+        assert!(has_scope(&scopes, "rfq:read"));
+        assert!(has_scope(&scopes, "orders:read"));
+        assert!(!has_scope(&scopes, "orders:write"));
+        assert!(!has_scope(&scopes, "invoices:read"));
+    }
+}
@@ -0,0 +1,170 @@
+// src/services/audit_service.rs
+
+use crate::{
+    errors::AppError,
+    models::{
+        audit::{AuditLogListResponse, AuditLogQuery},
+        user::{Claims, ROLE_OWNER},
+    },
+    utils::auth_utils,
+};
+use sqlx::{Executor, MySql, MySqlPool, QueryBuilder};
+
+const DEFAULT_PAGE_SIZE: u32 = 50;
+const MAX_PAGE_SIZE: u32 = 200;
+
+/// 一条审计日志。在业务事务内调用 record，与业务修改一起提交或回滚
+pub struct AuditEntry {
+    actor_user_id: i32,
+    actor_company_id: i32,
+    company_id: i32,
+    ip_address: Option<String>,
+    action: &'static str,
+    entity_type: &'static str,
+    entity_id: i32,
+    before: Option<serde_json::Value>,
+    after: Option<serde_json::Value>,
+}
+
+impl AuditEntry {
+    /// 受影响的公司默认为操作者所在公司，管理员操作其他公司时用 company 指定
+    pub fn new(claims: &Claims, action: &'static str, entity_type: &'static str, entity_id: i32) -> Self {
+        Self {
+            actor_user_id: claims.sub,
+            actor_company_id: claims.company_id,
+            company_id: claims.company_id,
+            ip_address: claims.ip_address.clone(),
+            action,
+            entity_type,
+            entity_id,
+            before: None,
+            after: None,
+        }
+    }
+
+    pub fn company(mut self, company_id: i32) -> Self {
+        self.company_id = company_id;
+        self
+    }
+
+    pub fn before(mut self, before: serde_json::Value) -> Self {
+        self.before = Some(before);
+        self
+    }
+
+    pub fn after(mut self, after: serde_json::Value) -> Self {
+        self.after = Some(after);
+        self
+    }
+
+    pub async fn record<'e, E>(self, executor: E) -> Result<(), AppError>
+    where
+        E: Executor<'e, Database = MySql>,
+    {
+        sqlx::query(
+            "INSERT INTO audit_logs (actor_user_id, actor_company_id, company_id, action, entity_type, entity_id, before_data, after_data, ip_address)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+            .bind(self.actor_user_id)
+            .bind(self.actor_company_id)
+            .bind(self.company_id)
+            .bind(self.action)
+            .bind(self.entity_type)
+            .bind(self.entity_id)
+            .bind(self.before)
+            .bind(self.after)
+            .bind(self.ip_address)
+            .execute(executor)
+            .await?;
+        Ok(())
+    }
+}
+
+fn push_audit_filters(qb: &mut QueryBuilder<MySql>, query: &AuditLogQuery) {
+    qb.push(" WHERE 1 = 1");
+    if let Some(actor_user_id) = query.actor_user_id {
+        qb.push(" AND actor_user_id = ").push_bind(actor_user_id);
+    }
+    if let Some(company_id) = query.company_id {
+        qb.push(" AND company_id = ").push_bind(company_id);
+    }
+    if let Some(action) = &query.action {
+        qb.push(" AND action = ").push_bind(action.to_uppercase());
+    }
+    if let Some(entity_type) = &query.entity_type {
+        qb.push(" AND entity_type = ").push_bind(entity_type.to_uppercase());
+    }
+    if let Some(entity_id) = query.entity_id {
+        qb.push(" AND entity_id = ").push_bind(entity_id);
+    }
+    if let Some(from) = query.from {
+        qb.push(" AND created_at >= ").push_bind(from);
+    }
+    if let Some(to) = query.to {
+        // 结束日期包含当天
+        qb.push(" AND created_at < ").push_bind(to + chrono::Days::new(1));
+    }
+}
+
+/// 按条件分页查询审计日志，最新的在前
+pub async fn list_audit_logs(pool: &MySqlPool, query: AuditLogQuery) -> Result<AuditLogListResponse, AppError> {
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query.page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+    let mut count_qb: QueryBuilder<MySql> = QueryBuilder::new("SELECT COUNT(*) FROM audit_logs");
+    push_audit_filters(&mut count_qb, &query);
+    let (total,): (i64,) = count_qb.build_query_as().fetch_one(pool).await?;
+
+    let mut qb: QueryBuilder<MySql> = QueryBuilder::new("SELECT * FROM audit_logs");
+    push_audit_filters(&mut qb, &query);
+    qb.push(" ORDER BY created_at DESC, id DESC LIMIT ")
+        .push_bind(page_size)
+        .push(" OFFSET ")
+        .push_bind((page - 1) as u64 * page_size as u64);
+    let entries = qb.build_query_as().fetch_all(pool).await?;
+
+    Ok(AuditLogListResponse { entries, total, page, page_size })
+}
+
+/// 公司所有者查看与本公司有关的审计日志
+pub async fn list_company_audit_logs(
+    pool: &MySqlPool,
+    company_id: i32,
+    mut query: AuditLogQuery,
+    claims: &Claims,
+) -> Result<AuditLogListResponse, AppError> {
+    if claims.company_id != company_id {
+        return Err(AppError::BadRequest("You can only view the audit log of your own company.".to_string()));
+    }
+    auth_utils::require_role(claims, &[ROLE_OWNER])?;
+
+    query.company_id = Some(company_id);
+    list_audit_logs(pool, query).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_push_audit_filters() {
+        let query = AuditLogQuery {
+            actor_user_id: Some(7),
+            company_id: None,
+            action: Some("quote_accepted".to_string()),
+            entity_type: None,
+            entity_id: None,
+            from: None,
+            to: Some(NaiveDate::from_ymd_opt(2025, 1, 31).unwrap()),
+            page: None,
+            page_size: None,
+        };
+        let mut qb: QueryBuilder<MySql> = QueryBuilder::new("SELECT * FROM audit_logs");
+        push_audit_filters(&mut qb, &query);
+        assert_eq!(
+            qb.sql(),
+            "SELECT * FROM audit_logs WHERE 1 = 1 AND actor_user_id = ? AND action = ? AND created_at < ?"
+        );
+    }
+}
@@ -1,7 +1,23 @@
 use crate::errors::AppError;
-use crate::models::user::{LoginDto, RegisterDto, User};
+use crate::models::user::{
+    Claims, ForgotPasswordDto, LoginDto, LoginResponse, LoginResult, RefreshTokenDto, RegisterDto, ResetPasswordDto, User, VerifyEmailDto,
+};
+use crate::services::{login_guard_service, mfa_service, notification_service};
 use crate::utils::auth_utils;
-use sqlx::{FromRow, MySqlPool, Row};
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{FromRow, MySqlConnection, MySqlPool, Row};
+use std::env;
+
+// 找回密码链接的有效时间
+const PASSWORD_RESET_EXPIRY_MINUTES: i64 = 60;
+// 邮箱验证链接的有效时间
+const EMAIL_VERIFICATION_EXPIRY_HOURS: i64 = 24;
+// 登录会话（刷新令牌）的有效期，从登录时算起，刷新不会延长
+const SESSION_EXPIRY_DAYS: i64 = 30;
+
+fn frontend_url() -> String {
+    env::var("FRONTEND_URL").unwrap_or_else(|_| "http://localhost:5173".to_string())
+}
 
 /// 处理新用户和公司的注册逻辑
 pub async fn register_user_and_company(
@@ -36,9 +52,9 @@ pub async fn register_user_and_company(
     let password_hash = auth_utils::hash_password(&dto.password)
         .map_err(|_| AppError::InternalServerError("Failed to hash password".to_string()))?;
 
-    // 3. 创建用户
-    sqlx::query(
-        "INSERT INTO users (company_id, email, password_hash, full_name) VALUES (?, ?, ?, ?)",
+    // 3. 创建用户，注册公司的用户是公司的所有者
+    let user_result = sqlx::query(
+        "INSERT INTO users (company_id, email, password_hash, full_name, role) VALUES (?, ?, ?, ?, 'OWNER')",
     )
         .bind(company_id)
         .bind(&dto.email)
@@ -46,48 +62,341 @@ pub async fn register_user_and_company(
         .bind(&dto.full_name)
         .execute(&mut *tx)
         .await?;
+    let verification_token = create_verification_token(&mut tx, user_result.last_insert_id() as i32).await?;
 
     // 提交事务
     tx.commit().await?;
 
+    // 4. 发送验证邮件。发送失败不影响注册，用户可以登录后重新发送
+    if let Err(e) = send_verification_email(&dto.email, &verification_token).await {
+        log::error!("Failed to send verification email: {:?}", e);
+    }
+
+    Ok(())
+}
+
+/// 生成新的邮箱验证令牌，之前未使用的令牌作废
+async fn create_verification_token(conn: &mut MySqlConnection, user_id: i32) -> Result<String, AppError> {
+    sqlx::query("UPDATE email_verification_tokens SET used_at = NOW() WHERE user_id = ? AND used_at IS NULL")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    let token = auth_utils::generate_token();
+    sqlx::query("INSERT INTO email_verification_tokens (user_id, token_hash, expires_at) VALUES (?, ?, ?)")
+        .bind(user_id)
+        .bind(auth_utils::hash_token(&token))
+        .bind(Utc::now() + Duration::hours(EMAIL_VERIFICATION_EXPIRY_HOURS))
+        .execute(&mut *conn)
+        .await?;
+    Ok(token)
+}
+
+async fn send_verification_email(email: &str, token: &str) -> Result<(), AppError> {
+    let subject = "Verify your SCCP email address".to_string();
+    let body = format!(
+        "Hello,\n\nPlease confirm your email address within {} hours:\n{}/verify-email?token={}\n\nUntil your email is verified you cannot publish RFQs or submit quotes.",
+        EMAIL_VERIFICATION_EXPIRY_HOURS, frontend_url(), token
+    );
+    notification_service::send_email(email.to_string(), subject, body).await
+}
+
+/// 使用邮件中的令牌验证邮箱
+pub async fn verify_email(pool: &MySqlPool, dto: VerifyEmailDto) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+    let (token_id, user_id): (i32, i32) = sqlx::query_as(
+        "SELECT id, user_id FROM email_verification_tokens WHERE token_hash = ? AND used_at IS NULL AND expires_at > NOW() FOR UPDATE"
+    )
+        .bind(auth_utils::hash_token(&dto.token))
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("This verification link is invalid or has expired.".to_string()))?;
+
+    sqlx::query("UPDATE users SET email_verified_at = COALESCE(email_verified_at, NOW()) WHERE id = ?")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("UPDATE email_verification_tokens SET used_at = NOW() WHERE id = ?")
+        .bind(token_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
     Ok(())
 }
 
+/// 已登录但尚未验证邮箱的用户重新获取验证链接
+pub async fn resend_verification_email(pool: &MySqlPool, claims: &Claims) -> Result<(), AppError> {
+    let (email, verified_at): (String, Option<DateTime<Utc>>) =
+        sqlx::query_as("SELECT email, email_verified_at FROM users WHERE id = ?")
+            .bind(claims.sub)
+            .fetch_one(pool)
+            .await?;
+    if verified_at.is_some() {
+        return Err(AppError::BadRequest("Your email address is already verified.".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+    let token = create_verification_token(&mut tx, claims.sub).await?;
+    tx.commit().await?;
+
+    send_verification_email(&email, &token).await
+}
+
 /// 处理用户登录逻辑
-pub async fn login_user(pool: &MySqlPool, dto: LoginDto) -> Result<String, AppError> {
+/// ip_address 为客户端地址，用于按IP限制失败次数
+pub async fn login_user(pool: &MySqlPool, dto: LoginDto, ip_address: &str) -> Result<LoginResult, AppError> {
+    login_guard_service::check_ip(pool, ip_address).await?;
+
     // 修改SQL查询以同时获取 is_admin 字段
     let row = sqlx::query(
         "SELECT u.*, c.company_type FROM users u JOIN companies c ON u.company_id = c.id WHERE u.email = ?"
     )
         .bind(&dto.email)
         .fetch_optional(pool)
-        .await?
-        .ok_or(AppError::AuthError)?;
+        .await?;
+    let Some(row) = row else {
+        login_guard_service::record_failure(pool, &dto.email, ip_address, None).await?;
+        return Err(AppError::AuthError);
+    };
 
     // 从查询结果中提取密码哈希和公司类型
     let password_hash: String = row.try_get("password_hash")?;
     let company_type: String = row.try_get("company_type")?;
+    let user: User = User::from_row(&row)?;
+
+    // 锁定期间不校验密码
+    login_guard_service::check_account(&user)?;
 
     // 2. 验证密码
     let valid_password = auth_utils::verify_password(&dto.password, &password_hash)
         .map_err(|_| AppError::InternalServerError("Password verification error".to_string()))?;
 
     if !valid_password {
+        login_guard_service::record_failure(pool, &dto.email, ip_address, Some(&user)).await?;
         return Err(AppError::AuthError); // 如果密码不匹配，返回认证失败
     }
 
     // 3. 创建JWT
-    let user: User = User::from_row(&row)?;
     // --- 【关键新增】检查用户是否被禁用 ---
     if !user.is_active {
         return Err(AppError::BadRequest("This account has been disabled.".to_string()));
     }
 
+    // 开启了两步验证的用户还需要提交验证码才能完成登录，验证码通过后才算登录成功并清空失败次数
+    if user.totp_enabled_at.is_some() {
+        let challenge = mfa_service::create_challenge(pool, user.id).await?;
+        return Ok(LoginResult::MfaRequired(challenge));
+    }
+    login_guard_service::record_success(pool, &dto.email, ip_address, user.id).await?;
+
+    let mut tx = pool.begin().await?;
+    let response = start_session(&mut tx, &user, &company_type).await?;
+    tx.commit().await?;
+
+    Ok(LoginResult::Session(response))
+}
+
+/// 为用户开启一个新的登录会话，签发访问令牌和刷新令牌
+pub(crate) async fn start_session(
+    conn: &mut MySqlConnection,
+    user: &User,
+    company_type: &str,
+) -> Result<LoginResponse, AppError> {
+    let refresh_token = auth_utils::generate_token();
+    let session_id = sqlx::query("INSERT INTO user_sessions (user_id, refresh_token_hash, expires_at) VALUES (?, ?, ?)")
+        .bind(user.id)
+        .bind(auth_utils::hash_token(&refresh_token))
+        .bind(Utc::now() + Duration::days(SESSION_EXPIRY_DAYS))
+        .execute(&mut *conn)
+        .await?
+        .last_insert_id() as i32;
+
+    let token = auth_utils::create_jwt(user, company_type, session_id)
+        .map_err(|_| AppError::InternalServerError("Failed to create token".to_string()))?;
+    Ok(LoginResponse::new(token, refresh_token, auth_utils::ACCESS_TOKEN_MINUTES * 60))
+}
+
+/// 用刷新令牌换取新的访问令牌和刷新令牌（旧的刷新令牌随即失效）
+pub async fn refresh_session(pool: &MySqlPool, dto: RefreshTokenDto) -> Result<LoginResponse, AppError> {
+    let token_hash = auth_utils::hash_token(&dto.refresh_token);
+
+    let mut tx = pool.begin().await?;
+    let session: Option<(i32, i32, String, bool, bool)> = sqlx::query_as(
+        "SELECT id, user_id, refresh_token_hash, revoked_at IS NULL, expires_at > NOW()
+         FROM user_sessions WHERE refresh_token_hash = ? OR previous_refresh_token_hash = ? FOR UPDATE"
+    )
+        .bind(&token_hash)
+        .bind(&token_hash)
+        .fetch_optional(&mut *tx)
+        .await?;
+    let (session_id, user_id, current_hash, active, unexpired) = session.ok_or(AppError::AuthError)?;
+
+    // 已轮换掉的刷新令牌又被使用，说明令牌可能被盗用，撤销整个会话
+    if current_hash != token_hash {
+        log::warn!("Refresh token reuse detected for session #{} of user #{}; revoking session", session_id, user_id);
+        sqlx::query("UPDATE user_sessions SET revoked_at = COALESCE(revoked_at, NOW()) WHERE id = ?")
+            .bind(session_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        return Err(AppError::AuthError);
+    }
+    if !active || !unexpired {
+        return Err(AppError::AuthError);
+    }
 
+    let row = sqlx::query("SELECT u.*, c.company_type FROM users u JOIN companies c ON u.company_id = c.id WHERE u.id = ?")
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    let user = User::from_row(&row)?;
     let company_type: String = row.try_get("company_type")?;
+    if !user.is_active {
+        return Err(AppError::AuthError);
+    }
 
-    let token = auth_utils::create_jwt(user.id, user.company_id, &company_type, user.is_admin) // <-- 传入 is_admin
+    let refresh_token = auth_utils::generate_token();
+    sqlx::query(
+        "UPDATE user_sessions SET previous_refresh_token_hash = refresh_token_hash, refresh_token_hash = ?, last_used_at = NOW() WHERE id = ?"
+    )
+        .bind(auth_utils::hash_token(&refresh_token))
+        .bind(session_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    let token = auth_utils::create_jwt(&user, &company_type, session_id)
         .map_err(|_| AppError::InternalServerError("Failed to create token".to_string()))?;
+    Ok(LoginResponse::new(token, refresh_token, auth_utils::ACCESS_TOKEN_MINUTES * 60))
+}
 
-    Ok(token)
+/// 撤销用户的登录会话。except_session_id 指定的会话（例如当前会话）会被保留
+pub(crate) async fn revoke_sessions(
+    conn: &mut MySqlConnection,
+    user_id: i32,
+    except_session_id: Option<i32>,
+) -> Result<u64, AppError> {
+    let result = sqlx::query(
+        "UPDATE user_sessions SET revoked_at = NOW() WHERE user_id = ? AND revoked_at IS NULL AND id <> ?"
+    )
+        .bind(user_id)
+        .bind(except_session_id.unwrap_or(0))
+        .execute(&mut *conn)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// 登出当前会话
+pub async fn logout(pool: &MySqlPool, claims: &Claims) -> Result<(), AppError> {
+    sqlx::query("UPDATE user_sessions SET revoked_at = NOW() WHERE id = ? AND user_id = ? AND revoked_at IS NULL")
+        .bind(claims.sid)
+        .bind(claims.sub)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// 登出所有设备，返回撤销的会话数
+pub async fn logout_all(pool: &MySqlPool, claims: &Claims) -> Result<u64, AppError> {
+    let mut conn = pool.acquire().await?;
+    revoke_sessions(&mut conn, claims.sub, None).await
+}
+
+/// 校验请求携带的JWT：会话未被撤销、用户未被禁用、令牌签发后没有修改过密码。同时读取邮箱验证和两步验证状态
+pub async fn authenticate(pool: &MySqlPool, token: &str) -> Result<Claims, AppError> {
+    let mut claims = auth_utils::validate_jwt(token).map_err(|_| AppError::AuthError)?;
+
+    let (token_version, is_active, is_admin, role, email_verified, mfa_enabled): (i32, bool, bool, String, bool, bool) = sqlx::query_as(
+        "SELECT u.token_version, u.is_active, u.is_admin, u.role, u.email_verified_at IS NOT NULL, u.totp_enabled_at IS NOT NULL
+         FROM users u JOIN user_sessions s ON s.user_id = u.id
+         WHERE u.id = ? AND s.id = ? AND s.revoked_at IS NULL"
+    )
+        .bind(claims.sub)
+        .bind(claims.sid)
+        .fetch_optional(pool)
+        .await?
+        .ok_or(AppError::AuthError)?;
+    if token_version != claims.ver || !is_active {
+        return Err(AppError::AuthError);
+    }
+    // 管理员身份和公司内角色以数据库为准，修改后无需等待令牌过期
+    claims.is_admin = is_admin;
+    claims.role = role;
+    claims.email_verified = email_verified;
+    claims.mfa_enabled = mfa_enabled;
+
+    Ok(claims)
+}
+
+/// 申请重置密码。无论邮箱是否存在都返回成功，避免泄露哪些邮箱已注册
+pub async fn request_password_reset(pool: &MySqlPool, dto: ForgotPasswordDto) -> Result<(), AppError> {
+    let user: Option<(i32,)> = sqlx::query_as("SELECT id FROM users WHERE email = ? AND is_active = TRUE")
+        .bind(dto.email.trim())
+        .fetch_optional(pool)
+        .await?;
+    let Some((user_id,)) = user else {
+        log::info!("Password reset requested for unknown email");
+        return Ok(());
+    };
+
+    let token = auth_utils::generate_token();
+    let expires_at = Utc::now() + Duration::minutes(PASSWORD_RESET_EXPIRY_MINUTES);
+
+    // 新的令牌生效后，之前未使用的令牌全部作废
+    let mut tx = pool.begin().await?;
+    sqlx::query("UPDATE password_reset_tokens SET used_at = NOW() WHERE user_id = ? AND used_at IS NULL")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("INSERT INTO password_reset_tokens (user_id, token_hash, expires_at) VALUES (?, ?, ?)")
+        .bind(user_id)
+        .bind(auth_utils::hash_token(&token))
+        .bind(expires_at)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    let subject = "Reset your SCCP password".to_string();
+    let body = format!(
+        "Hello,\n\nWe received a request to reset your SCCP password. Use the link below within {} minutes:\n{}/reset-password?token={}\n\nIf you did not request a password reset, you can ignore this email.",
+        PASSWORD_RESET_EXPIRY_MINUTES, frontend_url(), token
+    );
+    // 发信失败时同样返回成功，否则响应差异会暴露该邮箱已注册
+    if let Err(e) = notification_service::send_email(dto.email.trim().to_string(), subject, body).await {
+        log::error!("Failed to send password reset email to user {}: {:?}", user_id, e);
+    }
+    Ok(())
+}
+
+/// 使用邮件中的一次性令牌设置新密码。成功后该用户所有已登录的会话失效
+pub async fn reset_password(pool: &MySqlPool, dto: ResetPasswordDto) -> Result<(), AppError> {
+    if dto.new_password.len() < 6 {
+        return Err(AppError::BadRequest("New password must be at least 6 characters long.".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+    let (token_id, user_id): (i32, i32) = sqlx::query_as(
+        "SELECT id, user_id FROM password_reset_tokens WHERE token_hash = ? AND used_at IS NULL AND expires_at > NOW() FOR UPDATE"
+    )
+        .bind(auth_utils::hash_token(&dto.token))
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("This reset link is invalid or has expired.".to_string()))?;
+
+    let password_hash = auth_utils::hash_password(&dto.new_password)
+        .map_err(|_| AppError::InternalServerError("Failed to hash new password".to_string()))?;
+    sqlx::query("UPDATE users SET password_hash = ?, token_version = token_version + 1, failed_login_count = 0, locked_until = NULL WHERE id = ?")
+        .bind(password_hash)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("UPDATE password_reset_tokens SET used_at = NOW() WHERE id = ?")
+        .bind(token_id)
+        .execute(&mut *tx)
+        .await?;
+    revoke_sessions(&mut tx, user_id, None).await?;
+    tx.commit().await?;
+
+    log::info!("Password reset completed for user #{}", user_id);
+    Ok(())
 }
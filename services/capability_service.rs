@@ -2,7 +2,8 @@
 
 use crate::{
     errors::AppError,
-    models::{capability::Capability, user::Claims},
+    models::{capability::{Capability, CapabilityDto}, user::{Claims, OPERATOR_ROLES}},
+    utils::auth_utils,
 };
 use sqlx::MySqlPool;
 
@@ -31,6 +32,7 @@ pub async fn add_capability_to_company(
     claims: &Claims,
     capability_id: i32,
 ) -> Result<(), AppError> {
+    auth_utils::require_role(claims, OPERATOR_ROLES)?;
     // 权限检查：只有供应商才能为自己添加能力
     if claims.company_type != "SUPPLIER" {
         return Err(AppError::BadRequest("Only suppliers can add capabilities.".to_string()));
@@ -51,6 +53,7 @@ pub async fn remove_capability_from_company(
     claims: &Claims,
     capability_id: i32,
 ) -> Result<(), AppError> {
+    auth_utils::require_role(claims, OPERATOR_ROLES)?;
     if claims.company_type != "SUPPLIER" {
         return Err(AppError::BadRequest("Only suppliers can remove capabilities.".to_string()));
     }
@@ -62,4 +65,76 @@ pub async fn remove_capability_from_company(
         .await?;
 
     Ok(())
-}
\ No newline at end of file
+}
+// --- 管理后台：维护能力标签分类 ---
+
+fn validate_capability(dto: &CapabilityDto) -> Result<(String, String), AppError> {
+    let name = dto.name.trim();
+    let category = dto.category.trim();
+    if name.is_empty() || category.is_empty() {
+        return Err(AppError::BadRequest("Capability name and category are required.".to_string()));
+    }
+    if name.len() > 100 || category.len() > 100 {
+        return Err(AppError::BadRequest("Capability name and category must be at most 100 characters.".to_string()));
+    }
+    Ok((name.to_string(), category.to_string()))
+}
+
+async fn ensure_unique_capability(pool: &MySqlPool, name: &str, category: &str, exclude_id: i32) -> Result<(), AppError> {
+    let existing: Option<(i32,)> = sqlx::query_as("SELECT id FROM capabilities WHERE name = ? AND category = ? AND id <> ?")
+        .bind(name)
+        .bind(category)
+        .bind(exclude_id)
+        .fetch_optional(pool)
+        .await?;
+    if existing.is_some() {
+        return Err(AppError::BadRequest("A capability with this name already exists in the category.".to_string()));
+    }
+    Ok(())
+}
+
+pub async fn create_capability(pool: &MySqlPool, dto: CapabilityDto) -> Result<Capability, AppError> {
+    let (name, category) = validate_capability(&dto)?;
+    ensure_unique_capability(pool, &name, &category, 0).await?;
+
+    let result = sqlx::query("INSERT INTO capabilities (name, category) VALUES (?, ?)")
+        .bind(&name)
+        .bind(&category)
+        .execute(pool)
+        .await?;
+    Ok(Capability { id: result.last_insert_id() as i32, name, category })
+}
+
+pub async fn update_capability(pool: &MySqlPool, capability_id: i32, dto: CapabilityDto) -> Result<Capability, AppError> {
+    let (name, category) = validate_capability(&dto)?;
+    ensure_unique_capability(pool, &name, &category, capability_id).await?;
+
+    let result = sqlx::query("UPDATE capabilities SET name = ?, category = ? WHERE id = ?")
+        .bind(&name)
+        .bind(&category)
+        .bind(capability_id)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::BadRequest("Capability not found".to_string()));
+    }
+    Ok(Capability { id: capability_id, name, category })
+}
+
+/// 删除能力标签，同时从所有公司中移除
+pub async fn delete_capability(pool: &MySqlPool, capability_id: i32) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM company_capabilities WHERE capability_id = ?")
+        .bind(capability_id)
+        .execute(&mut *tx)
+        .await?;
+    let result = sqlx::query("DELETE FROM capabilities WHERE id = ?")
+        .bind(capability_id)
+        .execute(&mut *tx)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::BadRequest("Capability not found".to_string()));
+    }
+    tx.commit().await?;
+    Ok(())
+}
@@ -0,0 +1,290 @@
+// src/services/change_order_service.rs
+
+use crate::{
+    errors::AppError,
+    models::{
+        order::{ChangeRequest, ChangeRequestDto, OrderVersion, RespondChangeRequestDto},
+        user::{Claims, APPROVER_ROLES, OPERATOR_ROLES},
+    },
+    services::{chat_server::ChatServer, notification_service, order_service, shipment_service},
+    utils::auth_utils,
+};
+use actix::Addr;
+use sqlx::{types::Decimal, MySqlConnection, MySqlPool};
+use std::str::FromStr;
+
+// 变更单只能在订单执行过程中提出，发货完成后不再允许修改条款
+const CHANGEABLE_STATUSES: &[&str] = &["PENDING_CONFIRMATION", "CONFIRMED", "IN_PRODUCTION"];
+
+// 当前订单条款：(quantity, unit_price, total_amount, version, payment_status)
+async fn current_terms(conn: &mut MySqlConnection, order_id: i32) -> Result<(i32, Decimal, Decimal, i32, String), AppError> {
+    let terms = sqlx::query_as("SELECT quantity, unit_price, total_amount, version, payment_status FROM purchase_orders WHERE id = ?")
+        .bind(order_id)
+        .fetch_one(&mut *conn)
+        .await?;
+    Ok(terms)
+}
+
+// 变更后的订单总额。单价只保留4位小数，按单价重新计算会与授标时的总额有出入，
+// 所以只修改交期时保留原总额，数量或单价变化时才重新计算
+fn changed_total(
+    current_total: Decimal,
+    quantity: i32,
+    unit_price: Decimal,
+    new_quantity: Option<i32>,
+    new_unit_price: Option<Decimal>,
+) -> Decimal {
+    if new_quantity.is_none() && new_unit_price.is_none() {
+        return current_total;
+    }
+    let quantity = new_quantity.unwrap_or(quantity);
+    let unit_price = new_unit_price.unwrap_or(unit_price);
+    (unit_price * Decimal::from(quantity)).round_dp(2)
+}
+
+// 检查变更在当前订单状态下是否仍然可行
+async fn validate_change(
+    conn: &mut MySqlConnection,
+    order_id: i32,
+    current_status: &str,
+    new_quantity: Option<i32>,
+    new_unit_price: Option<Decimal>,
+    payment_status: &str,
+) -> Result<(), AppError> {
+    if !CHANGEABLE_STATUSES.contains(&current_status) {
+        return Err(AppError::BadRequest(format!("An order in status {} can no longer be changed.", current_status)));
+    }
+    if (new_quantity.is_some() || new_unit_price.is_some()) && !matches!(payment_status, "UNPAID" | "FAILED") {
+        return Err(AppError::BadRequest("Quantity and price cannot be changed once payment has started.".to_string()));
+    }
+    if let Some(quantity) = new_quantity {
+        let (shipped_total, rejected_total) = shipment_service::shipment_totals(conn, order_id).await?;
+        if (quantity as i64) < shipped_total - rejected_total {
+            return Err(AppError::BadRequest(format!(
+                "Quantity cannot be lower than the {} units already shipped.",
+                shipped_total - rejected_total
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// 订单一方提出变更（数量、单价、交期），需要对方批准
+pub async fn request_change(
+    pool: &MySqlPool,
+    chat_server: &Addr<ChatServer>,
+    order_id: i32,
+    dto: ChangeRequestDto,
+    claims: &Claims,
+) -> Result<u64, AppError> {
+    auth_utils::require_role(claims, OPERATOR_ROLES)?;
+    if dto.reason.trim().is_empty() {
+        return Err(AppError::BadRequest("A reason is required for a change order.".to_string()));
+    }
+    if dto.quantity.is_none() && dto.unit_price.is_none() && dto.committed_ship_date.is_none() {
+        return Err(AppError::BadRequest("A change order must change the quantity, price or ship date.".to_string()));
+    }
+    if dto.quantity.is_some_and(|q| q <= 0) {
+        return Err(AppError::BadRequest("Quantity must be positive.".to_string()));
+    }
+    let new_unit_price = match dto.unit_price {
+        Some(price) if price <= 0.0 => return Err(AppError::BadRequest("Unit price must be positive.".to_string())),
+        Some(price) => Some(
+            Decimal::from_str(&price.to_string())
+                .map_err(|_| AppError::BadRequest("Invalid price format".to_string()))?,
+        ),
+        None => None,
+    };
+    if dto.committed_ship_date.is_some_and(|d| d < chrono::Utc::now().date_naive()) {
+        return Err(AppError::BadRequest("Ship date cannot be in the past.".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+    let (current_status, buyer_company_id, supplier_company_id) =
+        order_service::lock_order(&mut tx, order_id, claims).await?;
+    let (_, _, _, version, payment_status) = current_terms(&mut tx, order_id).await?;
+
+    validate_change(&mut tx, order_id, &current_status, dto.quantity, new_unit_price, &payment_status).await?;
+
+    let pending: Option<(i32,)> = sqlx::query_as(
+        "SELECT id FROM order_change_requests WHERE order_id = ? AND status = 'PENDING'"
+    )
+        .bind(order_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    if pending.is_some() {
+        return Err(AppError::BadRequest("There is already a pending change order for this order.".to_string()));
+    }
+
+    let result = sqlx::query(
+        "INSERT INTO order_change_requests (order_id, base_version, requested_by_company_id, requested_by_user_id, new_quantity, new_unit_price, new_ship_date, reason)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+        .bind(order_id)
+        .bind(version)
+        .bind(claims.company_id)
+        .bind(claims.sub)
+        .bind(dto.quantity)
+        .bind(new_unit_price)
+        .bind(dto.committed_ship_date)
+        .bind(&dto.reason)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    let counterparty_id = if claims.company_id == buyer_company_id { supplier_company_id } else { buyer_company_id };
+    if let Err(e) = notification_service::notify_company(
+        pool,
+        chat_server,
+        counterparty_id,
+        format!("A change order was requested on order #{}: {}", order_id, dto.reason),
+        format!("/orders/{}", order_id),
+    ).await {
+        log::error!("Failed to send change order notification: {:?}", e);
+    }
+
+    Ok(result.last_insert_id())
+}
+
+/// 对方批准或拒绝变更单。批准后订单条款更新，版本号加一，数量或单价变化时重新计算订单总额
+pub async fn respond_to_change(
+    pool: &MySqlPool,
+    chat_server: &Addr<ChatServer>,
+    order_id: i32,
+    change_request_id: i32,
+    dto: RespondChangeRequestDto,
+    claims: &Claims,
+) -> Result<(), AppError> {
+    auth_utils::require_role(claims, APPROVER_ROLES)?;
+    let mut tx = pool.begin().await?;
+    let (current_status, _, _) = order_service::lock_order(&mut tx, order_id, claims).await?;
+
+    let request: ChangeRequest = sqlx::query_as(
+        "SELECT * FROM order_change_requests WHERE id = ? AND order_id = ? FOR UPDATE"
+    )
+        .bind(change_request_id)
+        .bind(order_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("Change order not found".to_string()))?;
+
+    if request.status != "PENDING" {
+        return Err(AppError::BadRequest("This change order has already been answered.".to_string()));
+    }
+    // 发起方不能自己批准自己的变更单
+    if request.requested_by_company_id == claims.company_id {
+        return Err(AppError::BadRequest("The other party must respond to this change order.".to_string()));
+    }
+
+    let new_request_status = if dto.approve { "APPROVED" } else { "REJECTED" };
+    sqlx::query(
+        "UPDATE order_change_requests SET status = ?, responded_by_user_id = ?, response_note = ?, responded_at = NOW() WHERE id = ?"
+    )
+        .bind(new_request_status)
+        .bind(claims.sub)
+        .bind(&dto.note)
+        .bind(change_request_id)
+        .execute(&mut *tx)
+        .await?;
+
+    if dto.approve {
+        let (quantity, unit_price, total_amount, version, payment_status) = current_terms(&mut tx, order_id).await?;
+        if version != request.base_version {
+            return Err(AppError::BadRequest("The order has changed since this change order was requested.".to_string()));
+        }
+        validate_change(&mut tx, order_id, &current_status, request.new_quantity, request.new_unit_price, &payment_status).await?;
+
+        let total_amount = changed_total(total_amount, quantity, unit_price, request.new_quantity, request.new_unit_price);
+        let quantity = request.new_quantity.unwrap_or(quantity);
+        let unit_price = request.new_unit_price.unwrap_or(unit_price);
+        let new_version = version + 1;
+
+        sqlx::query(
+            "UPDATE purchase_orders SET quantity = ?, unit_price = ?, total_amount = ?, committed_ship_date = COALESCE(?, committed_ship_date), version = ? WHERE id = ?"
+        )
+            .bind(quantity)
+            .bind(unit_price)
+            .bind(total_amount)
+            .bind(request.new_ship_date)
+            .bind(new_version)
+            .bind(order_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(
+            "INSERT INTO purchase_order_versions (order_id, version, quantity, unit_price, total_amount, committed_ship_date, change_request_id)
+             SELECT id, version, quantity, unit_price, total_amount, committed_ship_date, ? FROM purchase_orders WHERE id = ?"
+        )
+            .bind(change_request_id)
+            .bind(order_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    let message = if dto.approve {
+        format!("Your change order for order #{} was approved.", order_id)
+    } else {
+        format!("Your change order for order #{} was rejected.", order_id)
+    };
+    if let Err(e) = notification_service::notify_company(
+        pool,
+        chat_server,
+        request.requested_by_company_id,
+        message,
+        format!("/orders/{}", order_id),
+    ).await {
+        log::error!("Failed to send change order response notification: {:?}", e);
+    }
+
+    Ok(())
+}
+
+/// 获取订单的全部变更单
+pub async fn get_change_requests(
+    pool: &MySqlPool,
+    order_id: i32,
+    claims: &Claims,
+) -> Result<Vec<ChangeRequest>, AppError> {
+    order_service::ensure_order_party(pool, order_id, claims).await?;
+
+    let requests = sqlx::query_as("SELECT * FROM order_change_requests WHERE order_id = ? ORDER BY created_at DESC, id DESC")
+        .bind(order_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(requests)
+}
+
+/// 获取订单的全部版本快照
+pub async fn get_order_versions(
+    pool: &MySqlPool,
+    order_id: i32,
+    claims: &Claims,
+) -> Result<Vec<OrderVersion>, AppError> {
+    order_service::ensure_order_party(pool, order_id, claims).await?;
+
+    let versions = sqlx::query_as("SELECT * FROM purchase_order_versions WHERE order_id = ? ORDER BY version ASC")
+        .bind(order_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(versions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_total() {
+        // 12345.67 / 10000 保存为 1.2346，仅修改交期时总额不变
+        let total = Decimal::from_str("12345.67").unwrap();
+        let unit_price = (total / Decimal::from(10000)).round_dp(4);
+        assert_eq!(changed_total(total, 10000, unit_price, None, None), total);
+
+        assert_eq!(changed_total(total, 10000, unit_price, Some(5000), None), Decimal::from_str("6173.00").unwrap());
+        assert_eq!(
+            changed_total(total, 10000, unit_price, None, Some(Decimal::from_str("1.5").unwrap())),
+            Decimal::from(15000)
+        );
+    }
+}
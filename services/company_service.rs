@@ -2,12 +2,17 @@
 
 use crate::{
     errors::AppError,
-    models::{company::{CompanyProfile, UpdateCompanyDto}, user::Claims},
+    models::{
+        company::{CompanyProfile, UpdateCompanyDto},
+        user::{roles_for_company_type, Claims, CompanyMember, UpdateRoleDto, ROLE_OWNER},
+    },
+    services::{audit_service::AuditEntry, fx_service},
+    utils::auth_utils,
 };
 use sqlx::MySqlPool;
 
 pub async fn get_company_by_id(pool: &MySqlPool, company_id: i32) -> Result<CompanyProfile, AppError> {
-    let profile = sqlx::query_as("SELECT id, name, company_type, city, description, is_verified, created_at FROM companies WHERE id = ?")
+    let profile = sqlx::query_as("SELECT id, name, company_type, city, address, description, base_currency, is_verified, created_at FROM companies WHERE id = ?")
         .bind(company_id)
         .fetch_one(pool)
         .await?;
@@ -24,12 +29,90 @@ pub async fn update_company_profile(
     if claims.company_id != company_id {
         return Err(AppError::BadRequest("You are not authorized to edit this company profile.".to_string()));
     }
+    auth_utils::require_role(claims, &[ROLE_OWNER])?;
 
-    let result = sqlx::query("UPDATE companies SET description = ? WHERE id = ?")
+    let base_currency = dto.base_currency.as_deref().map(fx_service::normalize_currency).transpose()?;
+
+    // 未提供地址或本位币时保留原值
+    let result = sqlx::query("UPDATE companies SET description = ?, address = COALESCE(?, address), base_currency = COALESCE(?, base_currency) WHERE id = ?")
         .bind(dto.description)
+        .bind(dto.address)
+        .bind(base_currency)
         .bind(company_id)
         .execute(pool)
         .await?;
 
     Ok(result.rows_affected())
-}
\ No newline at end of file
+}
+
+/// 列出本公司的所有用户及其角色
+pub async fn list_members(pool: &MySqlPool, company_id: i32, claims: &Claims) -> Result<Vec<CompanyMember>, AppError> {
+    if claims.company_id != company_id {
+        return Err(AppError::BadRequest("You can only view members of your own company.".to_string()));
+    }
+
+    let members = sqlx::query_as(
+        "SELECT id, full_name, email, role, is_active FROM users WHERE company_id = ? ORDER BY id"
+    )
+        .bind(company_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(members)
+}
+
+/// 公司所有者修改成员的角色。新角色对该成员的下一个请求即生效；公司至少要保留一个所有者
+pub async fn update_member_role(
+    pool: &MySqlPool,
+    company_id: i32,
+    user_id: i32,
+    dto: UpdateRoleDto,
+    claims: &Claims,
+) -> Result<(), AppError> {
+    if claims.company_id != company_id {
+        return Err(AppError::BadRequest("You can only manage members of your own company.".to_string()));
+    }
+    auth_utils::require_role(claims, &[ROLE_OWNER])?;
+
+    let role = dto.role.trim().to_uppercase();
+    let allowed = roles_for_company_type(&claims.company_type);
+    if !allowed.contains(&role.as_str()) {
+        return Err(AppError::BadRequest(format!(
+            "Invalid role '{}'. Allowed roles: {}",
+            role,
+            allowed.join(", ")
+        )));
+    }
+
+    let mut tx = pool.begin().await?;
+    let (current_role,): (String,) = sqlx::query_as("SELECT role FROM users WHERE id = ? AND company_id = ? FOR UPDATE")
+        .bind(user_id)
+        .bind(company_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("User not found in this company.".to_string()))?;
+
+    if current_role == ROLE_OWNER && role != ROLE_OWNER {
+        let (owners,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM users WHERE company_id = ? AND role = 'OWNER' AND is_active = TRUE FOR UPDATE"
+        )
+            .bind(company_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        if owners <= 1 {
+            return Err(AppError::BadRequest("A company must keep at least one owner.".to_string()));
+        }
+    }
+
+    sqlx::query("UPDATE users SET role = ? WHERE id = ?")
+        .bind(&role)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    AuditEntry::new(claims, "MEMBER_ROLE_CHANGED", "USER", user_id)
+        .before(serde_json::json!({ "role": current_role }))
+        .after(serde_json::json!({ "role": role }))
+        .record(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
@@ -0,0 +1,321 @@
+// src/services/dispute_service.rs
+
+use crate::{
+    errors::AppError,
+    models::{
+        dispute::{Dispute, DisputeEvidence, DisputeMessage, DisputeMessageDto, OpenDisputeDto, ResolveDisputeDto},
+        user::{Claims, ADMIN_PERM_DISPUTE_RESOLUTION, OPERATOR_ROLES},
+    },
+    services::{admin_service, audit_service::AuditEntry, chat_server::ChatServer, notification_service, order_service, rfq_service},
+    utils::auth_utils,
+};
+use actix::Addr;
+use futures_util::stream::StreamExt;
+use sqlx::MySqlPool;
+
+// 争议证据不放在公开的 ./uploads 目录下，只能通过带权限检查的下载接口获取
+const EVIDENCE_UPLOAD_DIR: &str = "./private_uploads/disputes";
+
+/// 采购方对已发货/已收货的订单发起争议，订单进入 DISPUTED 状态
+pub async fn open_dispute(
+    pool: &MySqlPool,
+    chat_server: &Addr<ChatServer>,
+    order_id: i32,
+    dto: OpenDisputeDto,
+    claims: &Claims,
+) -> Result<u64, AppError> {
+    auth_utils::require_role(claims, OPERATOR_ROLES)?;
+    if dto.reason.trim().is_empty() {
+        return Err(AppError::BadRequest("A reason is required to open a dispute.".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+    let (current_status, _, supplier_company_id) = order_service::lock_order(&mut tx, order_id, claims).await?;
+
+    if !order_service::can_transition(&current_status, "DISPUTED", &claims.company_type) {
+        return Err(AppError::BadRequest(format!("A dispute cannot be opened for an order in status {}.", current_status)));
+    }
+
+    let result = sqlx::query(
+        "INSERT INTO order_disputes (order_id, opened_by_company_id, opened_by_user_id, reason, previous_order_status) VALUES (?, ?, ?, ?, ?)"
+    )
+        .bind(order_id)
+        .bind(claims.company_id)
+        .bind(claims.sub)
+        .bind(&dto.reason)
+        .bind(&current_status)
+        .execute(&mut *tx)
+        .await?;
+
+    order_service::apply_status_change(&mut tx, order_id, Some(&current_status), "DISPUTED", claims, Some(dto.reason.clone())).await?;
+    tx.commit().await?;
+
+    if let Err(e) = notification_service::notify_company(
+        pool,
+        chat_server,
+        supplier_company_id,
+        format!("A dispute was opened on order #{}: {}", order_id, dto.reason),
+        format!("/orders/{}", order_id),
+    ).await {
+        log::error!("Failed to send dispute notification: {:?}", e);
+    }
+
+    Ok(result.last_insert_id())
+}
+
+// 订单双方、超级管理员以及拥有争议处理权限的运营人员可以查看和参与争议。
+// 管理身份以数据库为准，与 AdminAuth 中间件一致；API密钥不能以管理身份访问
+async fn ensure_dispute_access(pool: &MySqlPool, order_id: i32, claims: &Claims) -> Result<(), AppError> {
+    if claims.api_key_scopes.is_none() {
+        let (is_admin, permissions) = admin_service::load_admin_access(pool, claims.sub).await?;
+        if is_admin || permissions.iter().any(|p| p == ADMIN_PERM_DISPUTE_RESOLUTION) {
+            return Ok(());
+        }
+    }
+    order_service::ensure_order_party(pool, order_id, claims).await
+}
+
+/// 获取订单的所有争议
+pub async fn get_disputes_for_order(
+    pool: &MySqlPool,
+    order_id: i32,
+    claims: &Claims,
+) -> Result<Vec<Dispute>, AppError> {
+    ensure_dispute_access(pool, order_id, claims).await?;
+
+    let disputes = sqlx::query_as("SELECT * FROM order_disputes WHERE order_id = ? ORDER BY created_at DESC")
+        .bind(order_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(disputes)
+}
+
+// 确保争议属于该订单，且操作者是订单的一方或有争议处理权限的管理人员
+async fn get_accessible_dispute(
+    pool: &MySqlPool,
+    order_id: i32,
+    dispute_id: i32,
+    claims: &Claims,
+) -> Result<Dispute, AppError> {
+    ensure_dispute_access(pool, order_id, claims).await?;
+
+    sqlx::query_as("SELECT * FROM order_disputes WHERE id = ? AND order_id = ?")
+        .bind(dispute_id)
+        .bind(order_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("Dispute not found".to_string()))
+}
+
+/// 在争议中发送一条消息（订单双方和管理员均可）
+pub async fn post_dispute_message(
+    pool: &MySqlPool,
+    order_id: i32,
+    dispute_id: i32,
+    dto: DisputeMessageDto,
+    claims: &Claims,
+) -> Result<u64, AppError> {
+    auth_utils::require_role(claims, OPERATOR_ROLES)?;
+    let dispute = get_accessible_dispute(pool, order_id, dispute_id, claims).await?;
+
+    if dispute.status != "OPEN" {
+        return Err(AppError::BadRequest("This dispute has already been resolved.".to_string()));
+    }
+    if dto.message.trim().is_empty() {
+        return Err(AppError::BadRequest("Message cannot be empty.".to_string()));
+    }
+
+    let result = sqlx::query(
+        "INSERT INTO dispute_messages (dispute_id, sender_user_id, sender_company_id, message) VALUES (?, ?, ?, ?)"
+    )
+        .bind(dispute_id)
+        .bind(claims.sub)
+        .bind(claims.company_id)
+        .bind(dto.message)
+        .execute(pool)
+        .await?;
+
+    Ok(result.last_insert_id())
+}
+
+/// 获取争议的消息记录
+pub async fn get_dispute_messages(
+    pool: &MySqlPool,
+    order_id: i32,
+    dispute_id: i32,
+    claims: &Claims,
+) -> Result<Vec<DisputeMessage>, AppError> {
+    get_accessible_dispute(pool, order_id, dispute_id, claims).await?;
+
+    let messages = sqlx::query_as("SELECT * FROM dispute_messages WHERE dispute_id = ? ORDER BY created_at ASC, id ASC")
+        .bind(dispute_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(messages)
+}
+
+/// 为争议上传证据文件
+pub async fn upload_dispute_evidence(
+    pool: &MySqlPool,
+    order_id: i32,
+    dispute_id: i32,
+    claims: &Claims,
+    mut payload: actix_multipart::Multipart,
+) -> Result<Vec<u64>, AppError> {
+    auth_utils::require_role(claims, OPERATOR_ROLES)?;
+    let dispute = get_accessible_dispute(pool, order_id, dispute_id, claims).await?;
+
+    if dispute.status != "OPEN" {
+        return Err(AppError::BadRequest("This dispute has already been resolved.".to_string()));
+    }
+
+    let mut evidence_ids = Vec::new();
+    while let Some(field_result) = payload.next().await {
+        let mut field = field_result?;
+
+        if let Some((filename, filepath)) = rfq_service::save_attachment_field(&mut field, EVIDENCE_UPLOAD_DIR).await? {
+            let result = sqlx::query(
+                "INSERT INTO dispute_evidence (dispute_id, uploaded_by_user_id, original_filename, stored_path) VALUES (?, ?, ?, ?)"
+            )
+                .bind(dispute_id)
+                .bind(claims.sub)
+                .bind(filename)
+                .bind(filepath)
+                .execute(pool)
+                .await?;
+            evidence_ids.push(result.last_insert_id());
+        }
+    }
+
+    if evidence_ids.is_empty() {
+        return Err(AppError::BadRequest("No evidence file found in the upload.".to_string()));
+    }
+
+    Ok(evidence_ids)
+}
+
+/// 获取争议的证据列表
+pub async fn get_dispute_evidence(
+    pool: &MySqlPool,
+    order_id: i32,
+    dispute_id: i32,
+    claims: &Claims,
+) -> Result<Vec<DisputeEvidence>, AppError> {
+    get_accessible_dispute(pool, order_id, dispute_id, claims).await?;
+
+    let evidence = sqlx::query_as("SELECT * FROM dispute_evidence WHERE dispute_id = ? ORDER BY created_at ASC")
+        .bind(dispute_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(evidence)
+}
+
+/// 获取单个证据文件（用于下载）
+pub async fn get_evidence_file(
+    pool: &MySqlPool,
+    order_id: i32,
+    dispute_id: i32,
+    evidence_id: i32,
+    claims: &Claims,
+) -> Result<DisputeEvidence, AppError> {
+    get_accessible_dispute(pool, order_id, dispute_id, claims).await?;
+
+    sqlx::query_as("SELECT * FROM dispute_evidence WHERE id = ? AND dispute_id = ?")
+        .bind(evidence_id)
+        .bind(dispute_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("Evidence not found".to_string()))
+}
+
+/// 管理员：获取所有未解决的争议
+pub async fn list_open_disputes(pool: &MySqlPool) -> Result<Vec<Dispute>, AppError> {
+    let disputes = sqlx::query_as("SELECT * FROM order_disputes WHERE status = 'OPEN' ORDER BY created_at ASC")
+        .fetch_all(pool)
+        .await?;
+    Ok(disputes)
+}
+
+/// 管理员裁决争议。订单可以恢复到争议前的状态，或者直接完成/取消
+pub async fn resolve_dispute(
+    pool: &MySqlPool,
+    chat_server: &Addr<ChatServer>,
+    dispute_id: i32,
+    dto: ResolveDisputeDto,
+    claims: &Claims,
+) -> Result<(), AppError> {
+    if dto.resolution.trim().is_empty() {
+        return Err(AppError::BadRequest("A resolution is required.".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+
+    let dispute: Dispute = sqlx::query_as("SELECT * FROM order_disputes WHERE id = ? FOR UPDATE")
+        .bind(dispute_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("Dispute not found".to_string()))?;
+
+    if dispute.status != "OPEN" {
+        return Err(AppError::BadRequest("This dispute has already been resolved.".to_string()));
+    }
+
+    let target_status = dto.order_status.as_str();
+    if target_status != dispute.previous_order_status && !matches!(target_status, "COMPLETED" | "CANCELLED") {
+        return Err(AppError::BadRequest(format!(
+            "A disputed order can only return to {} or be COMPLETED or CANCELLED.",
+            dispute.previous_order_status
+        )));
+    }
+
+    let (buyer_company_id, supplier_company_id): (i32, i32) = sqlx::query_as(
+        "SELECT buyer_company_id, supplier_company_id FROM purchase_orders WHERE id = ? FOR UPDATE"
+    )
+        .bind(dispute.order_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    sqlx::query(
+        "UPDATE order_disputes SET status = 'RESOLVED', resolution = ?, resolved_order_status = ?, resolved_by_user_id = ?, resolved_at = NOW() WHERE id = ?"
+    )
+        .bind(&dto.resolution)
+        .bind(target_status)
+        .bind(claims.sub)
+        .bind(dispute_id)
+        .execute(&mut *tx)
+        .await?;
+
+    order_service::apply_status_change(
+        &mut tx,
+        dispute.order_id,
+        Some("DISPUTED"),
+        target_status,
+        claims,
+        Some(format!("Dispute #{} resolved: {}", dispute_id, dto.resolution)),
+    ).await?;
+    // 争议由管理员处理，为订单双方各记录一条，双方的公司审计日志中都能看到
+    for company_id in [buyer_company_id, supplier_company_id] {
+        AuditEntry::new(claims, "DISPUTE_RESOLVED", "DISPUTE", dispute_id)
+            .company(company_id)
+            .before(serde_json::json!({ "status": dispute.status, "order_id": dispute.order_id }))
+            .after(serde_json::json!({ "status": "RESOLVED", "order_status": target_status, "resolution": dto.resolution }))
+            .record(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    let message = format!("The dispute on order #{} was resolved: {}", dispute.order_id, dto.resolution);
+    for company_id in [buyer_company_id, supplier_company_id] {
+        if let Err(e) = notification_service::notify_company(
+            pool,
+            chat_server,
+            company_id,
+            message.clone(),
+            format!("/orders/{}", dispute.order_id),
+        ).await {
+            log::error!("Failed to send dispute resolution notification: {:?}", e);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,476 @@
+// src/services/document_service.rs
+
+use crate::{
+    errors::AppError,
+    models::{invoice::Invoice, order::OrderDocument, user::Claims},
+    services::{invoice_service, order_service},
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use printpdf::{BuiltinFont, IndirectFontRef, Line, Mm, PdfDocument, PdfDocumentReference, PdfLayerReference, Point, TextRenderingMode};
+use sqlx::{types::Decimal, FromRow, MySqlPool};
+use std::sync::OnceLock;
+
+// A4 纵向，单位毫米
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 20.0;
+
+// 内置的 Helvetica 没有中文字形，文档嵌入一个支持中日韩字符的 TrueType 字体，可通过 PDF_FONT_PATH 指定。
+// 找不到字体文件时退回内置字体，文档照常生成，只是非拉丁字符显示为 '?'
+const DEFAULT_FONT_PATH: &str = "/usr/share/fonts/truetype/droid/DroidSansFallbackFull.ttf";
+
+pub const PURCHASE_ORDER: &str = "purchase-order";
+pub const INVOICE: &str = "invoice";
+
+// 生成订单文档所需的全部数据
+#[derive(FromRow)]
+struct OrderDocumentData {
+    id: i32,
+    version: i32,
+    quantity: i32,
+    unit_price: Decimal,
+    total_amount: Decimal,
+    currency: String,
+    status: String,
+    payment_status: String,
+    created_at: DateTime<Utc>,
+    committed_ship_date: Option<NaiveDate>,
+    rfq_title: String,
+    rfq_description: Option<String>,
+    lead_time_days: i32,
+    quote_notes: Option<String>,
+    buyer_name: String,
+    buyer_address: Option<String>,
+    buyer_city: Option<String>,
+    supplier_name: String,
+    supplier_address: Option<String>,
+    supplier_city: Option<String>,
+}
+
+pub fn purchase_order_number(order_id: i32) -> String {
+    format!("PO-{:06}", order_id)
+}
+
+// 交期取最后一次达成一致的议价，与授标时写入订单的条款保持一致
+async fn load_order_data(pool: &MySqlPool, order_id: i32, claims: &Claims) -> Result<OrderDocumentData, AppError> {
+    order_service::ensure_order_party(pool, order_id, claims).await?;
+
+    let data = sqlx::query_as(
+        "SELECT po.id, po.version, po.quantity, po.unit_price, po.total_amount, po.currency, po.status, po.payment_status, po.created_at, po.committed_ship_date,
+                r.title as rfq_title, r.description as rfq_description, q.notes as quote_notes,
+                COALESCE((SELECT qn.lead_time_days FROM quote_negotiations qn WHERE qn.quote_id = q.id AND qn.status = 'ACCEPTED' ORDER BY qn.id DESC LIMIT 1), q.lead_time_days) as lead_time_days,
+                b.name as buyer_name, b.address as buyer_address, b.city as buyer_city,
+                s.name as supplier_name, s.address as supplier_address, s.city as supplier_city
+         FROM purchase_orders po
+         JOIN rfqs r ON po.rfq_id = r.id
+         JOIN quotes q ON po.quote_id = q.id
+         JOIN companies b ON po.buyer_company_id = b.id
+         JOIN companies s ON po.supplier_company_id = s.id
+         WHERE po.id = ?"
+    )
+        .bind(order_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(data)
+}
+
+/// 列出订单当前可下载的文档：采购订单，以及已开具的每张发票
+pub async fn list_order_documents(pool: &MySqlPool, order_id: i32, claims: &Claims) -> Result<Vec<OrderDocument>, AppError> {
+    let order = load_order_data(pool, order_id, claims).await?;
+    let invoices = invoice_service::get_invoices_for_order(pool, order_id, claims).await?;
+
+    let mut documents = vec![OrderDocument {
+        document_type: PURCHASE_ORDER.to_string(),
+        number: purchase_order_number(order.id),
+        filename: format!("{}.pdf", purchase_order_number(order.id)),
+        url: format!("/api/orders/{}/documents/{}", order.id, PURCHASE_ORDER),
+    }];
+    documents.extend(invoices.into_iter().map(|invoice| OrderDocument {
+        document_type: INVOICE.to_string(),
+        filename: format!("{}.pdf", invoice.invoice_number),
+        number: invoice.invoice_number,
+        url: format!("/api/orders/{}/documents/invoices/{}", order.id, invoice.id),
+    }));
+    Ok(documents)
+}
+
+/// 生成采购订单PDF，返回 (文件名, PDF内容)
+pub async fn render_purchase_order_document(
+    pool: &MySqlPool,
+    order_id: i32,
+    claims: &Claims,
+) -> Result<(String, Vec<u8>), AppError> {
+    let order = load_order_data(pool, order_id, claims).await?;
+    let filename = format!("{}.pdf", purchase_order_number(order.id));
+    let bytes = render_purchase_order(&order)?;
+    Ok((filename, bytes))
+}
+
+/// 生成发票PDF，返回 (文件名, PDF内容)
+pub async fn render_invoice_document(
+    pool: &MySqlPool,
+    order_id: i32,
+    invoice_id: i32,
+    claims: &Claims,
+) -> Result<(String, Vec<u8>), AppError> {
+    let order = load_order_data(pool, order_id, claims).await?;
+    let invoice = invoice_service::get_invoice(pool, invoice_id, claims).await?;
+    if invoice.order_id != order_id {
+        return Err(AppError::BadRequest("Invoice not found for this order.".to_string()));
+    }
+
+    let filename = format!("{}.pdf", invoice.invoice_number);
+    let bytes = render_invoice(&order, &invoice)?;
+    Ok((filename, bytes))
+}
+
+fn render_purchase_order(order: &OrderDocumentData) -> Result<Vec<u8>, AppError> {
+    let number = purchase_order_number(order.id);
+    let mut pdf = PdfWriter::new(&number)?;
+
+    pdf.heading("PURCHASE ORDER");
+    pdf.line(&format!("PO Number: {}    Revision: {}", number, order.version));
+    pdf.line(&format!("Order Date: {}", order.created_at.format("%Y-%m-%d")));
+    pdf.line(&format!("Status: {}", order.status));
+    pdf.space(4.0);
+
+    pdf.parties(
+        ("Buyer", &order.buyer_name, &order.buyer_address, &order.buyer_city),
+        ("Supplier", &order.supplier_name, &order.supplier_address, &order.supplier_city),
+    );
+    line_items(&mut pdf, order);
+
+    pdf.subheading("Terms");
+    pdf.line(&format!("Lead time: {} days", order.lead_time_days));
+    match order.committed_ship_date {
+        Some(date) => pdf.line(&format!("Committed ship date: {}", date)),
+        None => pdf.line("Committed ship date: to be confirmed by the supplier"),
+    }
+    pdf.line(&format!("Payment: through the platform ({})", order.payment_status));
+    if let Some(notes) = order.quote_notes.as_deref().filter(|n| !n.trim().is_empty()) {
+        pdf.paragraph(&format!("Supplier notes: {}", notes));
+    }
+
+    pdf.finish()
+}
+
+fn render_invoice(order: &OrderDocumentData, invoice: &Invoice) -> Result<Vec<u8>, AppError> {
+    const COLUMNS: [f32; 4] = [MARGIN, 120.0, 145.0, 170.0];
+
+    let mut pdf = PdfWriter::new(&invoice.invoice_number)?;
+
+    pdf.heading("INVOICE");
+    pdf.line(&format!("Invoice Number: {}", invoice.invoice_number));
+    pdf.line(&format!("Invoice Date: {}", invoice.issue_date));
+    pdf.line(&format!("Due Date: {} (Net {})", invoice.due_date, invoice.payment_terms_days));
+    pdf.line(&format!("PO Reference: {} (Revision {})", purchase_order_number(order.id), order.version));
+    if let Some(shipment_id) = invoice.shipment_id {
+        pdf.line(&format!("Shipment Reference: #{}", shipment_id));
+    }
+    pdf.space(4.0);
+
+    pdf.parties(
+        ("From", &order.supplier_name, &order.supplier_address, &order.supplier_city),
+        ("Bill To", &order.buyer_name, &order.buyer_address, &order.buyer_city),
+    );
+
+    pdf.subheading("Line Items");
+    pdf.row(&COLUMNS, &["Description", "Qty", "Unit Price", &format!("Amount ({})", invoice.currency)], true);
+    pdf.rule();
+    pdf.row(
+        &COLUMNS,
+        &[&order.rfq_title, &invoice.quantity.to_string(), &invoice.unit_price.to_string(), &invoice.subtotal.to_string()],
+        false,
+    );
+    pdf.rule();
+    pdf.row(&COLUMNS, &["", "", "Subtotal", &invoice.subtotal.to_string()], false);
+    for tax in &invoice.tax_lines {
+        pdf.row(&COLUMNS, &["", "", &format!("{} ({}%)", tax.description, tax.rate), &tax.amount.to_string()], false);
+    }
+    pdf.row(&COLUMNS, &["", "", &format!("Total {}", invoice.currency), &invoice.total_amount.to_string()], true);
+    pdf.space(4.0);
+
+    pdf.subheading("Payment");
+    match invoice.status.as_str() {
+        "PAID" => pdf.line(&format!("Paid in full: {} {}", invoice.total_amount, invoice.currency)),
+        "OVERDUE" => pdf.line(&format!("OVERDUE - amount due: {} {}", invoice.total_amount, invoice.currency)),
+        _ => pdf.line(&format!("Amount due: {} {} by {}", invoice.total_amount, invoice.currency, invoice.due_date)),
+    }
+    if let Some(notes) = invoice.notes.as_deref().filter(|n| !n.trim().is_empty()) {
+        pdf.paragraph(&format!("Notes: {}", notes));
+    }
+
+    pdf.finish()
+}
+
+// 订单行项目表格：目前每个订单对应RFQ中的一个物料
+fn line_items(pdf: &mut PdfWriter, order: &OrderDocumentData) {
+    const COLUMNS: [f32; 4] = [MARGIN, 120.0, 145.0, 170.0];
+
+    pdf.subheading("Line Items");
+    pdf.row(&COLUMNS, &["Description", "Qty", "Unit Price", &format!("Amount ({})", order.currency)], true);
+    pdf.rule();
+    pdf.row(
+        &COLUMNS,
+        &[&order.rfq_title, &order.quantity.to_string(), &order.unit_price.to_string(), &order.total_amount.to_string()],
+        false,
+    );
+    if let Some(description) = order.rfq_description.as_deref().filter(|d| !d.trim().is_empty()) {
+        pdf.paragraph(description);
+    }
+    pdf.rule();
+    pdf.row(&COLUMNS, &["", "", &format!("Total {}", order.currency), &order.total_amount.to_string()], true);
+    pdf.space(4.0);
+}
+
+// 首次生成文档时读取字体文件，之后复用；字体不可用时返回 None
+fn font_data() -> Option<&'static [u8]> {
+    static FONT: OnceLock<Option<Vec<u8>>> = OnceLock::new();
+    FONT.get_or_init(|| {
+        let path = std::env::var("PDF_FONT_PATH").unwrap_or_else(|_| DEFAULT_FONT_PATH.to_string());
+        let data = match std::fs::read(&path) {
+            Ok(data) => data,
+            Err(e) => {
+                log::warn!("PDF font {} not available ({}), falling back to the built-in font", path, e);
+                return None;
+            }
+        };
+        if let Err(e) = ttf_parser::Face::parse(&data, 0) {
+            log::warn!("PDF font {} is invalid ({}), falling back to the built-in font", path, e);
+            return None;
+        }
+        Some(data)
+    }).as_deref()
+}
+
+// 按字符数简单折行
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for word in text.split_whitespace() {
+        if !current.is_empty() && current.len() + word.len() + 1 > width {
+            lines.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
+// 对 printpdf 的简单封装：自上而下排版，空间不够时自动换页
+struct PdfWriter {
+    doc: PdfDocumentReference,
+    layer: PdfLayerReference,
+    regular: IndirectFontRef,
+    bold: IndirectFontRef,
+    // 嵌入字体的字形表，使用内置字体时为 None
+    face: Option<ttf_parser::Face<'static>>,
+    y: f32,
+}
+
+impl PdfWriter {
+    fn new(title: &str) -> Result<Self, AppError> {
+        Self::with_font(title, font_data())
+    }
+
+    fn with_font(title: &str, font: Option<&'static [u8]>) -> Result<Self, AppError> {
+        let font_error = |e| AppError::InternalServerError(format!("Failed to load PDF font: {:?}", e));
+        let (doc, page, layer) = PdfDocument::new(title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+        let (regular, bold, face) = match font.and_then(|data| Some((data, ttf_parser::Face::parse(data, 0).ok()?))) {
+            Some((data, face)) => {
+                let font = doc.add_external_font(data).map_err(font_error)?;
+                (font.clone(), font, Some(face))
+            }
+            None => (
+                doc.add_builtin_font(BuiltinFont::Helvetica).map_err(font_error)?,
+                doc.add_builtin_font(BuiltinFont::HelveticaBold).map_err(font_error)?,
+                None,
+            ),
+        };
+        let layer = doc.get_page(page).get_layer(layer);
+        Ok(Self { doc, layer, regular, bold, face, y: PAGE_HEIGHT - MARGIN })
+    }
+
+    // 字体中没有字形的字符（以及控制字符）用 '?' 代替，避免渲染成空白；内置字体只支持拉丁字符
+    fn sanitize(&self, text: &str) -> String {
+        let renderable = |c: char| match &self.face {
+            Some(face) => !c.is_control() && face.glyph_index(c).is_some(),
+            None => c.is_ascii() && !c.is_ascii_control(),
+        };
+        text.chars().map(|c| if renderable(c) { c } else { '?' }).collect()
+    }
+
+    // 预留高度，不够时新起一页
+    fn reserve(&mut self, height: f32) {
+        if self.y - height < MARGIN {
+            let (page, layer) = self.doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+            self.layer = self.doc.get_page(page).get_layer(layer);
+            self.y = PAGE_HEIGHT - MARGIN;
+        }
+        self.y -= height;
+    }
+
+    // 中文字体通常没有单独的粗体，嵌入字体的粗体通过填充加描边模拟
+    fn text(&self, text: &str, size: f32, x: f32, bold: bool) {
+        let simulate_bold = bold && self.face.is_some();
+        if simulate_bold {
+            self.layer.save_graphics_state();
+            self.layer.set_text_rendering_mode(TextRenderingMode::FillStroke);
+            self.layer.set_outline_thickness(0.3);
+        }
+        let font = if bold { &self.bold } else { &self.regular };
+        self.layer.use_text(self.sanitize(text), size, Mm(x), Mm(self.y), font);
+        if simulate_bold {
+            self.layer.restore_graphics_state();
+        }
+    }
+
+    fn heading(&mut self, text: &str) {
+        self.reserve(8.0);
+        self.text(text, 18.0, MARGIN, true);
+        self.space(4.0);
+    }
+
+    fn subheading(&mut self, text: &str) {
+        self.reserve(7.0);
+        self.text(text, 12.0, MARGIN, true);
+    }
+
+    fn line(&mut self, text: &str) {
+        self.reserve(5.5);
+        self.text(text, 10.0, MARGIN, false);
+    }
+
+    fn paragraph(&mut self, text: &str) {
+        for line in wrap(text, 95) {
+            self.line(&line);
+        }
+    }
+
+    fn row(&mut self, columns: &[f32], cells: &[&str], bold: bool) {
+        self.reserve(5.5);
+        for (x, cell) in columns.iter().zip(cells) {
+            self.text(cell, 10.0, *x, bold);
+        }
+    }
+
+    fn rule(&mut self) {
+        self.reserve(2.0);
+        self.layer.add_line(Line {
+            points: vec![
+                (Point::new(Mm(MARGIN), Mm(self.y)), false),
+                (Point::new(Mm(PAGE_WIDTH - MARGIN), Mm(self.y)), false),
+            ],
+            is_closed: false,
+        });
+        self.reserve(3.0);
+    }
+
+    fn space(&mut self, height: f32) {
+        self.reserve(height);
+    }
+
+    // 左右两栏分别显示双方的名称和地址
+    fn parties(
+        &mut self,
+        left: (&str, &str, &Option<String>, &Option<String>),
+        right: (&str, &str, &Option<String>, &Option<String>),
+    ) {
+        let right_x = PAGE_WIDTH / 2.0;
+        let columns = [left, right].map(|(label, name, address, city)| {
+            let mut lines = vec![name.to_string()];
+            if let Some(address) = address {
+                lines.extend(wrap(address, 45));
+            }
+            if let Some(city) = city {
+                lines.push(city.clone());
+            }
+            (label, lines)
+        });
+
+        self.reserve(6.0);
+        self.text(columns[0].0, 11.0, MARGIN, true);
+        self.text(columns[1].0, 11.0, right_x, true);
+        let rows = columns[0].1.len().max(columns[1].1.len());
+        for i in 0..rows {
+            self.reserve(5.0);
+            if let Some(text) = columns[0].1.get(i) {
+                self.text(text, 10.0, MARGIN, false);
+            }
+            if let Some(text) = columns[1].1.get(i) {
+                self.text(text, 10.0, right_x, false);
+            }
+        }
+        self.space(6.0);
+    }
+
+    fn finish(self) -> Result<Vec<u8>, AppError> {
+        self.doc.save_to_bytes()
+            .map_err(|e| AppError::InternalServerError(format!("Failed to generate PDF: {:?}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_purchase_order_renders_pdf() {
+        let order = OrderDocumentData {
+            id: 42,
+            version: 2,
+            quantity: 500,
+            unit_price: Decimal::new(1250, 2),
+            total_amount: Decimal::new(625000, 2),
+            currency: "CNY".to_string(),
+            status: "CONFIRMED".to_string(),
+            payment_status: "UNPAID".to_string(),
+            created_at: Utc::now(),
+            committed_ship_date: None,
+            rfq_title: "M8 hex bolts".to_string(),
+            rfq_description: Some("Zinc plated, grade 8.8 ".repeat(20)),
+            lead_time_days: 14,
+            quote_notes: None,
+            buyer_name: "采购方".to_string(),
+            buyer_address: Some("1 Main Street".to_string()),
+            buyer_city: None,
+            supplier_name: "Acme Fasteners".to_string(),
+            supplier_address: None,
+            supplier_city: Some("Shenzhen".to_string()),
+        };
+
+        let bytes = render_purchase_order(&order).unwrap();
+        assert!(bytes.starts_with(b"%PDF"));
+        assert_eq!(purchase_order_number(order.id), "PO-000042");
+
+    }
+
+    #[test]
+    fn test_builtin_font_fallback() {
+        // 没有可用字体时退回内置字体，文档照常生成
+        let mut pdf = PdfWriter::with_font("test", None).unwrap();
+        assert_eq!(pdf.sanitize("采购方 PO-1"), "??? PO-1");
+        assert_eq!(pdf.sanitize("line\nbreak"), "line?break");
+        pdf.heading("采购订单 Purchase Order");
+        pdf.line("Buyer: 采购方");
+        assert!(pdf.finish().unwrap().starts_with(b"%PDF"));
+    }
+
+    #[test]
+    fn test_cjk_font_renders_chinese() {
+        // 只在安装了 fonts-droid-fallback（或通过 PDF_FONT_PATH 指定了中文字体）的环境中检查中文字形
+        let Some(font) = font_data() else {
+            return;
+        };
+        let mut pdf = PdfWriter::with_font("test", Some(font)).unwrap();
+        assert_eq!(pdf.sanitize("采购方"), "采购方");
+        assert_eq!(pdf.sanitize("M8 螺栓 ×500"), "M8 螺栓 ×500");
+        pdf.heading("采购订单");
+        assert!(pdf.finish().unwrap().starts_with(b"%PDF"));
+    }
+}
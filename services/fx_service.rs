@@ -0,0 +1,242 @@
+// src/services/fx_service.rs
+
+use crate::{
+    errors::AppError,
+    models::{
+        fx::{FxRate, FxRateDto, FxRateQuery},
+        user::Claims,
+    },
+};
+use chrono::{NaiveDate, Utc};
+use sqlx::{types::Decimal, MySqlPool, QueryBuilder};
+use std::str::FromStr;
+
+// 平台支持的交易币种
+pub const SUPPORTED_CURRENCIES: &[&str] = &["CNY", "EUR", "USD"];
+// 两个币种之间没有直接汇率时，通过该币种交叉换算
+const CROSS_CURRENCY: &str = "USD";
+
+/// 校验并规范化币种代码（大写的ISO 4217代码）
+pub fn normalize_currency(code: &str) -> Result<String, AppError> {
+    let code = code.trim().to_uppercase();
+    if !SUPPORTED_CURRENCIES.contains(&code.as_str()) {
+        return Err(AppError::BadRequest(format!(
+            "Unsupported currency '{}'. Supported currencies: {}",
+            code,
+            SUPPORTED_CURRENCIES.join(", ")
+        )));
+    }
+    Ok(code)
+}
+
+/// 内存中的汇率表，用于批量换算金额（例如统计报表）
+pub struct FxTable {
+    rates: Vec<FxRate>,
+}
+
+impl FxTable {
+    pub fn new(rates: Vec<FxRate>) -> Self {
+        Self { rates }
+    }
+
+    pub async fn load(pool: &MySqlPool) -> Result<Self, AppError> {
+        let rates = sqlx::query_as("SELECT * FROM fx_rates").fetch_all(pool).await?;
+        Ok(Self::new(rates))
+    }
+
+    /// from 到 to 在某天的汇率。没有直接汇率（含反向汇率）时通过美元交叉换算
+    pub fn rate(&self, from: &str, to: &str, on: NaiveDate) -> Option<Decimal> {
+        if from == to {
+            return Some(Decimal::ONE);
+        }
+        self.direct_rate(from, to, on).or_else(|| {
+            if from == CROSS_CURRENCY || to == CROSS_CURRENCY {
+                return None;
+            }
+            Some(self.direct_rate(from, CROSS_CURRENCY, on)? * self.direct_rate(CROSS_CURRENCY, to, on)?)
+        })
+    }
+
+    // 取当天或之前最近生效的汇率，也可以使用反向汇率；如果该日期之前没有汇率，则使用最早录入的汇率
+    fn direct_rate(&self, from: &str, to: &str, on: NaiveDate) -> Option<Decimal> {
+        let candidates = self.rates.iter().filter_map(|r| {
+            if r.base_currency == from && r.quote_currency == to {
+                Some((r.effective_date, r.rate))
+            } else if r.base_currency == to && r.quote_currency == from && !r.rate.is_zero() {
+                Some((r.effective_date, Decimal::ONE / r.rate))
+            } else {
+                None
+            }
+        });
+
+        let mut latest: Option<(NaiveDate, Decimal)> = None;
+        let mut earliest: Option<(NaiveDate, Decimal)> = None;
+        for (date, rate) in candidates {
+            if date <= on && latest.is_none_or(|(d, _)| date > d) {
+                latest = Some((date, rate));
+            }
+            if earliest.is_none_or(|(d, _)| date < d) {
+                earliest = Some((date, rate));
+            }
+        }
+        latest.or(earliest).map(|(_, rate)| rate)
+    }
+
+    /// 按某天的汇率换算金额，结果保留两位小数
+    pub fn convert(&self, amount: Decimal, from: &str, to: &str, on: NaiveDate) -> Result<Decimal, AppError> {
+        let rate = self.rate(from, to, on)
+            .ok_or_else(|| AppError::BadRequest(format!("No exchange rate from {} to {} is available.", from, to)))?;
+        Ok((amount * rate).round_dp(2))
+    }
+}
+
+/// 管理员录入或更新某天的汇率
+pub async fn upsert_rate(pool: &MySqlPool, dto: FxRateDto, claims: &Claims) -> Result<(), AppError> {
+    let base_currency = normalize_currency(&dto.base_currency)?;
+    let quote_currency = normalize_currency(&dto.quote_currency)?;
+    let rate = parse_rate(&dto.rate.to_string())?;
+    if base_currency == quote_currency {
+        return Err(AppError::BadRequest("Base and quote currency must differ.".to_string()));
+    }
+    let effective_date = dto.effective_date.unwrap_or_else(|| Utc::now().date_naive());
+
+    save_rates(pool, &[(base_currency, quote_currency, rate, effective_date)], "MANUAL", claims).await?;
+    Ok(())
+}
+
+fn parse_rate(value: &str) -> Result<Decimal, AppError> {
+    let rate = Decimal::from_str(value.trim())
+        .map_err(|_| AppError::BadRequest(format!("Invalid exchange rate '{}'", value.trim())))?;
+    if rate <= Decimal::ZERO {
+        return Err(AppError::BadRequest("Exchange rates must be positive.".to_string()));
+    }
+    Ok(rate.round_dp(8))
+}
+
+/// 解析汇率CSV，每行格式为 base_currency,quote_currency,rate,effective_date（YYYY-MM-DD）。
+/// 允许有表头行和空行
+pub fn parse_rates_csv(csv: &str) -> Result<Vec<(String, String, Decimal, NaiveDate)>, AppError> {
+    let mut rates = Vec::new();
+    for (index, line) in csv.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || (index == 0 && line.to_lowercase().starts_with("base")) {
+            continue;
+        }
+        let line_error = |message: String| AppError::BadRequest(format!("Line {}: {}", index + 1, message));
+
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let [base, quote, rate, date] = fields[..] else {
+            return Err(line_error("expected base_currency,quote_currency,rate,effective_date".to_string()));
+        };
+        let base = normalize_currency(base).map_err(|e| line_error(e.to_string()))?;
+        let quote = normalize_currency(quote).map_err(|e| line_error(e.to_string()))?;
+        if base == quote {
+            return Err(line_error("base and quote currency must differ".to_string()));
+        }
+        let rate = parse_rate(rate).map_err(|e| line_error(e.to_string()))?;
+        let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|_| line_error(format!("invalid date '{}'", date)))?;
+        rates.push((base, quote, rate, date));
+    }
+    if rates.is_empty() {
+        return Err(AppError::BadRequest("The file contains no exchange rates.".to_string()));
+    }
+    Ok(rates)
+}
+
+/// 导入汇率CSV。整个文件在一个事务中导入，任何一行有误都不会导入。返回导入的条数
+pub async fn import_rates(pool: &MySqlPool, csv: &str, claims: &Claims) -> Result<usize, AppError> {
+    let rates = parse_rates_csv(csv)?;
+    save_rates(pool, &rates, "IMPORT", claims).await?;
+    Ok(rates.len())
+}
+
+async fn save_rates(
+    pool: &MySqlPool,
+    rates: &[(String, String, Decimal, NaiveDate)],
+    source: &str,
+    claims: &Claims,
+) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+    for (base, quote, rate, date) in rates {
+        sqlx::query(
+            "INSERT INTO fx_rates (base_currency, quote_currency, rate, effective_date, source, created_by_user_id) VALUES (?, ?, ?, ?, ?, ?)
+             ON DUPLICATE KEY UPDATE rate = VALUES(rate), source = VALUES(source), created_by_user_id = VALUES(created_by_user_id)"
+        )
+            .bind(base)
+            .bind(quote)
+            .bind(rate)
+            .bind(date)
+            .bind(source)
+            .bind(claims.sub)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// 查询汇率，按生效日期倒序
+pub async fn list_rates(pool: &MySqlPool, query: FxRateQuery) -> Result<Vec<FxRate>, AppError> {
+    let mut builder = QueryBuilder::new("SELECT * FROM fx_rates WHERE 1 = 1");
+    if let Some(base) = query.base_currency {
+        builder.push(" AND base_currency = ").push_bind(base.to_uppercase());
+    }
+    if let Some(quote) = query.quote_currency {
+        builder.push(" AND quote_currency = ").push_bind(quote.to_uppercase());
+    }
+    builder.push(" ORDER BY effective_date DESC, base_currency, quote_currency");
+
+    let rates = builder.build_query_as().fetch_all(pool).await?;
+    Ok(rates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rate(base: &str, quote: &str, rate: &str, date: &str) -> FxRate {
+        FxRate {
+            id: 0,
+            base_currency: base.to_string(),
+            quote_currency: quote.to_string(),
+            rate: Decimal::from_str(rate).unwrap(),
+            effective_date: NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap(),
+            source: "MANUAL".to_string(),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_fx_table_uses_rate_in_effect() {
+        let table = FxTable::new(vec![
+            rate("USD", "CNY", "7.0", "2025-01-01"),
+            rate("USD", "CNY", "7.2", "2025-06-01"),
+            rate("EUR", "USD", "1.25", "2025-01-01"),
+        ]);
+        let day = |d: &str| NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap();
+
+        assert_eq!(table.convert(Decimal::from(100), "USD", "CNY", day("2025-03-01")).unwrap(), Decimal::from(700));
+        assert_eq!(table.convert(Decimal::from(100), "USD", "CNY", day("2025-07-01")).unwrap(), Decimal::from(720));
+        // 早于所有汇率的日期使用最早的汇率
+        assert_eq!(table.convert(Decimal::from(100), "USD", "CNY", day("2024-01-01")).unwrap(), Decimal::from(700));
+        // 反向汇率
+        assert_eq!(table.convert(Decimal::from(125), "USD", "EUR", day("2025-03-01")).unwrap(), Decimal::from(100));
+        assert_eq!(table.convert(Decimal::from(5), "EUR", "EUR", day("2025-03-01")).unwrap(), Decimal::from(5));
+        // 没有直接汇率时通过美元交叉换算：100 EUR = 125 USD = 875 CNY
+        assert_eq!(table.convert(Decimal::from(100), "EUR", "CNY", day("2025-03-01")).unwrap(), Decimal::from(875));
+        assert_eq!(table.convert(Decimal::from(875), "CNY", "EUR", day("2025-03-01")).unwrap(), Decimal::from(100));
+        assert!(FxTable::new(vec![]).convert(Decimal::from(100), "CNY", "EUR", day("2025-03-01")).is_err());
+    }
+
+    #[test]
+    fn test_parse_rates_csv() {
+        let rates = parse_rates_csv("base,quote,rate,date\nusd,CNY,7.1,2025-05-01\n\nEUR,USD,1.08,2025-05-01\n").unwrap();
+        assert_eq!(rates.len(), 2);
+        assert_eq!(rates[0].0, "USD");
+
+        assert!(parse_rates_csv("USD,JPY,150,2025-05-01").is_err());
+        assert!(parse_rates_csv("USD,CNY,-1,2025-05-01").is_err());
+        assert!(parse_rates_csv("USD,CNY,7.1").is_err());
+    }
+}
@@ -0,0 +1,249 @@
+// src/services/invitation_service.rs
+
+use crate::{
+    errors::AppError,
+    models::{
+        invitation::{AcceptInvitationDto, CreateInvitationDto, Invitation, InvitationPreview},
+        user::{roles_for_company_type, Claims, LoginResponse, User, ROLE_OWNER},
+    },
+    services::{audit_service::AuditEntry, auth_service, notification_service},
+    utils::auth_utils,
+};
+use chrono::{Duration, Utc};
+use sqlx::MySqlPool;
+use std::env;
+
+// 邀请链接的有效天数，重新发送时重新计算
+const INVITATION_EXPIRY_DAYS: i64 = 7;
+
+/// 只有本公司的所有者可以管理邀请
+fn ensure_company_owner(claims: &Claims, company_id: i32) -> Result<(), AppError> {
+    if claims.company_id != company_id {
+        return Err(AppError::BadRequest("You can only manage invitations of your own company.".to_string()));
+    }
+    auth_utils::require_role(claims, &[ROLE_OWNER])
+}
+
+async fn send_invitation_email(pool: &MySqlPool, company_id: i32, email: &str, token: &str) -> Result<(), AppError> {
+    let (company_name,): (String,) = sqlx::query_as("SELECT name FROM companies WHERE id = ?")
+        .bind(company_id)
+        .fetch_one(pool)
+        .await?;
+    let frontend_url = env::var("FRONTEND_URL").unwrap_or_else(|_| "http://localhost:5173".to_string());
+
+    let subject = format!("You have been invited to join {} on SCCP", company_name);
+    let body = format!(
+        "Hello,\n\nYou have been invited to join {} on SCCP.\n\nAccept the invitation within {} days:\n{}/invitations/accept?token={}\n\nIf you were not expecting this invitation, you can ignore this email.",
+        company_name, INVITATION_EXPIRY_DAYS, frontend_url, token
+    );
+    notification_service::send_email(email.to_string(), subject, body).await
+}
+
+async fn get_company_invitation(pool: &MySqlPool, company_id: i32, invitation_id: i32) -> Result<Invitation, AppError> {
+    sqlx::query_as("SELECT * FROM company_invitations WHERE id = ? AND company_id = ?")
+        .bind(invitation_id)
+        .bind(company_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("Invitation not found.".to_string()))
+}
+
+/// 公司所有者邀请同事加入本公司
+pub async fn create_invitation(
+    pool: &MySqlPool,
+    company_id: i32,
+    dto: CreateInvitationDto,
+    claims: &Claims,
+) -> Result<u64, AppError> {
+    ensure_company_owner(claims, company_id)?;
+    auth_utils::require_verified_email(claims)?;
+
+    let email = dto.email.trim().to_string();
+    if !email.contains('@') {
+        return Err(AppError::BadRequest("A valid email address is required.".to_string()));
+    }
+    let role = dto.role.trim().to_uppercase();
+    let allowed = roles_for_company_type(&claims.company_type);
+    if !allowed.contains(&role.as_str()) {
+        return Err(AppError::BadRequest(format!(
+            "Invalid role '{}'. Allowed roles: {}",
+            role,
+            allowed.join(", ")
+        )));
+    }
+
+    let existing_user = sqlx::query("SELECT id FROM users WHERE email = ?")
+        .bind(&email)
+        .fetch_optional(pool)
+        .await?;
+    if existing_user.is_some() {
+        return Err(AppError::BadRequest("A user with this email already exists.".to_string()));
+    }
+    let pending = sqlx::query(
+        "SELECT id FROM company_invitations WHERE company_id = ? AND email = ? AND status = 'PENDING' AND expires_at > NOW()"
+    )
+        .bind(company_id)
+        .bind(&email)
+        .fetch_optional(pool)
+        .await?;
+    if pending.is_some() {
+        return Err(AppError::BadRequest("This email already has a pending invitation. Resend it instead.".to_string()));
+    }
+
+    let token = auth_utils::generate_token();
+    let expires_at = Utc::now() + Duration::days(INVITATION_EXPIRY_DAYS);
+    let mut tx = pool.begin().await?;
+    let result = sqlx::query(
+        "INSERT INTO company_invitations (company_id, email, role, token_hash, invited_by_user_id, expires_at) VALUES (?, ?, ?, ?, ?, ?)"
+    )
+        .bind(company_id)
+        .bind(&email)
+        .bind(&role)
+        .bind(auth_utils::hash_token(&token))
+        .bind(claims.sub)
+        .bind(expires_at)
+        .execute(&mut *tx)
+        .await?;
+    AuditEntry::new(claims, "INVITATION_CREATED", "INVITATION", result.last_insert_id() as i32)
+        .after(serde_json::json!({ "email": email, "role": role }))
+        .record(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    send_invitation_email(pool, company_id, &email, &token).await?;
+
+    Ok(result.last_insert_id())
+}
+
+pub async fn list_invitations(pool: &MySqlPool, company_id: i32, claims: &Claims) -> Result<Vec<Invitation>, AppError> {
+    ensure_company_owner(claims, company_id)?;
+
+    let invitations = sqlx::query_as("SELECT * FROM company_invitations WHERE company_id = ? ORDER BY created_at DESC")
+        .bind(company_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(invitations)
+}
+
+/// 撤销尚未接受的邀请，邀请链接随即失效
+pub async fn revoke_invitation(pool: &MySqlPool, company_id: i32, invitation_id: i32, claims: &Claims) -> Result<(), AppError> {
+    ensure_company_owner(claims, company_id)?;
+
+    let invitation = get_company_invitation(pool, company_id, invitation_id).await?;
+    if invitation.status != "PENDING" {
+        return Err(AppError::BadRequest("Only pending invitations can be revoked.".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("UPDATE company_invitations SET status = 'REVOKED' WHERE id = ? AND status = 'PENDING'")
+        .bind(invitation_id)
+        .execute(&mut *tx)
+        .await?;
+    AuditEntry::new(claims, "INVITATION_REVOKED", "INVITATION", invitation_id)
+        .before(serde_json::json!({ "email": invitation.email, "status": invitation.status }))
+        .after(serde_json::json!({ "status": "REVOKED" }))
+        .record(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// 重新发送邀请：生成新令牌（旧链接失效）并顺延有效期
+pub async fn resend_invitation(pool: &MySqlPool, company_id: i32, invitation_id: i32, claims: &Claims) -> Result<(), AppError> {
+    ensure_company_owner(claims, company_id)?;
+
+    let invitation = get_company_invitation(pool, company_id, invitation_id).await?;
+    if invitation.status != "PENDING" {
+        return Err(AppError::BadRequest("Only pending invitations can be resent.".to_string()));
+    }
+
+    let token = auth_utils::generate_token();
+    let expires_at = Utc::now() + Duration::days(INVITATION_EXPIRY_DAYS);
+    sqlx::query(
+        "UPDATE company_invitations SET token_hash = ?, expires_at = ?, last_sent_at = NOW() WHERE id = ? AND status = 'PENDING'"
+    )
+        .bind(auth_utils::hash_token(&token))
+        .bind(expires_at)
+        .bind(invitation_id)
+        .execute(pool)
+        .await?;
+
+    send_invitation_email(pool, company_id, &invitation.email, &token).await
+}
+
+/// 被邀请人查看邀请详情（无需登录）
+pub async fn get_invitation_preview(pool: &MySqlPool, token: &str) -> Result<InvitationPreview, AppError> {
+    sqlx::query_as(
+        "SELECT c.name as company_name, c.company_type, i.email, i.role, i.expires_at
+         FROM company_invitations i JOIN companies c ON i.company_id = c.id
+         WHERE i.token_hash = ? AND i.status = 'PENDING' AND i.expires_at > NOW()"
+    )
+        .bind(auth_utils::hash_token(token))
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("This invitation is invalid or has expired.".to_string()))
+}
+
+/// 接受邀请：在邀请方公司内创建用户，角色为邀请时指定的角色，并直接登录
+pub async fn accept_invitation(pool: &MySqlPool, dto: AcceptInvitationDto) -> Result<LoginResponse, AppError> {
+    if dto.full_name.trim().is_empty() {
+        return Err(AppError::BadRequest("Full name is required.".to_string()));
+    }
+    if dto.password.len() < 6 {
+        return Err(AppError::BadRequest("Password must be at least 6 characters long.".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+    let invitation: Invitation = sqlx::query_as(
+        "SELECT * FROM company_invitations WHERE token_hash = ? AND status = 'PENDING' AND expires_at > NOW() FOR UPDATE"
+    )
+        .bind(auth_utils::hash_token(&dto.token))
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("This invitation is invalid or has expired.".to_string()))?;
+
+    let existing_user = sqlx::query("SELECT id FROM users WHERE email = ?")
+        .bind(&invitation.email)
+        .fetch_optional(&mut *tx)
+        .await?;
+    if existing_user.is_some() {
+        return Err(AppError::BadRequest("Email already exists".to_string()));
+    }
+
+    let password_hash = auth_utils::hash_password(&dto.password)
+        .map_err(|_| AppError::InternalServerError("Failed to hash password".to_string()))?;
+    let user_id = sqlx::query(
+        // 邀请链接是发到该邮箱的，接受邀请即视为邮箱已验证
+        "INSERT INTO users (company_id, email, password_hash, full_name, role, email_verified_at) VALUES (?, ?, ?, ?, ?, NOW())"
+    )
+        .bind(invitation.company_id)
+        .bind(&invitation.email)
+        .bind(password_hash)
+        .bind(dto.full_name.trim())
+        .bind(&invitation.role)
+        .execute(&mut *tx)
+        .await?
+        .last_insert_id() as i32;
+
+    sqlx::query(
+        "UPDATE company_invitations SET status = 'ACCEPTED', accepted_user_id = ?, accepted_at = NOW() WHERE id = ?"
+    )
+        .bind(user_id)
+        .bind(invitation.id)
+        .execute(&mut *tx)
+        .await?;
+
+    let (company_type,): (String,) = sqlx::query_as("SELECT company_type FROM companies WHERE id = ?")
+        .bind(invitation.company_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    let user: User = sqlx::query_as("SELECT * FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    let response = auth_service::start_session(&mut tx, &user, &company_type).await?;
+
+    tx.commit().await?;
+
+    Ok(response)
+}
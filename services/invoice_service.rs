@@ -0,0 +1,348 @@
+// src/services/invoice_service.rs
+
+use crate::{
+    errors::AppError,
+    models::{
+        invoice::{CreateInvoiceDto, Invoice, InvoiceQuery, InvoiceTaxLine},
+        user::{Claims, OPERATOR_ROLES},
+    },
+    services::{chat_server::ChatServer, notification_service, order_service, payment_service},
+    utils::auth_utils,
+};
+use actix::Addr;
+use chrono::{Days, Utc};
+use sqlx::{types::Decimal, MySqlConnection, MySqlPool, QueryBuilder};
+use std::str::FromStr;
+
+// 支持的付款条件：Net 30 / Net 60
+pub const PAYMENT_TERMS_DAYS: &[i32] = &[30, 60];
+const DEFAULT_PAYMENT_TERMS_DAYS: i32 = 30;
+
+// 只有发货之后才能对订单剩余数量整体开票
+const INVOICEABLE_STATUSES: &[&str] = &["SHIPPED", "DELIVERED", "COMPLETED", "DISPUTED"];
+
+// 发货数量、收货记录ID、拒收数量、已开发票ID
+type ShipmentInvoiceRow = (i32, Option<i32>, Option<i32>, Option<i32>);
+
+// 在调用方的事务中为供应商分配下一个发票编号，事务回滚时编号也会回滚，保证编号连续
+async fn next_invoice_number(conn: &mut MySqlConnection, supplier_company_id: i32) -> Result<String, AppError> {
+    sqlx::query(
+        "INSERT INTO invoice_sequences (supplier_company_id, last_number) VALUES (?, 1)
+         ON DUPLICATE KEY UPDATE last_number = last_number + 1"
+    )
+        .bind(supplier_company_id)
+        .execute(&mut *conn)
+        .await?;
+
+    let (number,): (i32,) = sqlx::query_as("SELECT last_number FROM invoice_sequences WHERE supplier_company_id = ?")
+        .bind(supplier_company_id)
+        .fetch_one(&mut *conn)
+        .await?;
+    Ok(format!("INV-{:06}", number))
+}
+
+// 为一组发票加载税费明细
+async fn load_tax_lines(pool: &MySqlPool, invoices: &mut [Invoice]) -> Result<(), AppError> {
+    if invoices.is_empty() {
+        return Ok(());
+    }
+
+    let mut builder = QueryBuilder::new("SELECT * FROM invoice_tax_lines WHERE invoice_id IN (");
+    let mut separated = builder.separated(", ");
+    for invoice in invoices.iter() {
+        separated.push_bind(invoice.id);
+    }
+    separated.push_unseparated(") ORDER BY id ASC");
+
+    let tax_lines: Vec<InvoiceTaxLine> = builder.build_query_as().fetch_all(pool).await?;
+    for line in tax_lines {
+        if let Some(invoice) = invoices.iter_mut().find(|i| i.id == line.invoice_id) {
+            invoice.tax_lines.push(line);
+        }
+    }
+    Ok(())
+}
+
+/// 供应商为订单开具发票。指定发货记录时按该次发货的实收数量开具部分发票，
+/// 否则对订单剩余未开票的数量开票
+pub async fn create_invoice(
+    pool: &MySqlPool,
+    chat_server: &Addr<ChatServer>,
+    order_id: i32,
+    dto: CreateInvoiceDto,
+    claims: &Claims,
+) -> Result<u64, AppError> {
+    auth_utils::require_role(claims, OPERATOR_ROLES)?;
+    let payment_terms_days = dto.payment_terms_days.unwrap_or(DEFAULT_PAYMENT_TERMS_DAYS);
+    if !PAYMENT_TERMS_DAYS.contains(&payment_terms_days) {
+        return Err(AppError::BadRequest("Payment terms must be Net 30 or Net 60.".to_string()));
+    }
+    let mut tax_rates = Vec::new();
+    for line in &dto.tax_lines {
+        if line.description.trim().is_empty() {
+            return Err(AppError::BadRequest("Each tax line needs a description.".to_string()));
+        }
+        if !(0.0..=100.0).contains(&line.rate) {
+            return Err(AppError::BadRequest("Tax rates must be between 0 and 100 percent.".to_string()));
+        }
+        let rate = Decimal::from_str(&line.rate.to_string())
+            .map_err(|_| AppError::BadRequest("Invalid tax rate format".to_string()))?;
+        tax_rates.push((line.description.trim().to_string(), rate));
+    }
+
+    let mut tx = pool.begin().await?;
+    let (current_status, buyer_company_id, supplier_company_id) =
+        order_service::lock_order(&mut tx, order_id, claims).await?;
+
+    if claims.company_id != supplier_company_id {
+        return Err(AppError::BadRequest("Only the supplier can issue invoices.".to_string()));
+    }
+
+    let (order_quantity, unit_price, order_total, currency): (i32, Decimal, Decimal, String) = sqlx::query_as(
+        "SELECT quantity, unit_price, total_amount, currency FROM purchase_orders WHERE id = ?"
+    )
+        .bind(order_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    let (invoiced_quantity, invoiced_subtotal): (i64, Decimal) = sqlx::query_as(
+        "SELECT CAST(COALESCE(SUM(quantity), 0) AS SIGNED), COALESCE(SUM(subtotal), 0) FROM invoices WHERE order_id = ?"
+    )
+        .bind(order_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    let remaining = order_quantity as i64 - invoiced_quantity;
+
+    let quantity = match dto.shipment_id {
+        Some(shipment_id) => {
+            let shipment: Option<ShipmentInvoiceRow> = sqlx::query_as(
+                "SELECT s.quantity_shipped, g.id, g.rejected_quantity, i.id
+                 FROM order_shipments s
+                 LEFT JOIN goods_receipts g ON g.shipment_id = s.id
+                 LEFT JOIN invoices i ON i.shipment_id = s.id
+                 WHERE s.id = ? AND s.order_id = ?"
+            )
+                .bind(shipment_id)
+                .bind(order_id)
+                .fetch_optional(&mut *tx)
+                .await?;
+            let (shipped, receipt_id, rejected, existing_invoice) = shipment
+                .ok_or_else(|| AppError::BadRequest("Shipment not found for this order.".to_string()))?;
+            if existing_invoice.is_some() {
+                return Err(AppError::BadRequest("This shipment has already been invoiced.".to_string()));
+            }
+            // 按发货开票必须等采购方确认收货，按实收数量开票
+            if receipt_id.is_none() {
+                return Err(AppError::BadRequest(
+                    "This shipment can only be invoiced after the buyer has confirmed receipt.".to_string(),
+                ));
+            }
+            let quantity = shipped - rejected.unwrap_or(0);
+            if quantity <= 0 {
+                return Err(AppError::BadRequest("Nothing was accepted from this shipment.".to_string()));
+            }
+            if quantity as i64 > remaining {
+                return Err(AppError::BadRequest(format!(
+                    "Only {} units of this order remain to be invoiced.",
+                    remaining
+                )));
+            }
+            quantity
+        }
+        None => {
+            if !INVOICEABLE_STATUSES.contains(&current_status.as_str()) {
+                return Err(AppError::BadRequest("The full order can only be invoiced after it has shipped.".to_string()));
+            }
+            if remaining <= 0 {
+                return Err(AppError::BadRequest("This order has already been fully invoiced.".to_string()));
+            }
+            remaining as i32
+        }
+    };
+
+    // 最后一张发票取订单总额的剩余部分，避免按单价计算产生的舍入误差
+    let subtotal = if quantity as i64 == remaining {
+        order_total - invoiced_subtotal
+    } else {
+        (unit_price * Decimal::from(quantity)).round_dp(2)
+    };
+    let tax_lines: Vec<(String, Decimal, Decimal)> = tax_rates
+        .into_iter()
+        .map(|(description, rate)| {
+            let amount = (subtotal * rate / Decimal::from(100)).round_dp(2);
+            (description, rate, amount)
+        })
+        .collect();
+    let tax_amount: Decimal = tax_lines.iter().map(|(_, _, amount)| *amount).sum();
+    let total_amount = subtotal + tax_amount;
+
+    let issue_date = Utc::now().date_naive();
+    let due_date = issue_date + Days::new(payment_terms_days as u64);
+    let invoice_number = next_invoice_number(&mut tx, supplier_company_id).await?;
+
+    let result = sqlx::query(
+        "INSERT INTO invoices (order_id, supplier_company_id, buyer_company_id, shipment_id, invoice_number, quantity, unit_price, subtotal, tax_amount, total_amount,
+                               currency, payment_terms_days, issue_date, due_date, notes, created_by_user_id)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+        .bind(order_id)
+        .bind(supplier_company_id)
+        .bind(buyer_company_id)
+        .bind(dto.shipment_id)
+        .bind(&invoice_number)
+        .bind(quantity)
+        .bind(unit_price)
+        .bind(subtotal)
+        .bind(tax_amount)
+        .bind(total_amount)
+        .bind(&currency)
+        .bind(payment_terms_days)
+        .bind(issue_date)
+        .bind(due_date)
+        .bind(&dto.notes)
+        .bind(claims.sub)
+        .execute(&mut *tx)
+        .await?;
+    let invoice_id = result.last_insert_id();
+
+    for (description, rate, amount) in &tax_lines {
+        sqlx::query("INSERT INTO invoice_tax_lines (invoice_id, description, rate, amount) VALUES (?, ?, ?, ?)")
+            .bind(invoice_id)
+            .bind(description)
+            .bind(rate)
+            .bind(amount)
+            .execute(&mut *tx)
+            .await?;
+    }
+    // 订单已有的整体付款（例如预付款）冲抵新发票，税额计入订单应付金额
+    payment_service::refresh_payment_status(&mut tx, order_id).await?;
+    tx.commit().await?;
+
+    if let Err(e) = notification_service::notify_company(
+        pool,
+        chat_server,
+        buyer_company_id,
+        format!("Invoice {} for {} {} was issued on order #{}, due {}", invoice_number, total_amount, currency, order_id, due_date),
+        format!("/orders/{}", order_id),
+    ).await {
+        log::error!("Failed to send invoice notification: {:?}", e);
+    }
+
+    Ok(invoice_id)
+}
+
+/// 获取订单的所有发票，只有订单双方可以查看
+pub async fn get_invoices_for_order(pool: &MySqlPool, order_id: i32, claims: &Claims) -> Result<Vec<Invoice>, AppError> {
+    order_service::ensure_order_party(pool, order_id, claims).await?;
+
+    let mut invoices: Vec<Invoice> = sqlx::query_as("SELECT * FROM invoices WHERE order_id = ? ORDER BY issue_date ASC, id ASC")
+        .bind(order_id)
+        .fetch_all(pool)
+        .await?;
+    load_tax_lines(pool, &mut invoices).await?;
+    Ok(invoices)
+}
+
+/// 获取当前公司的发票：采购方看到应付发票，供应商看到应收发票
+pub async fn get_invoices_for_company(
+    pool: &MySqlPool,
+    claims: &Claims,
+    query: InvoiceQuery,
+) -> Result<Vec<Invoice>, AppError> {
+    let mut builder = QueryBuilder::new("SELECT * FROM invoices WHERE ");
+    if claims.company_type == "BUYER" {
+        builder.push("buyer_company_id = ");
+    } else {
+        builder.push("supplier_company_id = ");
+    }
+    builder.push_bind(claims.company_id);
+    if let Some(status) = query.status {
+        builder.push(" AND status = ").push_bind(status);
+    }
+    builder.push(" ORDER BY due_date ASC, id ASC");
+
+    let mut invoices: Vec<Invoice> = builder.build_query_as().fetch_all(pool).await?;
+    load_tax_lines(pool, &mut invoices).await?;
+    Ok(invoices)
+}
+
+/// 获取单张发票，只有发票双方可以查看
+pub async fn get_invoice(pool: &MySqlPool, invoice_id: i32, claims: &Claims) -> Result<Invoice, AppError> {
+    let invoice: Invoice = sqlx::query_as("SELECT * FROM invoices WHERE id = ? AND (buyer_company_id = ? OR supplier_company_id = ?)")
+        .bind(invoice_id)
+        .bind(claims.company_id)
+        .bind(claims.company_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("Invoice not found or you are not authorized to view it.".to_string()))?;
+
+    let mut invoices = [invoice];
+    load_tax_lines(pool, &mut invoices).await?;
+    let [invoice] = invoices;
+    Ok(invoice)
+}
+
+/// 将已过到期日仍未付款的发票标记为逾期，并通知双方。返回新标记的发票数
+pub async fn flag_overdue_invoices(pool: &MySqlPool, chat_server: &Addr<ChatServer>) -> Result<usize, AppError> {
+    let overdue: Vec<(i32, i32, String, i32, i32)> = sqlx::query_as(
+        "SELECT id, order_id, invoice_number, buyer_company_id, supplier_company_id FROM invoices
+         WHERE status = 'ISSUED' AND due_date < CURDATE()"
+    )
+        .fetch_all(pool)
+        .await?;
+
+    for (invoice_id, order_id, invoice_number, buyer_company_id, supplier_company_id) in &overdue {
+        sqlx::query("UPDATE invoices SET status = 'OVERDUE', last_reminder_at = NOW() WHERE id = ? AND status = 'ISSUED'")
+            .bind(invoice_id)
+            .execute(pool)
+            .await?;
+
+        let message = format!("Invoice {} on order #{} is overdue.", invoice_number, order_id);
+        for company_id in [buyer_company_id, supplier_company_id] {
+            if let Err(e) = notification_service::notify_company(
+                pool,
+                chat_server,
+                *company_id,
+                message.clone(),
+                format!("/orders/{}", order_id),
+            ).await {
+                log::error!("Failed to send overdue invoice notification: {:?}", e);
+            }
+        }
+    }
+
+    Ok(overdue.len())
+}
+
+/// 定期提醒采购方支付逾期发票。返回发送的提醒数
+pub async fn send_overdue_reminders(
+    pool: &MySqlPool,
+    chat_server: &Addr<ChatServer>,
+    interval_days: i64,
+) -> Result<usize, AppError> {
+    let due: Vec<(i32, i32, String, Decimal, String, i32)> = sqlx::query_as(
+        "SELECT id, order_id, invoice_number, total_amount, currency, buyer_company_id FROM invoices
+         WHERE status = 'OVERDUE' AND (last_reminder_at IS NULL OR last_reminder_at < NOW() - INTERVAL ? DAY)"
+    )
+        .bind(interval_days)
+        .fetch_all(pool)
+        .await?;
+
+    for (invoice_id, order_id, invoice_number, total_amount, currency, buyer_company_id) in &due {
+        sqlx::query("UPDATE invoices SET last_reminder_at = NOW() WHERE id = ?")
+            .bind(invoice_id)
+            .execute(pool)
+            .await?;
+
+        if let Err(e) = notification_service::notify_company(
+            pool,
+            chat_server,
+            *buyer_company_id,
+            format!("Reminder: invoice {} for {} {} on order #{} is still unpaid.", invoice_number, total_amount, currency, order_id),
+            format!("/orders/{}", order_id),
+        ).await {
+            log::error!("Failed to send invoice reminder: {:?}", e);
+        }
+    }
+
+    Ok(due.len())
+}
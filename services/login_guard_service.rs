@@ -0,0 +1,131 @@
+// src/services/login_guard_service.rs
+
+use crate::{errors::AppError, models::user::User, services::notification_service};
+use chrono::{Duration, Utc};
+use sqlx::MySqlPool;
+
+// 连续失败多少次后锁定账号
+const MAX_FAILED_ATTEMPTS: i32 = 5;
+// 第一次锁定的时长，之后每多失败一次翻倍
+const BASE_LOCKOUT_MINUTES: i64 = 5;
+const MAX_LOCKOUT_MINUTES: i64 = 24 * 60;
+// 同一IP在时间窗口内失败次数达到上限后，暂停该IP的所有登录
+const IP_WINDOW_MINUTES: i64 = 15;
+const MAX_FAILURES_PER_IP: i64 = 20;
+
+/// 连续失败 failed_count 次后应锁定的分钟数，未达到阈值时返回 None
+pub fn lockout_minutes(failed_count: i32) -> Option<i64> {
+    if failed_count < MAX_FAILED_ATTEMPTS {
+        return None;
+    }
+    let doublings = (failed_count - MAX_FAILED_ATTEMPTS).min(16) as u32;
+    Some((BASE_LOCKOUT_MINUTES << doublings).min(MAX_LOCKOUT_MINUTES))
+}
+
+/// 登录前检查该IP最近的失败次数
+pub async fn check_ip(pool: &MySqlPool, ip_address: &str) -> Result<(), AppError> {
+    let (failures,): (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM login_attempts WHERE ip_address = ? AND succeeded = FALSE AND created_at > NOW() - INTERVAL ? MINUTE"
+    )
+        .bind(ip_address)
+        .bind(IP_WINDOW_MINUTES)
+        .fetch_one(pool)
+        .await?;
+    if failures >= MAX_FAILURES_PER_IP {
+        return Err(AppError::BadRequest("Too many failed login attempts. Please try again later.".to_string()));
+    }
+    Ok(())
+}
+
+/// 账号处于锁定期时拒绝登录（在校验密码之前调用，锁定期间不再尝试密码）
+pub fn check_account(user: &User) -> Result<(), AppError> {
+    match user.locked_until {
+        Some(locked_until) if locked_until > Utc::now() => Err(AppError::BadRequest(format!(
+            "This account is temporarily locked after too many failed login attempts. Try again after {} UTC.",
+            locked_until.format("%Y-%m-%d %H:%M")
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// 记录一次失败的登录。对已存在的账号累加失败次数，达到阈值时锁定并邮件通知用户
+pub async fn record_failure(pool: &MySqlPool, email: &str, ip_address: &str, user: Option<&User>) -> Result<(), AppError> {
+    sqlx::query("INSERT INTO login_attempts (email, ip_address, user_id, succeeded) VALUES (?, ?, ?, FALSE)")
+        .bind(email)
+        .bind(ip_address)
+        .bind(user.map(|u| u.id))
+        .execute(pool)
+        .await?;
+
+    let Some(user) = user else {
+        return Ok(());
+    };
+    // 在数据库里原子地累加，并发的失败请求不会互相覆盖计数；行锁保持到提交，读回的就是本次累加后的值
+    let mut tx = pool.begin().await?;
+    sqlx::query("UPDATE users SET failed_login_count = failed_login_count + 1 WHERE id = ?")
+        .bind(user.id)
+        .execute(&mut *tx)
+        .await?;
+    let (failed_count,): (i32,) = sqlx::query_as("SELECT failed_login_count FROM users WHERE id = ?")
+        .bind(user.id)
+        .fetch_one(&mut *tx)
+        .await?;
+    let locked_until = lockout_minutes(failed_count).map(|minutes| Utc::now() + Duration::minutes(minutes));
+    if locked_until.is_some() {
+        sqlx::query("UPDATE users SET locked_until = ? WHERE id = ?")
+            .bind(locked_until)
+            .bind(user.id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    if let Some(locked_until) = locked_until {
+        log::warn!("User {} locked until {} after {} failed logins (last from {})", user.id, locked_until, failed_count, ip_address);
+        let subject = "Your account has been temporarily locked".to_string();
+        let body = format!(
+            "We detected {} failed sign-in attempts on your account, the most recent from IP address {}.\n\n\
+             To protect your account, sign-in is blocked until {} UTC.\n\n\
+             If this wasn't you, we recommend resetting your password. If you need access sooner, contact the platform administrator.",
+            failed_count,
+            ip_address,
+            locked_until.format("%Y-%m-%d %H:%M")
+        );
+        // 邮件发送失败不影响登录流程
+        if let Err(e) = notification_service::send_email(user.email.clone(), subject, body).await {
+            log::error!("Failed to send lockout email to user {}: {:?}", user.id, e);
+        }
+    }
+    Ok(())
+}
+
+/// 登录成功（开启两步验证的账号需验证码也通过）后记录成功并清空失败次数
+pub async fn record_success(pool: &MySqlPool, email: &str, ip_address: &str, user_id: i32) -> Result<(), AppError> {
+    sqlx::query("INSERT INTO login_attempts (email, ip_address, user_id, succeeded) VALUES (?, ?, ?, TRUE)")
+        .bind(email)
+        .bind(ip_address)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    sqlx::query("UPDATE users SET failed_login_count = 0, locked_until = NULL WHERE id = ?")
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lockout_minutes() {
+        assert_eq!(lockout_minutes(1), None);
+        assert_eq!(lockout_minutes(4), None);
+        assert_eq!(lockout_minutes(5), Some(5));
+        assert_eq!(lockout_minutes(6), Some(10));
+        assert_eq!(lockout_minutes(8), Some(40));
+        assert_eq!(lockout_minutes(20), Some(24 * 60));
+        assert_eq!(lockout_minutes(1000), Some(24 * 60));
+    }
+}
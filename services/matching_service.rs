@@ -5,7 +5,7 @@ use crate::{
     models::rfq::Rfq,
     services::{
         chat_server::ChatServer,
-        notification_service::NotificationBuilder,
+        notification_service,
     },
 };
 use actix::Addr;
@@ -96,25 +96,15 @@ pub async fn find_and_notify_suppliers(
     // 4. 为匹配到的供应商创建并发送通知
     log::info!("Found {} matched suppliers for RFQ #{}. Sending notifications...", matched_suppliers.len(), rfq.id);
     for (company_id,) in matched_suppliers {
-        // 假设一个公司只有一个用户接收通知，实际应用可能更复杂
-        let user_result: Result<(i32,), _> = sqlx::query_as("SELECT id FROM users WHERE company_id = ? LIMIT 1")
-            .bind(company_id)
-            .fetch_one(pool)
-            .await;
-
-        if let Ok((user_id,)) = user_result {
-            // 不通知RFQ的发布者自己
-            if user_id != rfq.buyer_company_id {
-                let message = format!("New high-match opportunity: '{}'", &rfq.title);
-                let link = format!("/rfqs/{}", rfq.id);
-
-                // 使用我们已有的通知服务
-                NotificationBuilder::new(user_id, message)
-                    .with_link(link)
-                    .send(pool, chat_server)
-                    .await?;
-            }
+        // 不通知RFQ的发布者自己
+        if company_id == rfq.buyer_company_id {
+            continue;
         }
+        let message = format!("New high-match opportunity: '{}'", &rfq.title);
+        let link = format!("/rfqs/{}", rfq.id);
+
+        // 通知该供应商公司的所有用户
+        notification_service::notify_company(pool, chat_server, company_id, message, link).await?;
     }
 
     Ok(())
@@ -0,0 +1,289 @@
+// src/services/mfa_service.rs
+
+use crate::{
+    errors::AppError,
+    models::user::{
+        Claims, DisableTotpDto, LoginResponse, MfaChallengeResponse, MfaLoginDto, TotpCodeDto, TotpSetupResponse, User,
+    },
+    services::{admin_service, audit_service::AuditEntry, auth_service, login_guard_service},
+    utils::{auth_utils, totp},
+};
+use chrono::{Duration, Utc};
+use rand::Rng;
+use sqlx::{FromRow, MySqlConnection, MySqlPool, Row};
+
+// 验证器应用中显示的发行方名称
+const TOTP_ISSUER: &str = "SCCP";
+const RECOVERY_CODE_COUNT: usize = 10;
+// 登录第二步的有效时间和最多尝试次数
+const CHALLENGE_EXPIRY_MINUTES: i64 = 5;
+const MAX_CHALLENGE_ATTEMPTS: i32 = 5;
+
+/// 恢复码格式为 xxxxx-xxxxx，输入时忽略大小写、空格和连字符
+fn generate_recovery_code() -> String {
+    let bytes: [u8; 5] = rand::rng().random();
+    let code = hex::encode(bytes);
+    format!("{}-{}", &code[..5], &code[5..])
+}
+
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// 生成一组新的恢复码，旧的恢复码全部作废。返回明文，只展示这一次
+async fn replace_recovery_codes(conn: &mut MySqlConnection, user_id: i32) -> Result<Vec<String>, AppError> {
+    sqlx::query("DELETE FROM user_recovery_codes WHERE user_id = ?")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    let codes: Vec<String> = (0..RECOVERY_CODE_COUNT).map(|_| generate_recovery_code()).collect();
+    for code in &codes {
+        sqlx::query("INSERT INTO user_recovery_codes (user_id, code_hash) VALUES (?, ?)")
+            .bind(user_id)
+            .bind(auth_utils::hash_token(&normalize_recovery_code(code)))
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(codes)
+}
+
+/// 校验第二因素：验证器应用的验证码（同一时间步长只能用一次）或未使用过的恢复码
+async fn verify_second_factor(conn: &mut MySqlConnection, user_id: i32, code: &str) -> Result<bool, AppError> {
+    let (secret, last_step): (Option<String>, Option<i64>) = sqlx::query_as(
+        "SELECT totp_secret, totp_last_step FROM users WHERE id = ? AND totp_enabled_at IS NOT NULL FOR UPDATE"
+    )
+        .bind(user_id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("Two-factor authentication is not enabled.".to_string()))?;
+
+    if let Some(step) = secret.as_deref().and_then(|s| totp::verify(s, code, Utc::now().timestamp())) {
+        if last_step.is_some_and(|last| step <= last) {
+            return Ok(false);
+        }
+        sqlx::query("UPDATE users SET totp_last_step = ? WHERE id = ?")
+            .bind(step)
+            .bind(user_id)
+            .execute(&mut *conn)
+            .await?;
+        return Ok(true);
+    }
+
+    let result = sqlx::query(
+        "UPDATE user_recovery_codes SET used_at = NOW() WHERE user_id = ? AND code_hash = ? AND used_at IS NULL LIMIT 1"
+    )
+        .bind(user_id)
+        .bind(auth_utils::hash_token(&normalize_recovery_code(code)))
+        .execute(&mut *conn)
+        .await?;
+    if result.rows_affected() > 0 {
+        log::info!("User #{} signed in with a recovery code", user_id);
+    }
+    Ok(result.rows_affected() > 0)
+}
+
+/// 开始绑定验证器：生成新密钥并返回扫码地址。验证通过之前两步验证不会生效
+pub async fn setup_totp(pool: &MySqlPool, claims: &Claims) -> Result<TotpSetupResponse, AppError> {
+    let (email, enabled): (String, bool) =
+        sqlx::query_as("SELECT email, totp_enabled_at IS NOT NULL FROM users WHERE id = ?")
+            .bind(claims.sub)
+            .fetch_one(pool)
+            .await?;
+    if enabled {
+        return Err(AppError::BadRequest("Two-factor authentication is already enabled.".to_string()));
+    }
+
+    let secret = totp::generate_secret();
+    sqlx::query("UPDATE users SET totp_secret = ?, totp_last_step = NULL WHERE id = ?")
+        .bind(&secret)
+        .bind(claims.sub)
+        .execute(pool)
+        .await?;
+
+    let provisioning_uri = totp::provisioning_uri(TOTP_ISSUER, &email, &secret);
+    Ok(TotpSetupResponse { secret, provisioning_uri })
+}
+
+/// 用验证器中的第一个验证码确认绑定，开启两步验证并返回恢复码
+pub async fn enable_totp(pool: &MySqlPool, claims: &Claims, dto: TotpCodeDto) -> Result<Vec<String>, AppError> {
+    let mut tx = pool.begin().await?;
+    let (secret, enabled): (Option<String>, bool) =
+        sqlx::query_as("SELECT totp_secret, totp_enabled_at IS NOT NULL FROM users WHERE id = ? FOR UPDATE")
+            .bind(claims.sub)
+            .fetch_one(&mut *tx)
+            .await?;
+    if enabled {
+        return Err(AppError::BadRequest("Two-factor authentication is already enabled.".to_string()));
+    }
+    let secret = secret.ok_or_else(|| AppError::BadRequest("Start the two-factor setup first.".to_string()))?;
+    let step = totp::verify(&secret, &dto.code, Utc::now().timestamp())
+        .ok_or_else(|| AppError::BadRequest("Invalid verification code.".to_string()))?;
+
+    sqlx::query("UPDATE users SET totp_enabled_at = NOW(), totp_last_step = ? WHERE id = ?")
+        .bind(step)
+        .bind(claims.sub)
+        .execute(&mut *tx)
+        .await?;
+    let codes = replace_recovery_codes(&mut tx, claims.sub).await?;
+    tx.commit().await?;
+
+    log::info!("User #{} enabled two-factor authentication", claims.sub);
+    Ok(codes)
+}
+
+/// 关闭两步验证，需要同时提供密码和验证码（或恢复码）
+pub async fn disable_totp(pool: &MySqlPool, claims: &Claims, dto: DisableTotpDto) -> Result<(), AppError> {
+    let (password_hash,): (String,) = sqlx::query_as("SELECT password_hash FROM users WHERE id = ?")
+        .bind(claims.sub)
+        .fetch_one(pool)
+        .await?;
+    let valid_password = auth_utils::verify_password(&dto.password, &password_hash)
+        .map_err(|_| AppError::InternalServerError("Password verification error".to_string()))?;
+    if !valid_password {
+        return Err(AppError::BadRequest("Incorrect password.".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+    if !verify_second_factor(&mut tx, claims.sub, &dto.code).await? {
+        return Err(AppError::BadRequest("Invalid verification code.".to_string()));
+    }
+    clear_totp(&mut tx, claims.sub).await?;
+    tx.commit().await?;
+
+    log::info!("User #{} disabled two-factor authentication", claims.sub);
+    Ok(())
+}
+
+/// 重新生成恢复码，需要提供当前的验证码
+pub async fn regenerate_recovery_codes(pool: &MySqlPool, claims: &Claims, dto: TotpCodeDto) -> Result<Vec<String>, AppError> {
+    let mut tx = pool.begin().await?;
+    if !verify_second_factor(&mut tx, claims.sub, &dto.code).await? {
+        return Err(AppError::BadRequest("Invalid verification code.".to_string()));
+    }
+    let codes = replace_recovery_codes(&mut tx, claims.sub).await?;
+    tx.commit().await?;
+    Ok(codes)
+}
+
+async fn clear_totp(conn: &mut MySqlConnection, user_id: i32) -> Result<(), AppError> {
+    sqlx::query("UPDATE users SET totp_secret = NULL, totp_enabled_at = NULL, totp_last_step = NULL WHERE id = ?")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query("DELETE FROM user_recovery_codes WHERE user_id = ?")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query("UPDATE mfa_challenges SET used_at = NOW() WHERE user_id = ? AND used_at IS NULL")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// 管理员为丢失设备且没有恢复码的用户重置两步验证，用户下次登录后需要重新绑定
+pub async fn admin_reset_totp(pool: &MySqlPool, user_id: i32, claims: &Claims) -> Result<(), AppError> {
+    admin_service::ensure_can_manage_user(pool, user_id, claims).await?;
+    let mut tx = pool.begin().await?;
+    let (company_id, was_enabled): (i32, bool) =
+        sqlx::query_as("SELECT company_id, totp_enabled_at IS NOT NULL FROM users WHERE id = ? FOR UPDATE")
+            .bind(user_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| AppError::BadRequest("User not found".to_string()))?;
+    clear_totp(&mut tx, user_id).await?;
+    AuditEntry::new(claims, "USER_2FA_RESET", "USER", user_id)
+        .company(company_id)
+        .before(serde_json::json!({ "totp_enabled": was_enabled }))
+        .after(serde_json::json!({ "totp_enabled": false }))
+        .record(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    log::warn!("Admin #{} reset two-factor authentication for user #{}", claims.sub, user_id);
+    Ok(())
+}
+
+/// 密码校验通过后创建登录的第二步凭据
+pub(crate) async fn create_challenge(pool: &MySqlPool, user_id: i32) -> Result<MfaChallengeResponse, AppError> {
+    let token = auth_utils::generate_token();
+    sqlx::query("INSERT INTO mfa_challenges (user_id, token_hash, expires_at) VALUES (?, ?, ?)")
+        .bind(user_id)
+        .bind(auth_utils::hash_token(&token))
+        .bind(Utc::now() + Duration::minutes(CHALLENGE_EXPIRY_MINUTES))
+        .execute(pool)
+        .await?;
+
+    Ok(MfaChallengeResponse {
+        mfa_required: true,
+        mfa_token: token,
+        expires_in: CHALLENGE_EXPIRY_MINUTES * 60,
+    })
+}
+
+/// 登录第二步：校验验证码或恢复码，通过后开启登录会话
+pub async fn complete_login(pool: &MySqlPool, dto: MfaLoginDto, ip_address: &str) -> Result<LoginResponse, AppError> {
+    login_guard_service::check_ip(pool, ip_address).await?;
+
+    let mut tx = pool.begin().await?;
+    let (challenge_id, user_id, attempts): (i32, i32, i32) = sqlx::query_as(
+        "SELECT id, user_id, attempts FROM mfa_challenges WHERE token_hash = ? AND used_at IS NULL AND expires_at > NOW() FOR UPDATE"
+    )
+        .bind(auth_utils::hash_token(&dto.mfa_token))
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(AppError::AuthError)?;
+    if attempts >= MAX_CHALLENGE_ATTEMPTS {
+        return Err(AppError::AuthError);
+    }
+
+    let row = sqlx::query("SELECT u.*, c.company_type FROM users u JOIN companies c ON u.company_id = c.id WHERE u.id = ?")
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    let user = User::from_row(&row)?;
+    let company_type: String = row.try_get("company_type")?;
+    // 第二步验证失败同样计入登录失败次数，锁定期间不再校验验证码
+    login_guard_service::check_account(&user)?;
+
+    if !verify_second_factor(&mut tx, user_id, &dto.code).await? {
+        sqlx::query("UPDATE mfa_challenges SET attempts = attempts + 1 WHERE id = ?")
+            .bind(challenge_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        login_guard_service::record_failure(pool, &user.email, ip_address, Some(&user)).await?;
+        return Err(AppError::BadRequest("Invalid verification code.".to_string()));
+    }
+
+    sqlx::query("UPDATE mfa_challenges SET used_at = NOW() WHERE id = ?")
+        .bind(challenge_id)
+        .execute(&mut *tx)
+        .await?;
+    if !user.is_active {
+        return Err(AppError::BadRequest("This account has been disabled.".to_string()));
+    }
+
+    let response = auth_service::start_session(&mut tx, &user, &company_type).await?;
+    tx.commit().await?;
+    login_guard_service::record_success(pool, &user.email, ip_address, user.id).await?;
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recovery_code_format() {
+        let code = generate_recovery_code();
+        assert_eq!(code.len(), 11);
+        assert_eq!(&code[5..6], "-");
+        assert_eq!(normalize_recovery_code(" AB12C-de34F "), "ab12cde34f");
+        assert_eq!(normalize_recovery_code(&code), code.replace('-', ""));
+    }
+}
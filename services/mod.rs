@@ -9,7 +9,21 @@ pub(crate) mod company_service;
 pub(crate) mod user_service;
 pub(crate) mod analytics_service;
 pub(crate) mod payment_service;
+pub(crate) mod payment_provider;
 pub(crate) mod admin_service;
 pub(crate) mod capability_service;
 pub mod matching_service;
+pub(crate) mod scheduler;
+pub(crate) mod shipment_service;
+pub(crate) mod dispute_service;
+pub(crate) mod rma_service;
+pub(crate) mod change_order_service;
+pub(crate) mod document_service;
+pub(crate) mod invoice_service;
+pub(crate) mod fx_service;
+pub(crate) mod invitation_service;
+pub(crate) mod mfa_service;
+pub(crate) mod api_key_service;
+pub(crate) mod login_guard_service;
+pub(crate) mod audit_service;
 // <-- 新增
\ No newline at end of file
@@ -14,16 +14,19 @@ use crate::{
 use actix::Addr;
 use sqlx::MySqlPool;
 pub async fn send_email(to: String, subject: String, body: String) -> Result<(), AppError> {
-    let from = env::var("SMTP_FROM").expect("SMTP_FROM must be set");
-    let smtp_user = env::var("SMTP_USER").expect("SMTP_USER must be set");
-    let smtp_pass = env::var("SMTP_PASS").expect("SMTP_PASS must be set");
-    let smtp_host = env::var("SMTP_HOST").expect("SMTP_HOST must be set");
-    let smtp_port_str = env::var("SMTP_PORT").expect("SMTP_PORT must be set");
-    let smtp_port = smtp_port_str.parse::<u16>().expect("SMTP_PORT must be a valid number");
+    // 缺少SMTP配置时返回错误而不是panic，调用方可以决定是否忽略邮件发送失败
+    let smtp_env = |key: &str| env::var(key).map_err(|_| AppError::InternalServerError(format!("{} must be set", key)));
+    let from = smtp_env("SMTP_FROM")?;
+    let smtp_user = smtp_env("SMTP_USER")?;
+    let smtp_pass = smtp_env("SMTP_PASS")?;
+    let smtp_host = smtp_env("SMTP_HOST")?;
+    let smtp_port = smtp_env("SMTP_PORT")?
+        .parse::<u16>()
+        .map_err(|_| AppError::InternalServerError("SMTP_PORT must be a valid number".to_string()))?;
 
 
     let email = Message::builder()
-        .from(from.parse().unwrap())
+        .from(from.parse().map_err(|_| AppError::InternalServerError("Invalid 'from' email address".to_string()))?)
         .to(to.parse().map_err(|_| AppError::InternalServerError("Invalid 'to' email address".to_string()))?)
         .subject(subject)
         .body(body)
@@ -122,6 +125,37 @@ impl NotificationBuilder {
     }
 }
 
+/// 某个公司所有启用中的用户 (user_id, email)，用于通知的分发
+pub async fn company_recipients(pool: &MySqlPool, company_id: i32) -> Result<Vec<(i32, String)>, AppError> {
+    let recipients = sqlx::query_as("SELECT id, email FROM users WHERE company_id = ? AND is_active = TRUE ORDER BY id")
+        .bind(company_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(recipients)
+}
+
+// 向某个公司的所有用户发送站内通知。单个用户发送失败不影响其他用户
+pub async fn notify_company(
+    pool: &MySqlPool,
+    chat_server: &Addr<ChatServer>,
+    company_id: i32,
+    message: String,
+    link_url: String,
+) -> Result<(), AppError> {
+    let recipients = company_recipients(pool, company_id).await?;
+    for (user_id, _) in recipients {
+        let result = NotificationBuilder::new(user_id, message.clone())
+            .with_link(link_url.clone())
+            .send(pool, chat_server)
+            .await;
+        if let Err(e) = result {
+            log::error!("Failed to notify user #{} of company #{}: {:?}", user_id, company_id, e);
+        }
+    }
+
+    Ok(())
+}
+
 // 获取用户的所有通知
 pub async fn get_notifications_for_user(pool: &MySqlPool, claims: &Claims) -> Result<Vec<Notification>, AppError> {
     let notifications = sqlx::query_as("SELECT * FROM notifications WHERE recipient_user_id = ? ORDER BY created_at DESC")
@@ -2,65 +2,631 @@
 
 use crate::{
     errors::AppError,
-    models::{order::{PurchaseOrder, UpdateOrderStatusDto}, user::Claims},
+    models::{
+        order::{
+            CancellationRequest, CancellationRequestDto, ConfirmOrderDto, DeclineOrderDto, OrderDetail, OrderFilterParams,
+            OrderListResponse, OrderStatusHistory, PurchaseOrder, RespondCancellationDto, UpdateOrderStatusDto,
+        },
+        user::{Claims, APPROVER_ROLES, OPERATOR_ROLES},
+    },
+    services::{audit_service::AuditEntry, change_order_service, chat_server::ChatServer, invoice_service, notification_service, payment_service, rfq_service},
+    utils::auth_utils,
 };
-use sqlx::MySqlPool;
-pub async fn get_orders_for_user(pool: &MySqlPool, claims: &Claims) -> Result<Vec<PurchaseOrder>, AppError> {
-    let sql_query = if claims.company_type == "BUYER" {
-        "SELECT po.*, r.title as rfq_title, b.name as buyer_name, s.name as supplier_name
-         FROM purchase_orders po
-         JOIN rfqs r ON po.rfq_id = r.id
-         JOIN companies b ON po.buyer_company_id = b.id
-         JOIN companies s ON po.supplier_company_id = s.id
-         WHERE po.buyer_company_id = ? ORDER BY po.created_at DESC"
+use actix::Addr;
+use sqlx::{types::Decimal, MySql, MySqlConnection, MySqlPool, QueryBuilder};
+use std::str::FromStr;
+
+// 采购订单状态机：(当前状态, 目标状态, 允许执行该转换的公司类型)
+const ORDER_TRANSITIONS: &[(&str, &str, &str)] = &[
+    ("PENDING_CONFIRMATION", "CONFIRMED", "SUPPLIER"),
+    ("PENDING_CONFIRMATION", "DECLINED", "SUPPLIER"),
+    ("CONFIRMED", "IN_PRODUCTION", "SUPPLIER"),
+    ("IN_PRODUCTION", "SHIPPED", "SUPPLIER"),
+    ("SHIPPED", "DELIVERED", "BUYER"),
+    ("SHIPPED", "DISPUTED", "BUYER"),
+    ("DELIVERED", "COMPLETED", "BUYER"),
+    ("DELIVERED", "DISPUTED", "BUYER"),
+    // 取消需要双方同意，由同意取消申请的一方执行
+    ("PENDING_CONFIRMATION", "CANCELLED", "BUYER"),
+    ("PENDING_CONFIRMATION", "CANCELLED", "SUPPLIER"),
+    ("CONFIRMED", "CANCELLED", "BUYER"),
+    ("CONFIRMED", "CANCELLED", "SUPPLIER"),
+    ("IN_PRODUCTION", "CANCELLED", "BUYER"),
+    ("IN_PRODUCTION", "CANCELLED", "SUPPLIER"),
+];
+
+/// 判断某类公司能否将订单从 `from` 状态转换到 `to` 状态
+pub fn can_transition(from: &str, to: &str, company_type: &str) -> bool {
+    ORDER_TRANSITIONS
+        .iter()
+        .any(|(f, t, role)| *f == from && *t == to && *role == company_type)
+}
+const DEFAULT_PAGE_SIZE: u32 = 20;
+const MAX_PAGE_SIZE: u32 = 100;
+
+const ORDER_SELECT: &str =
+    "SELECT po.*, r.title as rfq_title, b.name as buyer_name, s.name as supplier_name
+     FROM purchase_orders po
+     JOIN rfqs r ON po.rfq_id = r.id
+     JOIN companies b ON po.buyer_company_id = b.id
+     JOIN companies s ON po.supplier_company_id = s.id";
+
+// 根据当前公司和筛选参数拼接 WHERE 条件
+fn push_order_filters(qb: &mut QueryBuilder<MySql>, claims: &Claims, params: &OrderFilterParams) -> Result<(), AppError> {
+    let (own_column, counterparty_column) = if claims.company_type == "BUYER" {
+        ("po.buyer_company_id", "po.supplier_company_id")
     } else {
-        "SELECT po.*, r.title as rfq_title, b.name as buyer_name, s.name as supplier_name
-         FROM purchase_orders po
-         JOIN rfqs r ON po.rfq_id = r.id
-         JOIN companies b ON po.buyer_company_id = b.id
-         JOIN companies s ON po.supplier_company_id = s.id
-         WHERE po.supplier_company_id = ? ORDER BY po.created_at DESC"
+        ("po.supplier_company_id", "po.buyer_company_id")
     };
+    qb.push(" WHERE ").push(own_column).push(" = ").push_bind(claims.company_id);
 
-    let orders = sqlx::query_as(sql_query)
+    if let Some(status) = &params.status {
+        qb.push(" AND po.status = ").push_bind(status.clone());
+    }
+    if let Some(payment_status) = &params.payment_status {
+        qb.push(" AND po.payment_status = ").push_bind(payment_status.clone());
+    }
+    if let Some(counterparty_id) = params.counterparty_id {
+        qb.push(" AND ").push(counterparty_column).push(" = ").push_bind(counterparty_id);
+    }
+    if let Some(from) = params.created_from {
+        qb.push(" AND po.created_at >= ").push_bind(from);
+    }
+    if let Some(to) = params.created_to {
+        // 结束日期包含当天
+        qb.push(" AND po.created_at < ").push_bind(to + chrono::Days::new(1));
+    }
+    if let Some(min_amount) = params.min_amount {
+        let min_amount = Decimal::from_str(&min_amount.to_string())
+            .map_err(|_| AppError::BadRequest("Invalid minimum amount".to_string()))?;
+        qb.push(" AND po.total_amount >= ").push_bind(min_amount);
+    }
+    if let Some(max_amount) = params.max_amount {
+        let max_amount = Decimal::from_str(&max_amount.to_string())
+            .map_err(|_| AppError::BadRequest("Invalid maximum amount".to_string()))?;
+        qb.push(" AND po.total_amount <= ").push_bind(max_amount);
+    }
+    Ok(())
+}
+
+/// 按筛选条件分页获取当前公司的订单
+pub async fn get_orders_for_user(
+    pool: &MySqlPool,
+    claims: &Claims,
+    params: OrderFilterParams,
+) -> Result<OrderListResponse, AppError> {
+    let page = params.page.unwrap_or(1).max(1);
+    let page_size = params.page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+    let mut count_qb: QueryBuilder<MySql> = QueryBuilder::new("SELECT COUNT(*) FROM purchase_orders po");
+    push_order_filters(&mut count_qb, claims, &params)?;
+    let (total,): (i64,) = count_qb.build_query_as().fetch_one(pool).await?;
+
+    let mut qb: QueryBuilder<MySql> = QueryBuilder::new(ORDER_SELECT);
+    push_order_filters(&mut qb, claims, &params)?;
+    qb.push(" ORDER BY po.created_at DESC, po.id DESC LIMIT ")
+        .push_bind(page_size)
+        .push(" OFFSET ")
+        .push_bind((page - 1) as u64 * page_size as u64);
+    let orders = qb.build_query_as().fetch_all(pool).await?;
+
+    Ok(OrderListResponse { orders, total, page, page_size })
+}
+
+/// 获取订单详情，只有订单双方可以查看
+pub async fn get_order_detail(pool: &MySqlPool, order_id: i32, claims: &Claims) -> Result<OrderDetail, AppError> {
+    let order: PurchaseOrder = sqlx::query_as(&format!(
+        "{} WHERE po.id = ? AND (po.buyer_company_id = ? OR po.supplier_company_id = ?)",
+        ORDER_SELECT
+    ))
+        .bind(order_id)
         .bind(claims.company_id)
-        .fetch_all(pool)
+        .bind(claims.company_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("Order not found or you are not authorized to view it.".to_string()))?;
+
+    let rfq = rfq_service::get_rfq_by_id(pool, order.rfq_id).await?;
+    let quote = sqlx::query_as(
+        "SELECT q.*, c.name as supplier_company_name FROM quotes q JOIN companies c ON q.supplier_company_id = c.id WHERE q.id = ?"
+    )
+        .bind(order.quote_id)
+        .fetch_one(pool)
         .await?;
+    let status_history = get_status_history(pool, order_id, claims).await?;
+    let change_requests = change_order_service::get_change_requests(pool, order_id, claims).await?;
+    let versions = change_order_service::get_order_versions(pool, order_id, claims).await?;
+    let invoices = invoice_service::get_invoices_for_order(pool, order_id, claims).await?;
+    let payment_summary = payment_service::get_order_payments(pool, order_id, claims).await?;
 
-    Ok(orders)
+    Ok(OrderDetail { order, rfq, quote, status_history, change_requests, versions, invoices, payment_summary })
 }
 
+/// 更新订单状态，并写入状态历史。状态变更由状态机和操作方角色共同约束
 pub async fn update_order_status(
     pool: &MySqlPool,
+    chat_server: &Addr<ChatServer>,
     order_id: i32,
     dto: UpdateOrderStatusDto,
     claims: &Claims,
+) -> Result<(), AppError> {
+    auth_utils::require_role(claims, OPERATOR_ROLES)?;
+    let new_status = dto.status.as_str();
+    // 以下状态需要额外信息或对方参与，必须走专门的接口
+    let dedicated_flow = match new_status {
+        "CONFIRMED" | "DECLINED" => Some("Use the confirm or decline endpoint for this order."),
+        "SHIPPED" | "DELIVERED" => Some("Shipping progress is tracked through shipments and goods receipts."),
+        "CANCELLED" => Some("Submit a cancellation request for this order instead."),
+        "DISPUTED" => Some("Open a dispute for this order instead."),
+        _ => None,
+    };
+    if let Some(message) = dedicated_flow {
+        return Err(AppError::BadRequest(message.to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+    let (current_status, buyer_company_id, supplier_company_id) = lock_order(&mut tx, order_id, claims).await?;
+
+    if !can_transition(&current_status, new_status, &claims.company_type) {
+        return Err(AppError::BadRequest(format!(
+            "Cannot change order status from {} to {}.",
+            current_status, new_status
+        )));
+    }
+
+    apply_status_change(&mut tx, order_id, Some(&current_status), new_status, claims, dto.comment).await?;
+    tx.commit().await?;
+
+    // 通知对方
+    let counterparty_id = if claims.company_id == buyer_company_id { supplier_company_id } else { buyer_company_id };
+    if let Err(e) = notification_service::notify_company(
+        pool,
+        chat_server,
+        counterparty_id,
+        format!("Order #{} status changed to {}", order_id, new_status),
+        format!("/orders/{}", order_id),
+    ).await {
+        log::error!("Failed to send order status notification: {:?}", e);
+    }
+
+    Ok(())
+}
+
+/// 锁定订单行并返回 (status, buyer_company_id, supplier_company_id)，同时确保操作者是订单的一方
+pub(crate) async fn lock_order(
+    conn: &mut MySqlConnection,
+    order_id: i32,
+    claims: &Claims,
+) -> Result<(String, i32, i32), AppError> {
+    sqlx::query_as(
+        "SELECT status, buyer_company_id, supplier_company_id FROM purchase_orders WHERE id = ? FOR UPDATE"
+    )
+        .bind(order_id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .filter(|(_, buyer, supplier): &(String, i32, i32)| *buyer == claims.company_id || *supplier == claims.company_id)
+        .ok_or_else(|| AppError::BadRequest("Order not found or you are not authorized to update it.".to_string()))
+}
+
+/// 供应商确认订单，并承诺发货日期
+pub async fn confirm_order(
+    pool: &MySqlPool,
+    chat_server: &Addr<ChatServer>,
+    order_id: i32,
+    dto: ConfirmOrderDto,
+    claims: &Claims,
+) -> Result<(), AppError> {
+    auth_utils::require_role(claims, OPERATOR_ROLES)?;
+    if dto.committed_ship_date < chrono::Utc::now().date_naive() {
+        return Err(AppError::BadRequest("Committed ship date cannot be in the past.".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+    let (current_status, buyer_company_id, _) = lock_order(&mut tx, order_id, claims).await?;
+
+    if !can_transition(&current_status, "CONFIRMED", &claims.company_type) {
+        return Err(AppError::BadRequest("Only the supplier can confirm an order awaiting confirmation.".to_string()));
+    }
+
+    sqlx::query(
+        "UPDATE purchase_orders SET committed_ship_date = ?, confirmed_at = NOW(), confirmation_overdue = FALSE WHERE id = ?"
+    )
+        .bind(dto.committed_ship_date)
+        .bind(order_id)
+        .execute(&mut *tx)
+        .await?;
+
+    apply_status_change(&mut tx, order_id, Some(&current_status), "CONFIRMED", claims, dto.comment).await?;
+    tx.commit().await?;
+
+    if let Err(e) = notification_service::notify_company(
+        pool,
+        chat_server,
+        buyer_company_id,
+        format!("Order #{} was confirmed. Committed ship date: {}", order_id, dto.committed_ship_date),
+        format!("/orders/{}", order_id),
+    ).await {
+        log::error!("Failed to send order confirmation notification: {:?}", e);
+    }
+
+    Ok(())
+}
+
+/// 供应商拒绝订单：订单标记为 DECLINED，对应报价被拒绝，RFQ 重新开放报价
+pub async fn decline_order(
+    pool: &MySqlPool,
+    chat_server: &Addr<ChatServer>,
+    order_id: i32,
+    dto: DeclineOrderDto,
+    claims: &Claims,
+) -> Result<(), AppError> {
+    auth_utils::require_role(claims, OPERATOR_ROLES)?;
+    if dto.reason.trim().is_empty() {
+        return Err(AppError::BadRequest("A reason is required to decline an order.".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+    let (current_status, buyer_company_id, _) = lock_order(&mut tx, order_id, claims).await?;
+
+    if !can_transition(&current_status, "DECLINED", &claims.company_type) {
+        return Err(AppError::BadRequest("Only the supplier can decline an order awaiting confirmation.".to_string()));
+    }
+
+    let (quote_id, rfq_id): (i32, i32) = sqlx::query_as("SELECT quote_id, rfq_id FROM purchase_orders WHERE id = ?")
+        .bind(order_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    sqlx::query("UPDATE quotes SET status = 'REJECTED' WHERE id = ?")
+        .bind(quote_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("UPDATE rfqs SET status = 'OPEN' WHERE id = ?")
+        .bind(rfq_id)
+        .execute(&mut *tx)
+        .await?;
+
+    apply_status_change(&mut tx, order_id, Some(&current_status), "DECLINED", claims, Some(dto.reason.clone())).await?;
+    tx.commit().await?;
+
+    if let Err(e) = notification_service::notify_company(
+        pool,
+        chat_server,
+        buyer_company_id,
+        format!("Order #{} was declined by the supplier: {}. The RFQ is open for quotes again.", order_id, dto.reason),
+        format!("/rfqs/{}", rfq_id),
+    ).await {
+        log::error!("Failed to send order decline notification: {:?}", e);
+    }
+
+    Ok(())
+}
+
+/// 标记超过确认时限仍未被供应商确认的订单，并通知双方。返回新标记的订单数
+pub async fn flag_unconfirmed_orders(
+    pool: &MySqlPool,
+    chat_server: &Addr<ChatServer>,
+    sla_hours: i64,
+) -> Result<usize, AppError> {
+    let overdue: Vec<(i32, i32, i32)> = sqlx::query_as(
+        "SELECT id, buyer_company_id, supplier_company_id FROM purchase_orders
+         WHERE status = 'PENDING_CONFIRMATION' AND confirmation_overdue = FALSE
+           AND created_at < NOW() - INTERVAL ? HOUR"
+    )
+        .bind(sla_hours)
+        .fetch_all(pool)
+        .await?;
+
+    for (order_id, buyer_company_id, supplier_company_id) in &overdue {
+        sqlx::query("UPDATE purchase_orders SET confirmation_overdue = TRUE WHERE id = ?")
+            .bind(order_id)
+            .execute(pool)
+            .await?;
+
+        let message = format!("Order #{} has not been confirmed within {} hours.", order_id, sla_hours);
+        for company_id in [buyer_company_id, supplier_company_id] {
+            if let Err(e) = notification_service::notify_company(
+                pool,
+                chat_server,
+                *company_id,
+                message.clone(),
+                format!("/orders/{}", order_id),
+            ).await {
+                log::error!("Failed to send confirmation SLA notification: {:?}", e);
+            }
+        }
+    }
+
+    Ok(overdue.len())
+}
+
+/// 检查尚未发货的订单是否能按期发货：截止日期为承诺发货日期，未确认时为授标时的预计发货日期。
+/// 距截止日期不足 `at_risk_days` 天的订单标记为有风险，已过截止日期的标记为延误，并通知双方。
+/// 截止日期因变更单推迟后，订单会恢复为正常。返回新标记的订单数
+pub async fn flag_delivery_risks(
+    pool: &MySqlPool,
+    chat_server: &Addr<ChatServer>,
+    at_risk_days: i64,
+) -> Result<usize, AppError> {
+    let orders: Vec<(i32, i32, i32, String, String, chrono::NaiveDate)> = sqlx::query_as(
+        "SELECT id, buyer_company_id, supplier_company_id, delivery_risk,
+                CASE WHEN COALESCE(committed_ship_date, expected_ship_date) < CURDATE() THEN 'LATE'
+                     WHEN COALESCE(committed_ship_date, expected_ship_date) <= CURDATE() + INTERVAL ? DAY THEN 'AT_RISK'
+                     ELSE 'ON_TRACK' END AS new_risk,
+                COALESCE(committed_ship_date, expected_ship_date) AS deadline
+         FROM purchase_orders
+         WHERE status IN ('PENDING_CONFIRMATION', 'CONFIRMED', 'IN_PRODUCTION')
+           AND COALESCE(committed_ship_date, expected_ship_date) IS NOT NULL"
+    )
+        .bind(at_risk_days)
+        .fetch_all(pool)
+        .await?;
+
+    let mut flagged = 0;
+    for (order_id, buyer_company_id, supplier_company_id, risk, new_risk, deadline) in &orders {
+        if risk == new_risk {
+            continue;
+        }
+        sqlx::query("UPDATE purchase_orders SET delivery_risk = ? WHERE id = ?")
+            .bind(new_risk)
+            .bind(order_id)
+            .execute(pool)
+            .await?;
+
+        let message = match new_risk.as_str() {
+            "LATE" => format!("Order #{} has not shipped by its ship date of {}.", order_id, deadline),
+            "AT_RISK" => format!("Order #{} is due to ship by {} and has not shipped yet.", order_id, deadline),
+            _ => continue,
+        };
+        flagged += 1;
+        for company_id in [buyer_company_id, supplier_company_id] {
+            if let Err(e) = notification_service::notify_company(
+                pool,
+                chat_server,
+                *company_id,
+                message.clone(),
+                format!("/orders/{}", order_id),
+            ).await {
+                log::error!("Failed to send delivery risk notification: {:?}", e);
+            }
+        }
+    }
+
+    Ok(flagged)
+}
+
+/// 订单一方发起取消申请，需要对方同意
+pub async fn request_cancellation(
+    pool: &MySqlPool,
+    chat_server: &Addr<ChatServer>,
+    order_id: i32,
+    dto: CancellationRequestDto,
+    claims: &Claims,
 ) -> Result<u64, AppError> {
-    // 权限检查：只有供应商才能更新订单状态
-    if claims.company_type != "SUPPLIER" {
-        return Err(AppError::BadRequest("Only suppliers can update order status.".to_string()));
+    auth_utils::require_role(claims, OPERATOR_ROLES)?;
+    if dto.reason.trim().is_empty() {
+        return Err(AppError::BadRequest("A reason is required to cancel an order.".to_string()));
     }
 
-    // 验证新状态是否合法
-    let new_status = dto.status.as_str();
-    if !matches!(new_status, "IN_PRODUCTION" | "SHIPPED" | "COMPLETED") {
-        return Err(AppError::BadRequest("Invalid status provided.".to_string()));
+    let mut tx = pool.begin().await?;
+    let (current_status, buyer_company_id, supplier_company_id) = lock_order(&mut tx, order_id, claims).await?;
+
+    if !can_transition(&current_status, "CANCELLED", &claims.company_type) {
+        return Err(AppError::BadRequest(format!("An order in status {} can no longer be cancelled.", current_status)));
+    }
+
+    let pending: Option<(i32,)> = sqlx::query_as(
+        "SELECT id FROM order_cancellation_requests WHERE order_id = ? AND status = 'PENDING'"
+    )
+        .bind(order_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    if pending.is_some() {
+        return Err(AppError::BadRequest("There is already a pending cancellation request for this order.".to_string()));
     }
 
-    // 执行更新，并确保该供应商确实是此订单的供应商
     let result = sqlx::query(
-        "UPDATE purchase_orders SET status = ? WHERE id = ? AND supplier_company_id = ?"
+        "INSERT INTO order_cancellation_requests (order_id, requested_by_company_id, requested_by_user_id, reason) VALUES (?, ?, ?, ?)"
+    )
+        .bind(order_id)
+        .bind(claims.company_id)
+        .bind(claims.sub)
+        .bind(&dto.reason)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    let counterparty_id = if claims.company_id == buyer_company_id { supplier_company_id } else { buyer_company_id };
+    if let Err(e) = notification_service::notify_company(
+        pool,
+        chat_server,
+        counterparty_id,
+        format!("Cancellation of order #{} was requested: {}", order_id, dto.reason),
+        format!("/orders/{}", order_id),
+    ).await {
+        log::error!("Failed to send cancellation request notification: {:?}", e);
+    }
+
+    Ok(result.last_insert_id())
+}
+
+/// 对方同意或拒绝取消申请；同意后订单进入 CANCELLED 状态
+pub async fn respond_to_cancellation(
+    pool: &MySqlPool,
+    chat_server: &Addr<ChatServer>,
+    order_id: i32,
+    request_id: i32,
+    dto: RespondCancellationDto,
+    claims: &Claims,
+) -> Result<(), AppError> {
+    auth_utils::require_role(claims, APPROVER_ROLES)?;
+    let mut tx = pool.begin().await?;
+    let (current_status, _, _) = lock_order(&mut tx, order_id, claims).await?;
+
+    let request: CancellationRequest = sqlx::query_as(
+        "SELECT * FROM order_cancellation_requests WHERE id = ? AND order_id = ? FOR UPDATE"
+    )
+        .bind(request_id)
+        .bind(order_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("Cancellation request not found".to_string()))?;
+
+    if request.status != "PENDING" {
+        return Err(AppError::BadRequest("This cancellation request has already been answered.".to_string()));
+    }
+    // 发起方不能自己批准自己的申请
+    if request.requested_by_company_id == claims.company_id {
+        return Err(AppError::BadRequest("The other party must respond to this cancellation request.".to_string()));
+    }
+
+    let new_request_status = if dto.approve { "APPROVED" } else { "REJECTED" };
+    sqlx::query(
+        "UPDATE order_cancellation_requests SET status = ?, responded_by_user_id = ?, responded_at = NOW() WHERE id = ?"
+    )
+        .bind(new_request_status)
+        .bind(claims.sub)
+        .bind(request_id)
+        .execute(&mut *tx)
+        .await?;
+
+    if dto.approve {
+        if !can_transition(&current_status, "CANCELLED", &claims.company_type) {
+            return Err(AppError::BadRequest(format!("An order in status {} can no longer be cancelled.", current_status)));
+        }
+        apply_status_change(
+            &mut tx,
+            order_id,
+            Some(&current_status),
+            "CANCELLED",
+            claims,
+            Some(format!("Cancelled by mutual agreement: {}", request.reason)),
+        ).await?;
+    }
+    tx.commit().await?;
+
+    let message = if dto.approve {
+        format!("Your cancellation request for order #{} was approved. The order is cancelled.", order_id)
+    } else {
+        format!("Your cancellation request for order #{} was rejected.", order_id)
+    };
+    if let Err(e) = notification_service::notify_company(
+        pool,
+        chat_server,
+        request.requested_by_company_id,
+        message,
+        format!("/orders/{}", order_id),
+    ).await {
+        log::error!("Failed to send cancellation response notification: {:?}", e);
+    }
+
+    Ok(())
+}
+
+/// 获取订单的取消申请记录
+pub async fn get_cancellation_requests(
+    pool: &MySqlPool,
+    order_id: i32,
+    claims: &Claims,
+) -> Result<Vec<CancellationRequest>, AppError> {
+    ensure_order_party(pool, order_id, claims).await?;
+
+    let requests = sqlx::query_as("SELECT * FROM order_cancellation_requests WHERE order_id = ? ORDER BY created_at DESC")
+        .bind(order_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(requests)
+}
+
+/// 在调用方的事务中修改订单状态并写入一条状态历史和审计日志
+pub(crate) async fn apply_status_change(
+    conn: &mut MySqlConnection,
+    order_id: i32,
+    from_status: Option<&str>,
+    to_status: &str,
+    claims: &Claims,
+    comment: Option<String>,
+) -> Result<(), AppError> {
+    sqlx::query("UPDATE purchase_orders SET status = ? WHERE id = ?")
+        .bind(to_status)
+        .bind(order_id)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query(
+        "INSERT INTO order_status_history (order_id, from_status, to_status, actor_user_id, actor_company_id, comment) VALUES (?, ?, ?, ?, ?, ?)"
     )
-        .bind(new_status)
         .bind(order_id)
+        .bind(from_status)
+        .bind(to_status)
+        .bind(claims.sub)
         .bind(claims.company_id)
-        .execute(pool)
+        .bind(&comment)
+        .execute(&mut *conn)
+        .await?;
+
+    AuditEntry::new(claims, "ORDER_STATUS_CHANGED", "ORDER", order_id)
+        .before(serde_json::json!({ "status": from_status }))
+        .after(serde_json::json!({ "status": to_status, "comment": comment }))
+        .record(&mut *conn)
         .await?;
 
-    // 如果没有行被影响，说明订单不存在或该供应商无权修改
-    if result.rows_affected() == 0 {
-        return Err(AppError::BadRequest("Order not found or you are not authorized to update it.".to_string()));
+    Ok(())
+}
+
+/// 确保操作者是订单的一方（采购方或供应商）
+pub(crate) async fn ensure_order_party(pool: &MySqlPool, order_id: i32, claims: &Claims) -> Result<(), AppError> {
+    let order: Option<(i32,)> = sqlx::query_as(
+        "SELECT id FROM purchase_orders WHERE id = ? AND (buyer_company_id = ? OR supplier_company_id = ?)"
+    )
+        .bind(order_id)
+        .bind(claims.company_id)
+        .bind(claims.company_id)
+        .fetch_optional(pool)
+        .await?;
+
+    if order.is_none() {
+        return Err(AppError::BadRequest("Order not found or you are not authorized to view it.".to_string()));
     }
+    Ok(())
+}
+
+/// 获取订单的状态变更历史，只有订单双方可以查看
+pub async fn get_status_history(
+    pool: &MySqlPool,
+    order_id: i32,
+    claims: &Claims,
+) -> Result<Vec<OrderStatusHistory>, AppError> {
+    ensure_order_party(pool, order_id, claims).await?;
 
-    Ok(result.rows_affected())
-}
\ No newline at end of file
+    let history = sqlx::query_as("SELECT * FROM order_status_history WHERE order_id = ? ORDER BY created_at ASC, id ASC")
+        .bind(order_id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(history)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_order_transitions_follow_lifecycle() {
+        // 正常流程
+        assert!(can_transition("PENDING_CONFIRMATION", "CONFIRMED", "SUPPLIER"));
+        assert!(can_transition("CONFIRMED", "IN_PRODUCTION", "SUPPLIER"));
+        assert!(can_transition("IN_PRODUCTION", "SHIPPED", "SUPPLIER"));
+        assert!(can_transition("SHIPPED", "DELIVERED", "BUYER"));
+        assert!(can_transition("DELIVERED", "COMPLETED", "BUYER"));
+
+        // 不允许跳过状态
+        assert!(!can_transition("PENDING_CONFIRMATION", "SHIPPED", "SUPPLIER"));
+        assert!(!can_transition("CONFIRMED", "COMPLETED", "SUPPLIER"));
+
+        // 不允许由错误的一方执行
+        assert!(!can_transition("PENDING_CONFIRMATION", "CONFIRMED", "BUYER"));
+        assert!(!can_transition("SHIPPED", "DELIVERED", "SUPPLIER"));
+        assert!(!can_transition("DELIVERED", "COMPLETED", "SUPPLIER"));
+    }
+}
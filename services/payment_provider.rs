@@ -0,0 +1,480 @@
+// src/services/payment_provider.rs
+
+use crate::{config::Config, errors::AppError};
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use sqlx::types::Decimal;
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+// 导入 ToPrimitive trait 以使用 .to_i64()
+use num_traits::ToPrimitive;
+// 导入 async_stripe 的相关模块
+use stripe::{
+    Client,
+    CheckoutSession, CheckoutSessionId, CheckoutSessionMode, CheckoutSessionPaymentStatus, CheckoutSessionStatus, CreateCheckoutSession, CreateCheckoutSessionLineItems,
+    CreateCheckoutSessionLineItemsPriceData, CreateCheckoutSessionLineItemsPriceDataProductData, CreateRefund,
+    EventObject, Refund, RequestStrategy, Webhook,
+};
+
+/// 支付渠道创建的结账会话
+#[derive(Debug, Clone)]
+pub struct PaymentSession {
+    pub id: String,
+    // 采购方跳转付款的地址
+    pub url: Option<String>,
+}
+
+/// Webhook事件对付款的影响
+#[derive(Debug, Clone, PartialEq)]
+pub enum PaymentOutcome {
+    Succeeded,
+    // 结账已完成但款项尚未到账（例如银行转账等异步付款方式）
+    Processing,
+    Failed(String),
+    // 我们暂不处理的事件类型
+    Ignored,
+}
+
+/// 与具体支付渠道无关的Webhook事件
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaymentEvent {
+    pub id: String,
+    pub event_type: String,
+    pub session_id: Option<String>,
+    pub outcome: PaymentOutcome,
+}
+
+// 结账会话事件的处理结果。两个渠道使用相同的事件类型名称
+fn checkout_outcome(event_type: &str, paid: bool) -> PaymentOutcome {
+    match event_type {
+        "checkout.session.completed" if paid => PaymentOutcome::Succeeded,
+        "checkout.session.completed" => PaymentOutcome::Processing,
+        "checkout.session.async_payment_succeeded" => PaymentOutcome::Succeeded,
+        "checkout.session.async_payment_failed" => PaymentOutcome::Failed("The payment failed.".to_string()),
+        "checkout.session.expired" => PaymentOutcome::Failed("The checkout session expired before payment.".to_string()),
+        _ => PaymentOutcome::Ignored,
+    }
+}
+
+/// 支付渠道。通过配置 PAYMENT_PROVIDER 选择 Stripe 或本地模拟渠道
+#[async_trait]
+pub trait PaymentProvider: Send + Sync {
+    /// 渠道名称，记录在付款事件日志中
+    fn name(&self) -> &'static str;
+
+    /// Webhook 签名所在的请求头
+    fn signature_header(&self) -> &'static str;
+
+    /// 创建一次性付款的结账会话，currency 为大写的ISO 4217代码
+    async fn create_checkout_session(&self, name: &str, amount: Decimal, currency: &str) -> Result<PaymentSession, AppError>;
+
+    /// 让尚未付款的结账会话失效，之后无法再用它付款。
+    /// 会话已经完成付款时返回错误，已经失效时视为成功
+    async fn expire_checkout_session(&self, session_id: &str) -> Result<(), AppError>;
+
+    /// 校验Webhook签名并解析事件
+    fn verify_webhook(&self, payload: &str, signature: &str) -> Result<PaymentEvent, AppError>;
+
+    /// 对某个结账会话的付款退款，返回渠道的退款ID。
+    /// 使用相同 idempotency_key 重试时不会重复退款，返回同一个退款ID
+    async fn refund(&self, session_id: &str, amount: Decimal, idempotency_key: &str) -> Result<String, AppError>;
+}
+
+/// 根据配置创建支付渠道。配置有误时不阻止服务启动，只记录错误，
+/// 并返回一个所有付款操作都失败的渠道
+pub fn from_config(config: &Config) -> Arc<dyn PaymentProvider> {
+    let provider: Result<Arc<dyn PaymentProvider>, String> = match config.payment_provider.as_str() {
+        "stripe" => StripeProvider::from_env().map(|p| Arc::new(p) as Arc<dyn PaymentProvider>),
+        "mock" => MockProvider::from_env().map(|p| {
+            log::warn!("Using the mock payment provider. No real payments will be collected.");
+            Arc::new(p) as Arc<dyn PaymentProvider>
+        }),
+        other => Err(format!("Unknown PAYMENT_PROVIDER '{}', expected 'stripe' or 'mock'", other)),
+    };
+    provider.unwrap_or_else(|reason| {
+        log::error!("Payments are disabled: {}", reason);
+        Arc::new(UnavailableProvider { reason })
+    })
+}
+
+// 读取必需的环境变量，缺失时返回配置错误
+fn required_env(key: &str) -> Result<String, String> {
+    env::var(key).ok().filter(|v| !v.trim().is_empty()).ok_or_else(|| format!("{} must be set", key))
+}
+
+// 金额转换为最小货币单位（分）
+fn to_minor_units(amount: Decimal) -> Result<i64, AppError> {
+    (amount * Decimal::from(100))
+        .round()
+        .to_i64()
+        .ok_or_else(|| AppError::BadRequest("Invalid payment amount".to_string()))
+}
+
+fn previous_checkout_completed() -> AppError {
+    AppError::BadRequest("An earlier checkout for this payment has already been completed. Please wait for it to be confirmed.".to_string())
+}
+
+fn frontend_url() -> String {
+    env::var("FRONTEND_URL").unwrap_or_else(|_| "http://localhost:5173".to_string())
+}
+
+pub struct StripeProvider {
+    client: Client,
+    webhook_secret: String,
+    frontend_url: String,
+}
+
+impl StripeProvider {
+    pub fn from_env() -> Result<Self, String> {
+        let secret_key = required_env("STRIPE_SECRET_KEY")?;
+        let webhook_secret = required_env("STRIPE_WEBHOOK_SECRET")?;
+        Ok(Self { client: Client::new(secret_key), webhook_secret, frontend_url: frontend_url() })
+    }
+}
+
+#[async_trait]
+impl PaymentProvider for StripeProvider {
+    fn name(&self) -> &'static str {
+        "stripe"
+    }
+
+    fn signature_header(&self) -> &'static str {
+        "Stripe-Signature"
+    }
+
+    async fn create_checkout_session(&self, name: &str, amount: Decimal, currency: &str) -> Result<PaymentSession, AppError> {
+        let currency = stripe::Currency::from_str(&currency.to_lowercase())
+            .map_err(|_| AppError::BadRequest(format!("Unsupported currency {}", currency)))?;
+        let success_url = format!("{}/payment/success?session_id={{CHECKOUT_SESSION_ID}}", self.frontend_url);
+        let cancel_url = format!("{}/orders", self.frontend_url);
+
+        let mut params = CreateCheckoutSession::new();
+        params.success_url = Some(&*success_url);
+        params.cancel_url = Some(&*cancel_url);
+        params.mode = Some(CheckoutSessionMode::Payment);
+        params.line_items = Some(vec![CreateCheckoutSessionLineItems {
+            price_data: Some(CreateCheckoutSessionLineItemsPriceData {
+                currency,
+                product_data: Some(CreateCheckoutSessionLineItemsPriceDataProductData {
+                    name: name.to_string(),
+                    ..Default::default()
+                }),
+                unit_amount: Some(to_minor_units(amount)?),
+                ..Default::default()
+            }),
+            quantity: Some(1),
+            ..Default::default()
+        }]);
+
+        let session = CheckoutSession::create(&self.client, params).await
+            .map_err(|e| AppError::InternalServerError(format!("Stripe error: {}", e)))?;
+
+        Ok(PaymentSession { id: session.id.to_string(), url: session.url })
+    }
+
+    async fn expire_checkout_session(&self, session_id: &str) -> Result<(), AppError> {
+        let session_id = CheckoutSessionId::from_str(session_id)
+            .map_err(|_| AppError::BadRequest("Invalid checkout session id".to_string()))?;
+        let session = CheckoutSession::retrieve(&self.client, &session_id, &[]).await
+            .map_err(|e| AppError::InternalServerError(format!("Stripe error: {}", e)))?;
+        match session.status {
+            Some(CheckoutSessionStatus::Expired) => Ok(()),
+            Some(CheckoutSessionStatus::Complete) => Err(previous_checkout_completed()),
+            _ => {
+                CheckoutSession::expire(&self.client, &session_id).await
+                    .map_err(|e| AppError::InternalServerError(format!("Stripe error: {}", e)))?;
+                Ok(())
+            }
+        }
+    }
+
+    fn verify_webhook(&self, payload: &str, signature: &str) -> Result<PaymentEvent, AppError> {
+        let event = Webhook::construct_event(payload, signature, &self.webhook_secret)
+            .map_err(|e| AppError::BadRequest(format!("Invalid Stripe signature: {}", e)))?;
+
+        let id = event.id.to_string();
+        let event_type = event.type_.to_string();
+        match event.data.object {
+            EventObject::CheckoutSession(session) => {
+                let paid = session.payment_status != CheckoutSessionPaymentStatus::Unpaid;
+                let outcome = checkout_outcome(&event_type, paid);
+                Ok(PaymentEvent { id, event_type, session_id: Some(session.id.to_string()), outcome })
+            }
+            _ => Ok(PaymentEvent { id, event_type, session_id: None, outcome: PaymentOutcome::Ignored }),
+        }
+    }
+
+    async fn refund(&self, session_id: &str, amount: Decimal, idempotency_key: &str) -> Result<String, AppError> {
+        // Stripe 按 PaymentIntent 退款，先从结账会话找到对应的 PaymentIntent
+        let session_id = CheckoutSessionId::from_str(session_id)
+            .map_err(|_| AppError::BadRequest("Invalid checkout session id".to_string()))?;
+        let session = CheckoutSession::retrieve(&self.client, &session_id, &[]).await
+            .map_err(|e| AppError::InternalServerError(format!("Stripe error: {}", e)))?;
+        let payment_intent = session.payment_intent
+            .ok_or_else(|| AppError::BadRequest("This checkout session has no payment to refund.".to_string()))?;
+
+        let mut params = CreateRefund::new();
+        params.payment_intent = Some(payment_intent.id());
+        params.amount = Some(to_minor_units(amount)?);
+        let client = self.client.clone().with_strategy(RequestStrategy::Idempotent(idempotency_key.to_string()));
+        let refund = Refund::create(&client, params).await
+            .map_err(|e| AppError::InternalServerError(format!("Stripe error: {}", e)))?;
+
+        Ok(refund.id.to_string())
+    }
+}
+
+/// 本地模拟支付渠道，用于离线开发和测试，release 构建中不可用。
+/// Webhook 以JSON提交，签名为用共享密钥对请求体计算的 HMAC-SHA256（十六进制）
+pub struct MockProvider {
+    webhook_secret: String,
+    frontend_url: String,
+    // session_id -> 金额
+    sessions: Mutex<HashMap<String, Decimal>>,
+    // (session_id, 金额)
+    refunds: Mutex<Vec<(String, Decimal)>>,
+    // idempotency_key -> 退款ID
+    refund_ids: Mutex<HashMap<String, String>>,
+    // 已提交付款完成Webhook的会话，以及已失效的会话
+    paid: Mutex<HashSet<String>>,
+    expired: Mutex<HashSet<String>>,
+}
+
+impl MockProvider {
+    pub fn new(webhook_secret: &str) -> Self {
+        Self {
+            webhook_secret: webhook_secret.to_string(),
+            frontend_url: frontend_url(),
+            sessions: Mutex::new(HashMap::new()),
+            refunds: Mutex::new(Vec::new()),
+            refund_ids: Mutex::new(HashMap::new()),
+            paid: Mutex::new(HashSet::new()),
+            expired: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn from_env() -> Result<Self, String> {
+        // 模拟渠道的Webhook可以把任意会话标记为已付款，不能出现在生产环境
+        if !cfg!(debug_assertions) {
+            return Err("the mock payment provider is only available in development builds".to_string());
+        }
+        let secret = required_env("MOCK_PAYMENT_WEBHOOK_SECRET")?;
+        Ok(Self::new(&secret))
+    }
+
+    fn mac(&self) -> Hmac<Sha256> {
+        Hmac::<Sha256>::new_from_slice(self.webhook_secret.as_bytes()).expect("HMAC accepts keys of any length")
+    }
+
+    /// 构造一个结账会话的Webhook，返回 (payload, signature)，可以直接提交到 webhook 接口。
+    /// event_type 与 Stripe 相同，例如 checkout.session.completed、checkout.session.expired
+    #[cfg(test)]
+    pub fn webhook(&self, event_type: &str, session_id: &str) -> (String, String) {
+        let payload = serde_json::json!({
+            "id": format!("evt_mock_{}", uuid::Uuid::new_v4().simple()),
+            "type": event_type,
+            "session_id": session_id,
+            "payment_status": "paid",
+        });
+        let payload = payload.to_string();
+        let mut mac = self.mac();
+        mac.update(payload.as_bytes());
+        let signature = hex::encode(mac.finalize().into_bytes());
+        (payload, signature)
+    }
+
+    /// 已创建的会话金额
+    pub fn session_amount(&self, session_id: &str) -> Option<Decimal> {
+        self.sessions.lock().unwrap().get(session_id).copied()
+    }
+
+    /// 已执行的退款记录
+    #[cfg(test)]
+    pub fn refunds(&self) -> Vec<(String, Decimal)> {
+        self.refunds.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl PaymentProvider for MockProvider {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn signature_header(&self) -> &'static str {
+        "X-Mock-Signature"
+    }
+
+    async fn create_checkout_session(&self, _name: &str, amount: Decimal, _currency: &str) -> Result<PaymentSession, AppError> {
+        to_minor_units(amount)?;
+        let id = format!("mock_cs_{}", uuid::Uuid::new_v4().simple());
+        self.sessions.lock().unwrap().insert(id.clone(), amount);
+        let url = format!("{}/payment/success?session_id={}", self.frontend_url, id);
+        Ok(PaymentSession { id, url: Some(url) })
+    }
+
+    async fn expire_checkout_session(&self, session_id: &str) -> Result<(), AppError> {
+        if self.paid.lock().unwrap().contains(session_id) {
+            return Err(previous_checkout_completed());
+        }
+        self.expired.lock().unwrap().insert(session_id.to_string());
+        Ok(())
+    }
+
+    fn verify_webhook(&self, payload: &str, signature: &str) -> Result<PaymentEvent, AppError> {
+        let signature = hex::decode(signature.trim())
+            .map_err(|_| AppError::BadRequest("Invalid mock webhook signature".to_string()))?;
+        let mut mac = self.mac();
+        mac.update(payload.as_bytes());
+        mac.verify_slice(&signature)
+            .map_err(|_| AppError::BadRequest("Invalid mock webhook signature".to_string()))?;
+
+        let event: serde_json::Value = serde_json::from_str(payload)
+            .map_err(|e| AppError::BadRequest(format!("Invalid webhook payload: {}", e)))?;
+
+        let field = |name: &str| event.get(name).and_then(|v| v.as_str()).map(str::to_string);
+        let id = field("id").ok_or_else(|| AppError::BadRequest("Webhook event id is missing".to_string()))?;
+        let event_type = field("type").unwrap_or_default();
+        let session_id = field("session_id");
+        log::warn!("Mock payment webhook {} ({}) for session {:?}. No real payment was collected.", id, event_type, session_id);
+
+        let outcome = match &session_id {
+            Some(session_id) => {
+                // 只接受本渠道创建的会话；已失效的会话不能再完成付款
+                if self.session_amount(session_id).is_none() {
+                    return Err(AppError::BadRequest("Unknown mock checkout session".to_string()));
+                }
+                let outcome = checkout_outcome(&event_type, field("payment_status").as_deref() != Some("unpaid"));
+                if matches!(outcome, PaymentOutcome::Succeeded | PaymentOutcome::Processing) {
+                    if self.expired.lock().unwrap().contains(session_id) {
+                        return Err(AppError::BadRequest("This mock checkout session has expired.".to_string()));
+                    }
+                    self.paid.lock().unwrap().insert(session_id.clone());
+                }
+                outcome
+            }
+            None => PaymentOutcome::Ignored,
+        };
+        Ok(PaymentEvent { id, event_type, session_id, outcome })
+    }
+
+    async fn refund(&self, session_id: &str, amount: Decimal, idempotency_key: &str) -> Result<String, AppError> {
+        if amount <= Decimal::ZERO {
+            return Err(AppError::BadRequest("Refund amount must be positive.".to_string()));
+        }
+        let mut refund_ids = self.refund_ids.lock().unwrap();
+        if let Some(refund_id) = refund_ids.get(idempotency_key) {
+            return Ok(refund_id.clone());
+        }
+        // 服务重启后内存中的会话会丢失，所以只对已知的会话检查退款上限
+        let mut refunds = self.refunds.lock().unwrap();
+        if let Some(paid) = self.session_amount(session_id) {
+            let refunded: Decimal = refunds.iter().filter(|(id, _)| id == session_id).map(|(_, a)| *a).sum();
+            if refunded + amount > paid {
+                return Err(AppError::BadRequest("Refund exceeds the amount paid.".to_string()));
+            }
+        }
+        refunds.push((session_id.to_string(), amount));
+        let refund_id = format!("mock_re_{}", uuid::Uuid::new_v4().simple());
+        refund_ids.insert(idempotency_key.to_string(), refund_id.clone());
+        Ok(refund_id)
+    }
+}
+
+/// 支付渠道配置有误时使用：服务照常运行，所有付款操作返回错误
+struct UnavailableProvider {
+    reason: String,
+}
+
+impl UnavailableProvider {
+    fn error(&self) -> AppError {
+        AppError::InternalServerError(format!("Payments are not configured: {}", self.reason))
+    }
+}
+
+#[async_trait]
+impl PaymentProvider for UnavailableProvider {
+    fn name(&self) -> &'static str {
+        "unavailable"
+    }
+
+    fn signature_header(&self) -> &'static str {
+        "Stripe-Signature"
+    }
+
+    async fn create_checkout_session(&self, _name: &str, _amount: Decimal, _currency: &str) -> Result<PaymentSession, AppError> {
+        Err(self.error())
+    }
+
+    async fn expire_checkout_session(&self, _session_id: &str) -> Result<(), AppError> {
+        Err(self.error())
+    }
+
+    fn verify_webhook(&self, _payload: &str, _signature: &str) -> Result<PaymentEvent, AppError> {
+        Err(self.error())
+    }
+
+    async fn refund(&self, _session_id: &str, _amount: Decimal, _idempotency_key: &str) -> Result<String, AppError> {
+        Err(self.error())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_mock_provider_checkout_and_refund() {
+        let provider = MockProvider::new("secret");
+        let session = provider.create_checkout_session("Order #1", Decimal::new(12050, 2), "CNY").await.unwrap();
+        assert_eq!(provider.session_amount(&session.id), Some(Decimal::new(12050, 2)));
+
+        // 测试可以像支付渠道一样提交付款完成的Webhook
+        let (payload, signature) = provider.webhook("checkout.session.completed", &session.id);
+        let event = provider.verify_webhook(&payload, &signature).unwrap();
+        assert_eq!(event.session_id.as_deref(), Some(session.id.as_str()));
+        assert_eq!(event.outcome, PaymentOutcome::Succeeded);
+        // 签名必须是用共享密钥对请求体计算的HMAC，密钥本身或篡改过的请求体都无效
+        assert!(provider.verify_webhook(&payload, "wrong").is_err());
+        assert!(provider.verify_webhook(&payload, "secret").is_err());
+        assert!(provider.verify_webhook(&payload.replace("paid", "unpaid"), &signature).is_err());
+        // 不是本渠道创建的会话不能被标记为已付款
+        let (payload, signature) = provider.webhook("checkout.session.completed", "cs_unknown");
+        assert!(provider.verify_webhook(&payload, &signature).is_err());
+
+        let (payload, signature) = provider.webhook("checkout.session.expired", &session.id);
+        assert!(matches!(provider.verify_webhook(&payload, &signature).unwrap().outcome, PaymentOutcome::Failed(_)));
+
+        // 已付款的会话不能再失效，未付款的可以；失效后不能再完成付款
+        assert!(provider.expire_checkout_session(&session.id).await.is_err());
+        let unpaid = provider.create_checkout_session("Order #1", Decimal::new(500, 2), "CNY").await.unwrap();
+        provider.expire_checkout_session(&unpaid.id).await.unwrap();
+        let (payload, signature) = provider.webhook("checkout.session.completed", &unpaid.id);
+        assert!(provider.verify_webhook(&payload, &signature).is_err());
+
+        // 退款不能超过已付金额；相同幂等键的重试返回同一笔退款
+        let refund_id = provider.refund(&session.id, Decimal::new(10000, 2), "key-1").await.unwrap();
+        assert_eq!(provider.refund(&session.id, Decimal::new(10000, 2), "key-1").await.unwrap(), refund_id);
+        assert!(provider.refund(&session.id, Decimal::new(2100, 2), "key-2").await.is_err());
+        assert_eq!(provider.refunds().len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_misconfigured_provider_does_not_panic() {
+        let config = Config {
+            database_url: String::new(),
+            order_confirmation_sla_hours: 48,
+            invoice_reminder_interval_days: 7,
+            delivery_at_risk_days: 3,
+            payment_provider: "paypal".to_string(),
+        };
+        let provider = from_config(&config);
+        assert_eq!(provider.name(), "unavailable");
+        assert!(provider.create_checkout_session("Order #1", Decimal::ONE, "CNY").await.is_err());
+    }
+}
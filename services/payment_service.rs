@@ -2,79 +2,778 @@
 
 use crate::{
     errors::AppError,
-    models::{order::PurchaseOrder, user::Claims},
+    models::{
+        order::PurchaseOrder,
+        payment::{CreateRefundDto, OrderPayment, OrderPaymentSummary},
+        user::{Claims, APPROVER_ROLES},
+    },
+    services::{
+        audit_service::AuditEntry,
+        chat_server::ChatServer,
+        invoice_service, notification_service, order_service,
+        payment_provider::{PaymentEvent, PaymentOutcome, PaymentProvider, PaymentSession},
+    },
+    utils::auth_utils,
 };
-use sqlx::MySqlPool;
-use std::env;
-// 导入 async_stripe 的相关模块
-use stripe::{
-    Client,
-    CheckoutSession, CheckoutSessionMode, CreateCheckoutSession, CreateCheckoutSessionLineItems, CreateCheckoutSessionLineItemsPriceData,CreateCheckoutSessionLineItemsPriceDataProductData
-};
-// 导入 ToPrimitive trait 以使用 .to_f64()
-use num_traits::ToPrimitive;
+use actix::Addr;
+use sqlx::{types::Decimal, MySqlConnection, MySqlPool};
+use std::str::FromStr;
+
+/// 根据应付金额、已付和已退金额以及最近一次付款尝试的状态得出订单的付款状态
+pub(crate) fn derive_payment_status(
+    amount_due: Decimal,
+    paid: Decimal,
+    refunded: Decimal,
+    latest_attempt: Option<&str>,
+) -> &'static str {
+    let net_paid = paid - refunded;
+    if paid > Decimal::ZERO && net_paid >= amount_due {
+        "PAID"
+    } else if refunded > Decimal::ZERO {
+        if net_paid <= Decimal::ZERO { "REFUNDED" } else { "PARTIALLY_REFUNDED" }
+    } else if latest_attempt == Some("PROCESSING") {
+        "PROCESSING"
+    } else if net_paid > Decimal::ZERO {
+        "PARTIALLY_PAID"
+    } else if latest_attempt == Some("FAILED") {
+        "FAILED"
+    } else {
+        "UNPAID"
+    }
+}
+
+/// 订单整体付款的下一期：(kind, 金额)。
+/// 有预付款要求时先付预付款，再付尾款；没有时一次付清
+pub(crate) fn next_installment(total: Decimal, deposit_percent: Decimal, paid: Decimal) -> Option<(&'static str, Decimal)> {
+    let remaining = total - paid;
+    if remaining <= Decimal::ZERO {
+        return None;
+    }
+    if paid.is_zero() {
+        if deposit_percent > Decimal::ZERO {
+            let deposit = (total * deposit_percent / Decimal::from(100)).round_dp(2);
+            return Some(("DEPOSIT", deposit.min(remaining)));
+        }
+        return Some(("FULL", remaining));
+    }
+    Some(("BALANCE", remaining))
+}
+
+// 每张发票的未付金额：(invoice_id, outstanding, status)。
+// 按发票付的款只计入该发票，订单整体付款（例如预付款）按开票顺序冲抵发票
+async fn invoice_balances(conn: &mut MySqlConnection, order_id: i32) -> Result<Vec<(i32, Decimal, String)>, AppError> {
+    let (mut credit,): (Decimal,) = sqlx::query_as(
+        "SELECT COALESCE(SUM(amount), 0) FROM order_payments WHERE order_id = ? AND invoice_id IS NULL AND status = 'PAID'"
+    )
+        .bind(order_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    let invoices: Vec<(i32, Decimal, String, Decimal)> = sqlx::query_as(
+        "SELECT i.id, i.total_amount, i.status,
+                COALESCE((SELECT SUM(p.amount) FROM order_payments p WHERE p.invoice_id = i.id AND p.status = 'PAID'), 0)
+         FROM invoices i WHERE i.order_id = ? ORDER BY i.id ASC"
+    )
+        .bind(order_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+    Ok(invoices
+        .into_iter()
+        .map(|(invoice_id, total_amount, status, paid)| {
+            let unpaid = (total_amount - paid).max(Decimal::ZERO);
+            let applied = credit.min(unpaid);
+            credit -= applied;
+            (invoice_id, unpaid - applied, status)
+        })
+        .collect())
+}
+
+/// 根据付款和退款记录重新计算订单的已付金额和付款状态，并把已付金额按开票顺序冲抵发票。
+/// 应付金额为订单总额加上已开发票的税额
+pub(crate) async fn refresh_payment_status(conn: &mut MySqlConnection, order_id: i32) -> Result<String, AppError> {
+    let (amount_due, paid, refunded): (Decimal, Decimal, Decimal) = sqlx::query_as(
+        "SELECT po.total_amount + COALESCE((SELECT SUM(i.tax_amount) FROM invoices i WHERE i.order_id = po.id), 0),
+                COALESCE((SELECT SUM(p.amount) FROM order_payments p WHERE p.order_id = po.id AND p.status = 'PAID'), 0),
+                COALESCE((SELECT SUM(r.amount) FROM order_refunds r WHERE r.order_id = po.id AND r.status = 'SUCCEEDED'), 0)
+         FROM purchase_orders po WHERE po.id = ?"
+    )
+        .bind(order_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    let latest_attempt: Option<(String,)> = sqlx::query_as(
+        "SELECT status FROM order_payments WHERE order_id = ? ORDER BY id DESC LIMIT 1"
+    )
+        .bind(order_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+    let status = derive_payment_status(amount_due, paid, refunded, latest_attempt.as_ref().map(|(s,)| s.as_str()));
+    sqlx::query("UPDATE purchase_orders SET amount_paid = ?, amount_refunded = ?, payment_status = ? WHERE id = ?")
+        .bind(paid)
+        .bind(refunded)
+        .bind(status)
+        .bind(order_id)
+        .execute(&mut *conn)
+        .await?;
+
+    // 退款不会让已付的发票重新变为未付
+    for (invoice_id, outstanding, invoice_status) in invoice_balances(conn, order_id).await? {
+        if outstanding.is_zero() && invoice_status != "PAID" {
+            sqlx::query("UPDATE invoices SET status = 'PAID', paid_at = NOW() WHERE id = ?")
+                .bind(invoice_id)
+                .execute(&mut *conn)
+                .await?;
+        }
+    }
+
+    Ok(status.to_string())
+}
+
+// 创建中的结账超过该时间仍未写回会话，视为创建请求已中断
+const CHECKOUT_CREATION_TIMEOUT_MINUTES: i64 = 5;
+
+// 付款的第一步，在调用方持有订单行锁的事务中执行：记录一条尚未关联结账会话的 PENDING 付款，
+// 它同时阻止同一期款项的并发结账。返回 (付款ID, 需要先在支付渠道失效的旧会话)
+async fn reserve_payment(
+    conn: &mut MySqlConnection,
+    provider: &dyn PaymentProvider,
+    order_id: i32,
+    invoice_id: Option<i32>,
+    (kind, amount, currency): (&str, Decimal, &str),
+    claims: &Claims,
+) -> Result<(u64, Vec<(i32, String)>), AppError> {
+    sqlx::query(
+        "UPDATE order_payments SET status = 'FAILED'
+         WHERE order_id = ? AND status = 'PENDING' AND session_id IS NULL AND created_at < NOW() - INTERVAL ? MINUTE"
+    )
+        .bind(order_id)
+        .bind(CHECKOUT_CREATION_TIMEOUT_MINUTES)
+        .execute(&mut *conn)
+        .await?;
+
+    let open: Vec<(i32, String, Option<String>)> = sqlx::query_as(
+        "SELECT id, provider, session_id FROM order_payments WHERE order_id = ? AND invoice_id <=> ? AND status = 'PENDING'"
+    )
+        .bind(order_id)
+        .bind(invoice_id)
+        .fetch_all(&mut *conn)
+        .await?;
 
+    let mut stale_sessions = Vec::new();
+    for (payment_id, payment_provider, session_id) in open {
+        let Some(session_id) = session_id else {
+            return Err(AppError::BadRequest("A checkout for this payment is already being created. Please try again shortly.".to_string()));
+        };
+        if payment_provider != provider.name() {
+            return Err(AppError::BadRequest(format!(
+                "An earlier checkout through {} is still open for this payment.",
+                payment_provider
+            )));
+        }
+        stale_sessions.push((payment_id, session_id));
+    }
+
+    let result = sqlx::query(
+        "INSERT INTO order_payments (order_id, invoice_id, kind, amount, currency, provider, created_by_user_id) VALUES (?, ?, ?, ?, ?, ?, ?)"
+    )
+        .bind(order_id)
+        .bind(invoice_id)
+        .bind(kind)
+        .bind(amount)
+        .bind(currency)
+        .bind(provider.name())
+        .bind(claims.sub)
+        .execute(&mut *conn)
+        .await?;
+    Ok((result.last_insert_id(), stale_sessions))
+}
+
+// 付款的第二步，在事务提交之后执行，调用支付渠道时不持有订单的行锁：
+// 先让同一期款项（同一张发票，或订单整体付款）之前未付款的会话失效，避免采购方打开多个结账页面重复付款，
+// 再创建新会话并写回付款记录。
+// 任何一步失败都把这笔付款标记为 FAILED，已经创建的会话会被失效
+async fn open_checkout(
+    pool: &MySqlPool,
+    provider: &dyn PaymentProvider,
+    payment_id: u64,
+    stale_sessions: Vec<(i32, String)>,
+    name: &str,
+    amount: Decimal,
+    currency: &str,
+) -> Result<PaymentSession, AppError> {
+    let result = async {
+        for (stale_payment_id, session_id) in stale_sessions {
+            provider.expire_checkout_session(&session_id).await?;
+            sqlx::query("UPDATE order_payments SET status = 'EXPIRED' WHERE id = ? AND status = 'PENDING'")
+                .bind(stale_payment_id)
+                .execute(pool)
+                .await?;
+        }
+
+        let session = provider.create_checkout_session(name, amount, currency).await?;
+        let attached = sqlx::query("UPDATE order_payments SET session_id = ? WHERE id = ? AND status = 'PENDING'")
+            .bind(&session.id)
+            .bind(payment_id)
+            .execute(pool)
+            .await;
+        match attached {
+            Ok(done) if done.rows_affected() == 1 => Ok(session),
+            other => {
+                if let Err(e) = provider.expire_checkout_session(&session.id).await {
+                    log::error!("Failed to expire unrecorded checkout session {}: {:?}", session.id, e);
+                }
+                match other {
+                    Err(e) => Err(e.into()),
+                    Ok(_) => Err(AppError::BadRequest("This checkout is no longer valid. Please try again.".to_string())),
+                }
+            }
+        }
+    }.await;
+
+    if result.is_err() {
+        let marked = sqlx::query("UPDATE order_payments SET status = 'FAILED' WHERE id = ? AND status = 'PENDING'")
+            .bind(payment_id)
+            .execute(pool)
+            .await;
+        if let Err(e) = marked {
+            log::error!("Failed to mark payment #{} as failed: {:?}", payment_id, e);
+        }
+    }
+    result
+}
+
+// 订单是否有仍在处理中的付款
+async fn has_processing_payment(conn: &mut MySqlConnection, order_id: i32) -> Result<bool, AppError> {
+    let processing: Option<(i32,)> = sqlx::query_as(
+        "SELECT id FROM order_payments WHERE order_id = ? AND status = 'PROCESSING' LIMIT 1"
+    )
+        .bind(order_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+    Ok(processing.is_some())
+}
+
+/// 采购方为订单的下一期款项（预付款、尾款或全款）创建结账会话
 pub async fn create_checkout_session(
     pool: &MySqlPool,
+    provider: &dyn PaymentProvider,
     order_id: i32,
     claims: &Claims,
-) -> Result<String, AppError> {
-    // 1. 验证订单
+) -> Result<PaymentSession, AppError> {
+    auth_utils::require_role(claims, APPROVER_ROLES)?;
+    let mut tx = pool.begin().await?;
+    // 1. 验证订单并加锁，同一订单的并发结账请求依次执行
     let order: PurchaseOrder = sqlx::query_as(
         "SELECT po.*, r.title as rfq_title, b.name as buyer_name, s.name as supplier_name
          FROM purchase_orders po
          JOIN rfqs r ON po.rfq_id = r.id
          JOIN companies b ON po.buyer_company_id = b.id
          JOIN companies s ON po.supplier_company_id = s.id
-         WHERE po.id = ? AND po.buyer_company_id = ?"
+         WHERE po.id = ? AND po.buyer_company_id = ?
+         FOR UPDATE OF po"
     )
         .bind(order_id)
         .bind(claims.company_id)
-        .fetch_one(pool)
+        .fetch_one(&mut *tx)
         .await
         .map_err(|_| AppError::BadRequest("Order not found or you are not authorized.".to_string()))?;
 
-    if order.payment_status == "PAID" {
-        return Err(AppError::BadRequest("This order has already been paid.".to_string()));
-    }
-
-    // 2. 配置Stripe客户端
-    let secret_key = env::var("STRIPE_SECRET_KEY").expect("STRIPE_SECRET_KEY must be set");
-    let client = Client::new(secret_key);
-
-    // 3. 定义URL
-    let frontend_url = env::var("FRONTEND_URL").expect("FRONTEND_URL must be set");
-    let success_url = format!("{}/payment/success?session_id={{CHECKOUT_SESSION_ID}}", frontend_url);
-    let cancel_url = format!("{}/orders", frontend_url);
-
-    // 4. 【关键修复】使用 async-stripe 的API创建Checkout会话
-    let mut params = CreateCheckoutSession::new();
-    params.success_url = Some(&*success_url);
-    params.cancel_url = Some(&*cancel_url);
-    params.mode = Some(CheckoutSessionMode::Payment);
-    params.line_items = Some(vec![CreateCheckoutSessionLineItems {
-        price_data: Some(CreateCheckoutSessionLineItemsPriceData {
-            currency: stripe::Currency::USD, // Or your currency
-            product_data: Some(CreateCheckoutSessionLineItemsPriceDataProductData {
-                name: order.rfq_title.clone(),
-                ..Default::default()
-            }),
-            unit_amount: Some((order.total_amount.to_f64().unwrap_or(0.0) * 100.0) as i64),
-            ..Default::default()
-        }),
-        quantity: Some(1),
-        ..Default::default()
-    }]);
-
-    let session = CheckoutSession::create(&client, params).await
-        .map_err(|e| AppError::InternalServerError(format!("Stripe error: {}", e)))?;
-
-    // 5. 将会话ID存入数据库
-    sqlx::query("UPDATE purchase_orders SET stripe_session_id = ? WHERE id = ?")
-        .bind(session.id.to_string()) // 使用 .to_string() 转换ID
+    if has_processing_payment(&mut tx, order_id).await? {
+        return Err(AppError::BadRequest("A payment for this order is still being processed.".to_string()));
+    }
+
+    // 已经开具发票的订单按发票付款
+    let invoiced: Option<(i32,)> = sqlx::query_as("SELECT id FROM invoices WHERE order_id = ? LIMIT 1")
+        .bind(order_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    if invoiced.is_some() {
+        return Err(AppError::BadRequest("This order has been invoiced. Please pay its invoices instead.".to_string()));
+    }
+
+    let (kind, amount) = next_installment(order.total_amount, order.deposit_percent, order.amount_paid)
+        .ok_or_else(|| AppError::BadRequest("This order has already been paid.".to_string()))?;
+    let name = match kind {
+        "DEPOSIT" => format!("{} - deposit ({}%)", order.rfq_title, order.deposit_percent.normalize()),
+        "BALANCE" => format!("{} - balance", order.rfq_title),
+        _ => order.rfq_title.clone(),
+    };
+
+    // 2. 先记录付款并释放订单锁，再到支付渠道创建会话
+    let (payment_id, stale_sessions) =
+        reserve_payment(&mut tx, provider, order_id, None, (kind, amount, &order.currency), claims).await?;
+    tx.commit().await?;
+    open_checkout(pool, provider, payment_id, stale_sessions, &name, amount, &order.currency).await
+}
+
+/// 采购方为单张发票创建结账会话。订单整体付款（例如预付款）冲抵后只需支付剩余部分
+pub async fn create_invoice_checkout_session(
+    pool: &MySqlPool,
+    provider: &dyn PaymentProvider,
+    invoice_id: i32,
+    claims: &Claims,
+) -> Result<PaymentSession, AppError> {
+    auth_utils::require_role(claims, APPROVER_ROLES)?;
+    let invoice = invoice_service::get_invoice(pool, invoice_id, claims).await?;
+
+    if invoice.buyer_company_id != claims.company_id {
+        return Err(AppError::BadRequest("Only the buyer can pay this invoice.".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("SELECT id FROM purchase_orders WHERE id = ? FOR UPDATE")
+        .bind(invoice.order_id)
+        .execute(&mut *tx)
+        .await?;
+    let (invoice_status,): (String,) = sqlx::query_as("SELECT status FROM invoices WHERE id = ?")
+        .bind(invoice_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    if invoice_status == "PAID" {
+        return Err(AppError::BadRequest("This invoice has already been paid.".to_string()));
+    }
+    if has_processing_payment(&mut tx, invoice.order_id).await? {
+        return Err(AppError::BadRequest("A payment for this order is still being processed.".to_string()));
+    }
+
+    let amount = invoice_balances(&mut tx, invoice.order_id)
+        .await?
+        .into_iter()
+        .find(|(id, _, _)| *id == invoice_id)
+        .map(|(_, outstanding, _)| outstanding)
+        .unwrap_or(invoice.total_amount);
+    if amount <= Decimal::ZERO {
+        return Err(AppError::BadRequest("This invoice is already covered by earlier payments.".to_string()));
+    }
+
+    let name = format!("Invoice {}", invoice.invoice_number);
+    let (payment_id, stale_sessions) =
+        reserve_payment(&mut tx, provider, invoice.order_id, Some(invoice_id), ("INVOICE", amount, &invoice.currency), claims).await?;
+    tx.commit().await?;
+    open_checkout(pool, provider, payment_id, stale_sessions, &name, amount, &invoice.currency).await
+}
+
+/// 获取订单的付款计划、付款和退款记录，只有订单双方可以查看
+pub async fn get_order_payments(pool: &MySqlPool, order_id: i32, claims: &Claims) -> Result<OrderPaymentSummary, AppError> {
+    order_service::ensure_order_party(pool, order_id, claims).await?;
+
+    let (payment_status, currency, deposit_percent): (String, String, Decimal) = sqlx::query_as(
+        "SELECT payment_status, currency, deposit_percent FROM purchase_orders WHERE id = ?"
+    )
         .bind(order_id)
+        .fetch_one(pool)
+        .await?;
+    let (amount_due, amount_paid, amount_refunded): (Decimal, Decimal, Decimal) = sqlx::query_as(
+        "SELECT po.total_amount + COALESCE((SELECT SUM(i.tax_amount) FROM invoices i WHERE i.order_id = po.id), 0),
+                po.amount_paid, po.amount_refunded
+         FROM purchase_orders po WHERE po.id = ?"
+    )
+        .bind(order_id)
+        .fetch_one(pool)
+        .await?;
+
+    let payments = sqlx::query_as("SELECT * FROM order_payments WHERE order_id = ? ORDER BY id ASC")
+        .bind(order_id)
+        .fetch_all(pool)
+        .await?;
+    let refunds = sqlx::query_as("SELECT * FROM order_refunds WHERE order_id = ? ORDER BY id ASC")
+        .bind(order_id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(OrderPaymentSummary { payment_status, currency, deposit_percent, amount_due, amount_paid, amount_refunded, payments, refunds })
+}
+
+/// 供应商通过支付渠道退款，可以部分退款，也可以关联售后退货（RMA）
+pub async fn refund_payment(
+    pool: &MySqlPool,
+    chat_server: &Addr<ChatServer>,
+    provider: &dyn PaymentProvider,
+    order_id: i32,
+    dto: CreateRefundDto,
+    claims: &Claims,
+) -> Result<u64, AppError> {
+    auth_utils::require_role(claims, APPROVER_ROLES)?;
+    if dto.reason.trim().is_empty() {
+        return Err(AppError::BadRequest("A reason is required for a refund.".to_string()));
+    }
+    let amount = Decimal::from_str(&dto.amount.to_string())
+        .map_err(|_| AppError::BadRequest("Invalid amount format".to_string()))?
+        .round_dp(2);
+    if amount <= Decimal::ZERO {
+        return Err(AppError::BadRequest("Refund amount must be positive.".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+    let (_, buyer_company_id, supplier_company_id) = order_service::lock_order(&mut tx, order_id, claims).await?;
+    if claims.company_id != supplier_company_id {
+        return Err(AppError::BadRequest("Only the supplier can issue refunds.".to_string()));
+    }
+
+    // RMA 约定了退款金额时，该RMA的退款总额不能超过约定金额
+    if let Some(rma_id) = dto.rma_id {
+        let (rma_status, credit_amount): (String, Option<Decimal>) = sqlx::query_as(
+            "SELECT status, credit_amount FROM rmas WHERE id = ? AND order_id = ?"
+        )
+            .bind(rma_id)
+            .bind(order_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| AppError::BadRequest("RMA not found".to_string()))?;
+        if rma_status != "APPROVED" && rma_status != "CLOSED" {
+            return Err(AppError::BadRequest("Only approved RMAs can be refunded.".to_string()));
+        }
+        if let Some(credit_amount) = credit_amount {
+            let (refunded,): (Decimal,) = sqlx::query_as("SELECT COALESCE(SUM(amount), 0) FROM order_refunds WHERE rma_id = ? AND status <> 'FAILED'")
+                .bind(rma_id)
+                .fetch_one(&mut *tx)
+                .await?;
+            if refunded + amount > credit_amount {
+                return Err(AppError::BadRequest(format!(
+                    "Refunds for this RMA cannot exceed the agreed credit of {}.",
+                    credit_amount
+                )));
+            }
+        }
+    }
+
+    // 每次退款只针对一笔付款；未指定时选最近一笔剩余可退金额足够的付款
+    let payments: Vec<OrderPayment> = sqlx::query_as(
+        "SELECT * FROM order_payments WHERE order_id = ? AND status = 'PAID' ORDER BY id DESC FOR UPDATE"
+    )
+        .bind(order_id)
+        .fetch_all(&mut *tx)
+        .await?;
+    let payment = match dto.payment_id {
+        Some(payment_id) => payments.into_iter().find(|p| p.id == payment_id)
+            .ok_or_else(|| AppError::BadRequest("Payment not found or not paid.".to_string()))?,
+        None => payments.into_iter().find(|p| p.amount - p.refunded_amount >= amount)
+            .ok_or_else(|| AppError::BadRequest("No single payment covers this refund amount.".to_string()))?,
+    };
+    if payment.amount - payment.refunded_amount < amount {
+        return Err(AppError::BadRequest(format!(
+            "At most {} can still be refunded from this payment.",
+            payment.amount - payment.refunded_amount
+        )));
+    }
+    if payment.provider != provider.name() {
+        return Err(AppError::BadRequest(format!(
+            "This payment was made through {}, which is not the active payment provider.",
+            payment.provider
+        )));
+    }
+
+    // 先记录退款并预留可退金额后提交，再调用支付渠道。
+    // 即使之后的步骤失败，也有记录可以用同一个幂等键重试，不会重复退款
+    let idempotency_key = format!("refund_{}", uuid::Uuid::new_v4().simple());
+    let result = sqlx::query(
+        "INSERT INTO order_refunds (order_id, payment_id, rma_id, amount, reason, idempotency_key, created_by_user_id) VALUES (?, ?, ?, ?, ?, ?, ?)"
+    )
+        .bind(order_id)
+        .bind(payment.id)
+        .bind(dto.rma_id)
+        .bind(amount)
+        .bind(&dto.reason)
+        .bind(&idempotency_key)
+        .bind(claims.sub)
+        .execute(&mut *tx)
+        .await?;
+    let refund_id = result.last_insert_id() as i32;
+    sqlx::query("UPDATE order_payments SET refunded_amount = refunded_amount + ? WHERE id = ?")
+        .bind(amount)
+        .bind(payment.id)
+        .execute(&mut *tx)
+        .await?;
+    AuditEntry::new(claims, "PAYMENT_REFUND_REQUESTED", "ORDER", order_id)
+        .after(serde_json::json!({
+            "refund_id": refund_id,
+            "payment_id": payment.id,
+            "amount": amount.to_string(),
+            "currency": payment.currency,
+            "reason": dto.reason,
+        }))
+        .record(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    let (session_id,): (String,) = sqlx::query_as("SELECT session_id FROM order_payments WHERE id = ?")
+        .bind(payment.id)
+        .fetch_one(pool)
+        .await?;
+    match provider.refund(&session_id, amount, &idempotency_key).await {
+        Ok(provider_refund_id) => complete_refund(pool, refund_id, &provider_refund_id).await?,
+        Err(e) => {
+            fail_refund(pool, refund_id, &format!("{}", e)).await?;
+            return Err(e);
+        }
+    }
+
+    if let Err(e) = notification_service::notify_company(
+        pool,
+        chat_server,
+        buyer_company_id,
+        format!("A refund of {} {} was issued on order #{}: {}", amount, payment.currency, order_id, dto.reason),
+        format!("/orders/{}", order_id),
+    ).await {
+        log::error!("Failed to send refund notification: {:?}", e);
+    }
+
+    Ok(refund_id as u64)
+}
+
+// 支付渠道退款成功后，标记退款完成并重新计算订单付款状态
+async fn complete_refund(pool: &MySqlPool, refund_id: i32, provider_refund_id: &str) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+    let (order_id,): (i32,) = sqlx::query_as("SELECT order_id FROM order_refunds WHERE id = ? FOR UPDATE")
+        .bind(refund_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    sqlx::query(
+        "UPDATE order_refunds SET status = 'SUCCEEDED', provider_refund_id = ?, completed_at = NOW() WHERE id = ? AND status = 'PENDING'"
+    )
+        .bind(provider_refund_id)
+        .bind(refund_id)
+        .execute(&mut *tx)
+        .await?;
+    refresh_payment_status(&mut tx, order_id).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+// 支付渠道拒绝退款时，标记失败并释放预留的可退金额
+async fn fail_refund(pool: &MySqlPool, refund_id: i32, error: &str) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+    let updated = sqlx::query(
+        "UPDATE order_refunds SET status = 'FAILED', error = ?, completed_at = NOW() WHERE id = ? AND status = 'PENDING'"
+    )
+        .bind(error)
+        .bind(refund_id)
+        .execute(&mut *tx)
+        .await?;
+    if updated.rows_affected() > 0 {
+        sqlx::query(
+            "UPDATE order_payments p JOIN order_refunds r ON r.payment_id = p.id
+             SET p.refunded_amount = p.refunded_amount - r.amount WHERE r.id = ?"
+        )
+            .bind(refund_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+// 待处理的退款：(refund_id, 金额, 幂等键, session_id, 是否仍在幂等键有效期内)
+type PendingRefund = (i32, Decimal, String, String, bool);
+
+/// 处理仍为 PENDING 的退款（调用支付渠道后服务中断或数据库写入失败）。
+/// 用原来的幂等键重新请求支付渠道：已退过的返回同一笔退款，没退过的现在退款。
+/// 幂等键在支付渠道只保留24小时，更早的记录需要人工核对
+pub async fn reconcile_pending_refunds(pool: &MySqlPool, provider: &dyn PaymentProvider) -> Result<usize, AppError> {
+    let pending: Vec<PendingRefund> = sqlx::query_as(
+        "SELECT r.id, r.amount, r.idempotency_key, p.session_id, r.created_at > NOW() - INTERVAL 23 HOUR
+         FROM order_refunds r JOIN order_payments p ON r.payment_id = p.id
+         WHERE r.status = 'PENDING' AND p.provider = ? AND r.created_at < NOW() - INTERVAL 10 MINUTE"
+    )
+        .bind(provider.name())
+        .fetch_all(pool)
+        .await?;
+
+    let mut completed = 0;
+    for (refund_id, amount, idempotency_key, session_id, within_window) in pending {
+        if !within_window {
+            log::error!("Refund #{} is still pending after 23 hours and needs to be checked manually.", refund_id);
+            continue;
+        }
+        match provider.refund(&session_id, amount, &idempotency_key).await {
+            Ok(provider_refund_id) => {
+                complete_refund(pool, refund_id, &provider_refund_id).await?;
+                completed += 1;
+            }
+            Err(AppError::BadRequest(message)) => fail_refund(pool, refund_id, &message).await?,
+            Err(e) => log::error!("Failed to reconcile refund #{}: {:?}", refund_id, e),
+        }
+    }
+    Ok(completed)
+}
+
+// 结账会话对应的付款：(payment_id, order_id, amount, currency, invoice_number, buyer_company_id, supplier_company_id)
+type SessionPayment = (i32, i32, Decimal, String, Option<String>, i32, i32);
+
+// 根据事件结果更新付款记录和订单付款状态，返回需要通知双方的消息。
+// 事件可能乱序到达，已付款的记录不会因为之后到达的失败事件而改变状态
+async fn apply_payment_outcome(
+    pool: &MySqlPool,
+    provider: &str,
+    session_id: &str,
+    outcome: &PaymentOutcome,
+) -> Result<Option<(SessionPayment, String)>, AppError> {
+    let mut tx = pool.begin().await?;
+
+    let payment: Option<SessionPayment> = sqlx::query_as(
+        "SELECT p.id, p.order_id, p.amount, p.currency, i.invoice_number, po.buyer_company_id, po.supplier_company_id
+         FROM order_payments p
+         JOIN purchase_orders po ON p.order_id = po.id
+         LEFT JOIN invoices i ON p.invoice_id = i.id
+         WHERE p.session_id = ? AND p.provider = ?
+         FOR UPDATE"
+    )
+        .bind(session_id)
+        .bind(provider)
+        .fetch_optional(&mut *tx)
+        .await?;
+    let Some(payment) = payment else {
+        return Ok(None);
+    };
+    let (payment_id, order_id, amount, currency, invoice_number, _, _) = &payment;
+    let subject = match invoice_number {
+        Some(number) => format!("invoice {} of order #{}", number, order_id),
+        None => format!("order #{}", order_id),
+    };
+
+    let message = match outcome {
+        PaymentOutcome::Succeeded => {
+            let updated = sqlx::query("UPDATE order_payments SET status = 'PAID', paid_at = NOW() WHERE id = ? AND status <> 'PAID'")
+                .bind(payment_id)
+                .execute(&mut *tx)
+                .await?;
+            if updated.rows_affected() == 0 {
+                return Ok(None);
+            }
+            sqlx::query("UPDATE purchase_orders SET payment_error = NULL WHERE id = ?")
+                .bind(order_id)
+                .execute(&mut *tx)
+                .await?;
+            Some(format!("Payment of {} {} for {} succeeded.", amount, currency, subject))
+        }
+        PaymentOutcome::Processing => {
+            sqlx::query("UPDATE order_payments SET status = 'PROCESSING' WHERE id = ? AND status IN ('PENDING', 'FAILED')")
+                .bind(payment_id)
+                .execute(&mut *tx)
+                .await?;
+            // 款项到账后还会收到成功或失败事件，此时不通知
+            None
+        }
+        PaymentOutcome::Failed(reason) => {
+            let updated = sqlx::query("UPDATE order_payments SET status = 'FAILED' WHERE id = ? AND status IN ('PENDING', 'PROCESSING')")
+                .bind(payment_id)
+                .execute(&mut *tx)
+                .await?;
+            if updated.rows_affected() == 0 {
+                return Ok(None);
+            }
+            sqlx::query("UPDATE purchase_orders SET payment_error = ? WHERE id = ?")
+                .bind(reason)
+                .bind(order_id)
+                .execute(&mut *tx)
+                .await?;
+            Some(format!("Payment of {} {} for {} failed: {}", amount, currency, subject, reason))
+        }
+        PaymentOutcome::Ignored => return Ok(None),
+    };
+
+    refresh_payment_status(&mut tx, *order_id).await?;
+    tx.commit().await?;
+
+    Ok(message.map(|message| (payment, message)))
+}
+
+/// 处理一条已通过签名校验的Webhook事件。
+/// 所有事件都记录在 payment_events 中，重复投递的事件直接忽略；处理失败的事件在渠道重试时会再次处理
+pub async fn process_webhook_event(
+    pool: &MySqlPool,
+    chat_server: &Addr<ChatServer>,
+    provider: &str,
+    payload: &str,
+    event: PaymentEvent,
+) -> Result<(), AppError> {
+    let inserted = sqlx::query(
+        "INSERT IGNORE INTO payment_events (provider, event_id, event_type, session_id, payload) VALUES (?, ?, ?, ?, ?)"
+    )
+        .bind(provider)
+        .bind(&event.id)
+        .bind(&event.event_type)
+        .bind(&event.session_id)
+        .bind(payload)
         .execute(pool)
         .await?;
 
-    Ok(session.id.to_string())
-}
\ No newline at end of file
+    if inserted.rows_affected() == 0 {
+        // 只有上次处理失败的事件才会被重新处理
+        let retried = sqlx::query(
+            "UPDATE payment_events SET status = 'RECEIVED', attempts = attempts + 1, error = NULL
+             WHERE provider = ? AND event_id = ? AND status = 'FAILED'"
+        )
+            .bind(provider)
+            .bind(&event.id)
+            .execute(pool)
+            .await?;
+        if retried.rows_affected() == 0 {
+            log::info!("Ignoring duplicate payment event {} from {}", event.id, provider);
+            return Ok(());
+        }
+    }
+
+    let result = match &event.session_id {
+        Some(session_id) if event.outcome != PaymentOutcome::Ignored => {
+            apply_payment_outcome(pool, provider, session_id, &event.outcome).await.map(|applied| (true, applied))
+        }
+        _ => Ok((false, None)),
+    };
+
+    let (status, error) = match &result {
+        Ok((true, _)) => ("PROCESSED", None),
+        Ok((false, _)) => ("IGNORED", None),
+        Err(e) => ("FAILED", Some(e.to_string())),
+    };
+    sqlx::query("UPDATE payment_events SET status = ?, error = ?, processed_at = NOW() WHERE provider = ? AND event_id = ?")
+        .bind(status)
+        .bind(&error)
+        .bind(provider)
+        .bind(&event.id)
+        .execute(pool)
+        .await?;
+
+    if let Some(((_, order_id, _, _, _, buyer_company_id, supplier_company_id), message)) = result?.1 {
+        for company_id in [buyer_company_id, supplier_company_id] {
+            if let Err(e) = notification_service::notify_company(
+                pool,
+                chat_server,
+                company_id,
+                message.clone(),
+                format!("/orders/{}", order_id),
+            ).await {
+                log::error!("Failed to send payment notification: {:?}", e);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deposit_schedule_and_payment_status() {
+        let total = Decimal::from(1000);
+        let deposit_percent = Decimal::from(30);
+
+        // 先付 30% 预付款，再付尾款
+        assert_eq!(next_installment(total, deposit_percent, Decimal::ZERO), Some(("DEPOSIT", Decimal::from(300))));
+        assert_eq!(next_installment(total, deposit_percent, Decimal::from(300)), Some(("BALANCE", Decimal::from(700))));
+        assert_eq!(next_installment(total, deposit_percent, total), None);
+        assert_eq!(next_installment(total, Decimal::ZERO, Decimal::ZERO), Some(("FULL", total)));
+
+        assert_eq!(derive_payment_status(total, Decimal::ZERO, Decimal::ZERO, None), "UNPAID");
+        assert_eq!(derive_payment_status(total, Decimal::ZERO, Decimal::ZERO, Some("FAILED")), "FAILED");
+        assert_eq!(derive_payment_status(total, Decimal::from(300), Decimal::ZERO, Some("PAID")), "PARTIALLY_PAID");
+        assert_eq!(derive_payment_status(total, Decimal::from(300), Decimal::ZERO, Some("PROCESSING")), "PROCESSING");
+        assert_eq!(derive_payment_status(total, total, Decimal::ZERO, Some("PAID")), "PAID");
+        assert_eq!(derive_payment_status(total, total, Decimal::from(200), Some("PAID")), "PARTIALLY_REFUNDED");
+        assert_eq!(derive_payment_status(total, total, total, Some("PAID")), "REFUNDED");
+    }
+}
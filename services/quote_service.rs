@@ -1,15 +1,23 @@
 // src/services/quote_service.rs
 use crate::{
     errors::AppError,
-    models::{quote::{CreateQuoteDto, Quote}, user::Claims},
+    models::{quote::{CounterOfferDto, CreateQuoteDto, Quote, QuoteAttachment, QuoteNegotiation, RespondNegotiationDto}, user::{Claims, APPROVER_ROLES, OPERATOR_ROLES}},
+    utils::auth_utils,
 };
-use sqlx::{types::Decimal, MySqlPool, Row};
+use sqlx::{types::Decimal, MySqlConnection, MySqlPool, Row};
 use std::str::FromStr;
 use actix::Addr;
 use crate::models::order::PurchaseOrder;
 use crate::services::chat_server::ChatServer;
 use crate::services::notification_service;
 use crate::services::notification_service::NotificationBuilder;
+use crate::services::rfq_service;
+use crate::services::audit_service::AuditEntry;
+use crate::services::fx_service;
+use futures_util::stream::StreamExt;
+
+// 报价附件不放在公开的 ./uploads 目录下，只能通过带权限检查的下载接口获取
+const QUOTE_UPLOAD_DIR: &str = "./private_uploads/quotes";
 
 pub async fn create_quote(
     pool: &MySqlPool,
@@ -18,6 +26,8 @@ pub async fn create_quote(
     dto: CreateQuoteDto,
     claims: &Claims,
 ) -> Result<u64, AppError> {
+    auth_utils::require_role(claims, OPERATOR_ROLES)?;
+    auth_utils::require_verified_email(claims)?;
     if claims.company_type != "SUPPLIER" {
         return Err(AppError::BadRequest("Only suppliers can create quotes".to_string()));
     }
@@ -30,14 +40,34 @@ pub async fn create_quote(
 
     let price_decimal = Decimal::from_str(&dto.price.to_string())
         .map_err(|_| AppError::BadRequest("Invalid price format".to_string()))?;
+    let deposit_percent = dto.deposit_percent.unwrap_or(0.0);
+    if !(0.0..100.0).contains(&deposit_percent) {
+        return Err(AppError::BadRequest("Deposit must be between 0% and 100% of the price.".to_string()));
+    }
+    let deposit_percent = Decimal::from_str(&deposit_percent.to_string())
+        .map_err(|_| AppError::BadRequest("Invalid deposit format".to_string()))?
+        .round_dp(2);
+    // 未指定币种时按供应商的本位币报价
+    let currency = match dto.currency.as_deref() {
+        Some(currency) => fx_service::normalize_currency(currency)?,
+        None => {
+            let (base_currency,): (String,) = sqlx::query_as("SELECT base_currency FROM companies WHERE id = ?")
+                .bind(claims.company_id)
+                .fetch_one(pool)
+                .await?;
+            base_currency
+        }
+    };
 
     let result = sqlx::query(
-        "INSERT INTO quotes (rfq_id, supplier_company_id, price, lead_time_days, notes) VALUES (?, ?, ?, ?, ?)",
+        "INSERT INTO quotes (rfq_id, supplier_company_id, price, currency, lead_time_days, deposit_percent, notes) VALUES (?, ?, ?, ?, ?, ?, ?)",
     )
         .bind(rfq_id)
         .bind(claims.company_id)
         .bind(price_decimal)
+        .bind(&currency)
         .bind(dto.lead_time_days)
+        .bind(deposit_percent)
         .bind(dto.notes)
         .execute(pool)
         .await?;
@@ -45,46 +75,44 @@ pub async fn create_quote(
     let quote_id = result.last_insert_id();
 
     // --- 【关键修改】同时触发两种通知 ---
-
-    // 1. 查询需要通知的用户ID、邮箱和RFQ标题
-    // 我们假设一个公司只有一个用户，实际应用中这里可能更复杂
-    // --- 【THE FIX】Handle notification results with `if let` ---
-
-    // 1. Fetch the necessary info for notifications
-    let rfq_owner_info: Result<(i32, String, String), _> = sqlx::query_as(
-        "SELECT u.id, u.email, r.title FROM rfqs r JOIN users u ON r.buyer_company_id = u.company_id WHERE r.id = ?"
-    )
+    // 通知采购方公司的所有用户：站内通知 + 邮件
+    let rfq_info: Result<(i32, String), _> = sqlx::query_as("SELECT buyer_company_id, title FROM rfqs WHERE id = ?")
         .bind(rfq_id)
         .fetch_one(pool)
         .await;
 
-    if let Ok((buyer_user_id, buyer_email, rfq_title)) = rfq_owner_info {
-        // 2. Try to send the in-app notification
-        let in_app_result = NotificationBuilder::new(
-            buyer_user_id,
-            format!("You received a new quote for '{}'", &rfq_title)
-        )
-            .with_link(format!("/rfqs/{}", rfq_id))
-            .send(pool, chat_server)
-            .await;
-
-        if let Err(e) = in_app_result {
-            log::error!("Failed to send in-app notification: {:?}", e);
+    match rfq_info {
+        Ok((buyer_company_id, rfq_title)) => {
+            let recipients = notification_service::company_recipients(pool, buyer_company_id).await.unwrap_or_else(|e| {
+                log::error!("Failed to fetch buyer users for RFQ #{}: {:?}", rfq_id, e);
+                Vec::new()
+            });
+            for (buyer_user_id, buyer_email) in recipients {
+                let in_app_result = NotificationBuilder::new(
+                    buyer_user_id,
+                    format!("You received a new quote for '{}'", &rfq_title)
+                )
+                    .with_link(format!("/rfqs/{}", rfq_id))
+                    .send(pool, chat_server)
+                    .await;
+
+                if let Err(e) = in_app_result {
+                    log::error!("Failed to send in-app notification: {:?}", e);
+                }
+
+                let subject = format!("New Quote Received: {}", &rfq_title);
+                let body = format!(
+                    "Hello,\n\nA new quote has been submitted for your RFQ '{}'.\n\nPlease log in to your SCCP account to review it.",
+                    &rfq_title
+                );
+
+                let email_result = notification_service::send_email(buyer_email, subject, body).await;
+                if let Err(e) = email_result {
+                    log::error!("Failed to send email notification: {:?}", e);
+                }
+            }
         }
-
-        // 3. Try to send the email notification
-        let subject = format!("New Quote Received: {}", &rfq_title);
-        let body = format!(
-            "Hello,\n\nA new quote has been submitted for your RFQ '{}'.\n\nPlease log in to your SCCP account to review it.",
-            &rfq_title
-        );
-
-        let email_result = notification_service::send_email(buyer_email, subject, body).await;
-        if let Err(e) = email_result {
-            log::error!("Failed to send email notification: {:?}", e);
-        }
-    } else {
-        log::error!("Failed to fetch RFQ owner info for notifications for RFQ ID: {}", rfq_id);
+        Err(e) => log::error!("Failed to fetch RFQ info for notifications for RFQ ID {}: {:?}", rfq_id, e),
     }
 
     // The main function still succeeds and returns the quote_id
@@ -118,10 +146,13 @@ pub async fn accept_quote(
     quote_id: i32,
     claims: &Claims,
 ) -> Result<u64, AppError> {
+    auth_utils::require_role(claims, APPROVER_ROLES)?;
+    auth_utils::require_verified_email(claims)?;
+    auth_utils::require_mfa(claims)?;
     let mut tx = pool.begin().await?;
 
     let quote_info = sqlx::query(
-        "SELECT q.rfq_id, q.supplier_company_id, q.price, r.buyer_company_id, r.status as rfq_status, r.title as rfq_title
+        "SELECT q.rfq_id, q.supplier_company_id, q.price, q.currency, q.lead_time_days, q.deposit_percent, q.status as quote_status, r.buyer_company_id, r.quantity, r.status as rfq_status, r.title as rfq_title
          FROM quotes q JOIN rfqs r ON q.rfq_id = r.id WHERE q.id = ? FOR UPDATE",
     )
         .bind(quote_id)
@@ -131,37 +162,104 @@ pub async fn accept_quote(
     let rfq_id: i32 = quote_info.try_get("rfq_id")?;
     let supplier_company_id: i32 = quote_info.try_get("supplier_company_id")?;
     let price: Decimal = quote_info.try_get("price")?;
+    let lead_time_days: i32 = quote_info.try_get("lead_time_days")?;
+    let deposit_percent: Decimal = quote_info.try_get("deposit_percent")?;
+    let currency: String = quote_info.try_get("currency")?;
     let buyer_company_id: i32 = quote_info.try_get("buyer_company_id")?;
     let rfq_status: String = quote_info.try_get("rfq_status")?;
     let rfq_title: String = quote_info.try_get("rfq_title")?;
+    let quote_status: String = quote_info.try_get("quote_status")?;
+    let quantity: i32 = quote_info.try_get("quantity")?;
 
     if buyer_company_id != claims.company_id || rfq_status != "OPEN" {
         return Err(AppError::BadRequest(
             "Not authorized to accept this quote or RFQ is not open.".to_string(),
         ));
     }
+    // 被供应商拒绝过订单的报价不能再次被接受
+    if quote_status != "SUBMITTED" {
+        return Err(AppError::BadRequest("This quote can no longer be accepted.".to_string()));
+    }
+
+    // 如果双方已经通过议价达成一致，则使用最后一次达成一致的价格和交期
+    let agreed_terms: Option<(Decimal, i32)> = sqlx::query_as(
+        "SELECT price, lead_time_days FROM quote_negotiations WHERE quote_id = ? AND status = 'ACCEPTED' ORDER BY id DESC LIMIT 1"
+    )
+        .bind(quote_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    let (price, lead_time_days) = agreed_terms.unwrap_or((price, lead_time_days));
+
+    // RFQ 授标后，该RFQ下所有仍在等待回应的议价都自动撤回
+    sqlx::query(
+        "UPDATE quote_negotiations qn JOIN quotes q ON qn.quote_id = q.id
+         SET qn.status = 'WITHDRAWN', qn.responded_at = NOW()
+         WHERE q.rfq_id = ? AND qn.status = 'PENDING'"
+    )
+        .bind(rfq_id)
+        .execute(&mut *tx)
+        .await?;
 
     sqlx::query("UPDATE rfqs SET status = 'AWARDED' WHERE id = ?").bind(rfq_id).execute(&mut *tx).await?;
     sqlx::query("UPDATE quotes SET status = 'ACCEPTED' WHERE id = ?").bind(quote_id).execute(&mut *tx).await?;
 
+    // 报价为订单总价，单价用于之后的变更单重新计算总价
+    let unit_price = if quantity > 0 { (price / Decimal::from(quantity)).round_dp(4) } else { price };
+
+    // 根据报价（或议价后）的交期计算预计发货日期，用于交期监控
+    let expected_ship_date = chrono::Utc::now().date_naive() + chrono::Days::new(lead_time_days.max(0) as u64);
+
     let po_result = sqlx::query(
-        "INSERT INTO purchase_orders (quote_id, rfq_id, buyer_company_id, supplier_company_id, total_amount) VALUES (?, ?, ?, ?, ?)",
+        "INSERT INTO purchase_orders (quote_id, rfq_id, buyer_company_id, supplier_company_id, quantity, unit_price, total_amount, currency, deposit_percent, expected_ship_date) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
     )
-        .bind(quote_id).bind(rfq_id).bind(buyer_company_id).bind(supplier_company_id).bind(price)
+        .bind(quote_id).bind(rfq_id).bind(buyer_company_id).bind(supplier_company_id).bind(quantity).bind(unit_price).bind(price).bind(&currency).bind(deposit_percent).bind(expected_ship_date)
         .execute(&mut *tx)
         .await?;
 
     let po_id = po_result.last_insert_id();
-    tx.commit().await?;
 
-    // --- 【THE FIX】Handle notification Results with `if let` ---
-    let supplier_user: Result<(i32, String), _> =
-        sqlx::query_as("SELECT id, email FROM users WHERE company_id = ? LIMIT 1")
-            .bind(supplier_company_id)
-            .fetch_one(pool)
-            .await;
+    // 版本1：授标时的原始条款
+    sqlx::query(
+        "INSERT INTO purchase_order_versions (order_id, version, quantity, unit_price, total_amount) VALUES (?, 1, ?, ?, ?)"
+    )
+        .bind(po_id)
+        .bind(quantity)
+        .bind(unit_price)
+        .bind(price)
+        .execute(&mut *tx)
+        .await?;
+
+    // 记录订单的初始状态
+    sqlx::query(
+        "INSERT INTO order_status_history (order_id, from_status, to_status, actor_user_id, actor_company_id, comment) VALUES (?, NULL, 'PENDING_CONFIRMATION', ?, ?, ?)"
+    )
+        .bind(po_id)
+        .bind(claims.sub)
+        .bind(claims.company_id)
+        .bind(format!("Created from accepted quote #{}", quote_id))
+        .execute(&mut *tx)
+        .await?;
+
+    AuditEntry::new(claims, "QUOTE_ACCEPTED", "QUOTE", quote_id)
+        .before(serde_json::json!({ "status": quote_status }))
+        .after(serde_json::json!({
+            "status": "ACCEPTED",
+            "order_id": po_id,
+            "supplier_company_id": supplier_company_id,
+            "total_amount": price.to_string(),
+            "currency": currency,
+        }))
+        .record(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
 
-    if let Ok((supplier_user_id, supplier_email)) = supplier_user {
+    // 通知供应商公司的所有用户：站内通知 + 邮件
+    let recipients = notification_service::company_recipients(pool, supplier_company_id).await.unwrap_or_else(|e| {
+        log::error!("Failed to fetch supplier users for quote #{}: {:?}", quote_id, e);
+        Vec::new()
+    });
+    for (supplier_user_id, supplier_email) in recipients {
         // Try to send in-app notification
         let in_app_result = NotificationBuilder::new(
             supplier_user_id,
@@ -211,4 +309,350 @@ pub async fn get_orders_for_user(pool: &MySqlPool, claims: &Claims) -> Result<Ve
         .await?;
 
     Ok(orders)
-}
\ No newline at end of file
+}
+
+// 查询报价所属RFQ的双方公司及状态：(supplier_company_id, buyer_company_id, quote_status, rfq_status, rfq_title)
+async fn get_quote_parties(
+    pool: &MySqlPool,
+    quote_id: i32,
+) -> Result<(i32, i32, String, String, String), AppError> {
+    sqlx::query_as(
+        "SELECT q.supplier_company_id, r.buyer_company_id, q.status, r.status, r.title
+         FROM quotes q JOIN rfqs r ON q.rfq_id = r.id WHERE q.id = ?"
+    )
+        .bind(quote_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("Quote not found".to_string()))
+}
+
+// 在事务内锁定报价行后再读取双方及状态，避免并发议价基于过期状态插入多轮 PENDING
+async fn lock_quote_parties(
+    conn: &mut MySqlConnection,
+    quote_id: i32,
+) -> Result<(i32, i32, String, String, String), AppError> {
+    sqlx::query_as(
+        "SELECT q.supplier_company_id, r.buyer_company_id, q.status, r.status, r.title
+         FROM quotes q JOIN rfqs r ON q.rfq_id = r.id WHERE q.id = ? FOR UPDATE"
+    )
+        .bind(quote_id)
+        .fetch_optional(conn)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("Quote not found".to_string()))
+}
+
+// 还价金额必须为正数
+fn counter_offer_price(price: f64) -> Result<Decimal, AppError> {
+    let price = Decimal::from_str(&price.to_string())
+        .map_err(|_| AppError::BadRequest("Invalid price format".to_string()))?;
+    if price <= Decimal::ZERO {
+        return Err(AppError::BadRequest("Counter-offer price must be greater than zero.".to_string()));
+    }
+    Ok(price)
+}
+
+/// 采购方针对某个报价发起还价（价格、交期、备注）
+pub async fn create_counter_offer(
+    pool: &MySqlPool,
+    chat_server: &Addr<ChatServer>,
+    quote_id: i32,
+    dto: CounterOfferDto,
+    claims: &Claims,
+) -> Result<u64, AppError> {
+    auth_utils::require_role(claims, OPERATOR_ROLES)?;
+    auth_utils::require_verified_email(claims)?;
+    if dto.lead_time_days <= 0 {
+        return Err(AppError::BadRequest("Lead time must be a positive number of days.".to_string()));
+    }
+    let price_decimal = counter_offer_price(dto.price)?;
+
+    let mut tx = pool.begin().await?;
+    let (supplier_company_id, buyer_company_id, quote_status, rfq_status, rfq_title) =
+        lock_quote_parties(&mut tx, quote_id).await?;
+
+    if claims.company_type != "BUYER" || buyer_company_id != claims.company_id {
+        return Err(AppError::BadRequest("Only the RFQ owner can send a counter-offer.".to_string()));
+    }
+    if rfq_status != "OPEN" || quote_status != "SUBMITTED" {
+        return Err(AppError::BadRequest("This quote is no longer open for negotiation.".to_string()));
+    }
+
+    // 同一时间只允许存在一轮等待回应的议价（报价行已加锁，检查与插入不会交错）
+    let pending: Option<(i32,)> = sqlx::query_as(
+        "SELECT id FROM quote_negotiations WHERE quote_id = ? AND status = 'PENDING'"
+    )
+        .bind(quote_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    if pending.is_some() {
+        return Err(AppError::BadRequest("There is already a pending negotiation round for this quote.".to_string()));
+    }
+
+    let result = sqlx::query(
+        "INSERT INTO quote_negotiations (quote_id, proposed_by_company_id, proposer_type, price, lead_time_days, note) VALUES (?, ?, 'BUYER', ?, ?, ?)"
+    )
+        .bind(quote_id)
+        .bind(claims.company_id)
+        .bind(price_decimal)
+        .bind(dto.lead_time_days)
+        .bind(dto.note)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    if let Err(e) = notification_service::notify_company(
+        pool,
+        chat_server,
+        supplier_company_id,
+        format!("You received a counter-offer on your quote for '{}'", &rfq_title),
+        format!("/quotes/{}/negotiations", quote_id),
+    ).await {
+        log::error!("Failed to send counter-offer notification: {:?}", e);
+    }
+
+    Ok(result.last_insert_id())
+}
+
+/// 对一轮议价进行回应：接受、拒绝或再次还价
+/// 只有议价的接收方（即非发起方）才能回应
+pub async fn respond_to_negotiation(
+    pool: &MySqlPool,
+    chat_server: &Addr<ChatServer>,
+    quote_id: i32,
+    round_id: i32,
+    dto: RespondNegotiationDto,
+    claims: &Claims,
+) -> Result<(), AppError> {
+    auth_utils::require_role(claims, OPERATOR_ROLES)?;
+    auth_utils::require_verified_email(claims)?;
+    let mut tx = pool.begin().await?;
+    let (supplier_company_id, buyer_company_id, quote_status, rfq_status, rfq_title) =
+        lock_quote_parties(&mut tx, quote_id).await?;
+
+    if rfq_status != "OPEN" || quote_status != "SUBMITTED" {
+        return Err(AppError::BadRequest("This quote is no longer open for negotiation.".to_string()));
+    }
+
+    let round: QuoteNegotiation = sqlx::query_as(
+        "SELECT * FROM quote_negotiations WHERE id = ? AND quote_id = ? FOR UPDATE"
+    )
+        .bind(round_id)
+        .bind(quote_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("Negotiation round not found".to_string()))?;
+
+    if round.status != "PENDING" {
+        return Err(AppError::BadRequest("This negotiation round has already been answered.".to_string()));
+    }
+
+    // 回应方必须是发起方的对手方
+    let responder_company_id = if round.proposer_type == "BUYER" { supplier_company_id } else { buyer_company_id };
+    if claims.company_id != responder_company_id {
+        return Err(AppError::BadRequest("You are not authorized to respond to this negotiation round.".to_string()));
+    }
+
+    let new_status = match dto.action.as_str() {
+        "ACCEPT" => "ACCEPTED",
+        "REJECT" => "REJECTED",
+        "COUNTER" => "COUNTERED",
+        _ => return Err(AppError::BadRequest("Invalid action provided.".to_string())),
+    };
+
+    sqlx::query("UPDATE quote_negotiations SET status = ?, responded_at = NOW() WHERE id = ?")
+        .bind(new_status)
+        .bind(round_id)
+        .execute(&mut *tx)
+        .await?;
+
+    if new_status == "COUNTERED" {
+        let (price, lead_time_days) = match (dto.price, dto.lead_time_days) {
+            (Some(price), Some(days)) if days > 0 => (price, days),
+            _ => return Err(AppError::BadRequest("A counter-offer requires a price and a positive lead time.".to_string())),
+        };
+        let price_decimal = counter_offer_price(price)?;
+
+        sqlx::query(
+            "INSERT INTO quote_negotiations (quote_id, proposed_by_company_id, proposer_type, price, lead_time_days, note) VALUES (?, ?, ?, ?, ?, ?)"
+        )
+            .bind(quote_id)
+            .bind(claims.company_id)
+            .bind(&claims.company_type)
+            .bind(price_decimal)
+            .bind(lead_time_days)
+            .bind(dto.note)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+
+    let message = match new_status {
+        "ACCEPTED" => format!("Your counter-offer for '{}' was accepted.", &rfq_title),
+        "REJECTED" => format!("Your counter-offer for '{}' was rejected.", &rfq_title),
+        _ => format!("You received a new counter-offer for '{}'", &rfq_title),
+    };
+    if let Err(e) = notification_service::notify_company(
+        pool,
+        chat_server,
+        round.proposed_by_company_id,
+        message,
+        format!("/quotes/{}/negotiations", quote_id),
+    ).await {
+        log::error!("Failed to send negotiation notification: {:?}", e);
+    }
+
+    Ok(())
+}
+
+/// 获取某个报价的全部议价记录，只有RFQ发布方和报价供应商可以查看
+pub async fn get_negotiations_for_quote(
+    pool: &MySqlPool,
+    quote_id: i32,
+    claims: &Claims,
+) -> Result<Vec<QuoteNegotiation>, AppError> {
+    let (supplier_company_id, buyer_company_id, _, _, _) = get_quote_parties(pool, quote_id).await?;
+
+    if claims.company_id != supplier_company_id && claims.company_id != buyer_company_id {
+        return Err(AppError::BadRequest("You are not authorized to view this negotiation.".to_string()));
+    }
+
+    let rounds = sqlx::query_as("SELECT * FROM quote_negotiations WHERE quote_id = ? ORDER BY created_at ASC, id ASC")
+        .bind(quote_id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rounds)
+}
+
+/// 报价供应商为自己的报价上传附件（正式报价单、DFM反馈、样品照片等）
+/// 只有仍在等待采购方决定的报价（报价 SUBMITTED 且 RFQ OPEN）可以追加附件
+pub async fn upload_attachments_for_quote(
+    pool: &MySqlPool,
+    claims: &Claims,
+    quote_id: i32,
+    payload: actix_multipart::Multipart,
+) -> Result<Vec<u64>, AppError> {
+    auth_utils::require_role(claims, OPERATOR_ROLES)?;
+    let (supplier_company_id, _, quote_status, rfq_status, _) = get_quote_parties(pool, quote_id).await?;
+    ensure_quote_accepts_attachments(supplier_company_id, &quote_status, &rfq_status, claims)?;
+
+    // 文件先全部写入磁盘再统一入库，之后任何一步失败都要删除已写入的文件，避免留下无主文件
+    let mut saved: Vec<(String, String)> = Vec::new();
+    let result = store_quote_attachments(pool, claims, quote_id, payload, &mut saved).await;
+    if result.is_err() {
+        for (_, path) in &saved {
+            rfq_service::remove_attachment(path);
+        }
+    }
+    result
+}
+
+fn ensure_quote_accepts_attachments(
+    supplier_company_id: i32,
+    quote_status: &str,
+    rfq_status: &str,
+    claims: &Claims,
+) -> Result<(), AppError> {
+    if claims.company_id != supplier_company_id {
+        return Err(AppError::BadRequest("Only the quoting supplier can upload attachments.".to_string()));
+    }
+    if quote_status != "SUBMITTED" || rfq_status != "OPEN" {
+        return Err(AppError::BadRequest("Attachments can only be added while the quote is open.".to_string()));
+    }
+    Ok(())
+}
+
+async fn store_quote_attachments(
+    pool: &MySqlPool,
+    claims: &Claims,
+    quote_id: i32,
+    mut payload: actix_multipart::Multipart,
+    saved: &mut Vec<(String, String)>,
+) -> Result<Vec<u64>, AppError> {
+    while let Some(field_result) = payload.next().await {
+        let mut field = field_result?;
+        if let Some(file) = rfq_service::save_attachment_field(&mut field, QUOTE_UPLOAD_DIR).await? {
+            saved.push(file);
+        }
+    }
+    if saved.is_empty() {
+        return Err(AppError::BadRequest("No attachment found in the upload.".to_string()));
+    }
+
+    // 上传期间报价状态可能已变化，入库前在锁内重新检查
+    let mut tx = pool.begin().await?;
+    let (supplier_company_id, _, quote_status, rfq_status, _) = lock_quote_parties(&mut tx, quote_id).await?;
+    ensure_quote_accepts_attachments(supplier_company_id, &quote_status, &rfq_status, claims)?;
+
+    let mut attachment_ids = Vec::with_capacity(saved.len());
+    for (filename, filepath) in saved.iter() {
+        let result = sqlx::query(
+            "INSERT INTO quote_attachments (quote_id, original_filename, stored_path) VALUES (?, ?, ?)"
+        )
+            .bind(quote_id)
+            .bind(filename)
+            .bind(filepath)
+            .execute(&mut *tx)
+            .await?;
+        attachment_ids.push(result.last_insert_id());
+    }
+    tx.commit().await?;
+
+    Ok(attachment_ids)
+}
+
+/// 获取报价的附件列表，只有报价供应商和RFQ发布方可以查看
+pub async fn get_attachments_for_quote(
+    pool: &MySqlPool,
+    quote_id: i32,
+    claims: &Claims,
+) -> Result<Vec<QuoteAttachment>, AppError> {
+    let (supplier_company_id, buyer_company_id, _, _, _) = get_quote_parties(pool, quote_id).await?;
+
+    if claims.company_id != supplier_company_id && claims.company_id != buyer_company_id {
+        return Err(AppError::BadRequest("You are not authorized to view these attachments.".to_string()));
+    }
+
+    let attachments = sqlx::query_as("SELECT * FROM quote_attachments WHERE quote_id = ? ORDER BY created_at ASC")
+        .bind(quote_id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(attachments)
+}
+
+/// 获取单个报价附件（用于下载），权限规则与附件列表相同
+pub async fn get_quote_attachment(
+    pool: &MySqlPool,
+    quote_id: i32,
+    attachment_id: i32,
+    claims: &Claims,
+) -> Result<QuoteAttachment, AppError> {
+    let (supplier_company_id, buyer_company_id, _, _, _) = get_quote_parties(pool, quote_id).await?;
+
+    if claims.company_id != supplier_company_id && claims.company_id != buyer_company_id {
+        return Err(AppError::BadRequest("You are not authorized to view these attachments.".to_string()));
+    }
+
+    sqlx::query_as("SELECT * FROM quote_attachments WHERE id = ? AND quote_id = ?")
+        .bind(attachment_id)
+        .bind(quote_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("Attachment not found".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counter_offer_price_must_be_positive() {
+        assert_eq!(counter_offer_price(12.5).unwrap(), Decimal::from_str("12.5").unwrap());
+        assert!(counter_offer_price(0.0).is_err());
+        assert!(counter_offer_price(-3.0).is_err());
+        assert!(counter_offer_price(f64::NAN).is_err());
+    }
+}
@@ -1,7 +1,8 @@
 // src/services/rfq_service.rs
 use crate::{
     errors::AppError,
-    models::{rfq::{CreateRfqDto, Rfq}, user::Claims},
+    models::{rfq::{CreateRfqDto, Rfq}, user::{Claims, OPERATOR_ROLES}},
+    utils::auth_utils,
 };
 use sqlx::{MySql, MySqlPool, QueryBuilder};
 use actix_multipart::Field;
@@ -23,12 +24,97 @@ const ALLOWED_EXTENSIONS: &[&str] = &["pdf", "doc", "docx", "png", "jpg", "jpeg"
 // 上传文件的最大体积（以字节为单位）。当前设置为 10MB。
 const MAX_UPLOAD_SIZE_BYTES: usize = 10 * 1024 * 1024;
 
+// RFQ 附件的存储目录（通过 /uploads 静态路由公开访问）
+const UPLOAD_DIR: &str = "./uploads";
+
+/// 校验并保存一个 multipart 附件字段，返回 (原始文件名, 存储路径)。
+/// 字段没有文件名时返回 None。RFQ 附件和报价附件共用这套校验规则。
+pub(crate) async fn save_attachment_field(
+    field: &mut Field,
+    upload_dir: &str,
+) -> Result<Option<(String, String)>, AppError> {
+    let filename = match field
+        .content_disposition()
+        .and_then(|cd| cd.get_filename().map(|s| s.to_string()))
+    {
+        Some(filename) => filename,
+        None => return Ok(None),
+    };
+
+    // 扩展名校验
+    if let Some(ext) = filename.split('.').last() {
+        let ext_lower = ext.to_lowercase();
+        if !ALLOWED_EXTENSIONS.contains(&ext_lower.as_str()) {
+            return Err(AppError::BadRequest(format!(
+                "Unsupported attachment type: {}",
+                ext
+            )));
+        }
+    } else {
+        return Err(AppError::BadRequest(
+            "Attachment must have a file extension".to_string(),
+        ));
+    }
+
+    // 生成唯一文件名并确定存储路径
+    let unique_filename = format!("{}-{}", Uuid::new_v4(), filename);
+    let filepath = format!("{}/{}", upload_dir, unique_filename);
+
+    // 确保目录存在
+    let dir = std::path::Path::new(upload_dir);
+    if !dir.exists() {
+        fs::create_dir_all(dir)
+            .map_err(|e| AppError::IoError(std::io::Error::new(e.kind(), e)))?;
+    }
+
+    // 创建文件
+    let filepath_clone = filepath.clone();
+    let file_create_result =
+        web::block(move || fs::File::create(&filepath_clone)).await?;
+    let mut f = file_create_result?;
+
+    // 累加文件大小，限制单个文件体积
+    let written: Result<(), AppError> = async {
+        let mut total_size: usize = 0;
+        while let Some(chunk) = field.next().await {
+            let data = chunk?;
+            total_size += data.len();
+            if total_size > MAX_UPLOAD_SIZE_BYTES {
+                return Err(AppError::BadRequest(format!(
+                    "Attachment exceeds maximum size of {} bytes",
+                    MAX_UPLOAD_SIZE_BYTES
+                )));
+            }
+            let write_result =
+                web::block(move || f.write_all(&data).map(|_| f)).await?;
+            f = write_result?;
+        }
+        Ok(())
+    }.await;
+    // 上传中途失败时删除已写入的部分文件
+    if let Err(e) = written {
+        remove_attachment(&filepath);
+        return Err(e);
+    }
+
+    Ok(Some((filename, filepath)))
+}
+
+/// 删除已保存的附件文件，用于保存之后的处理失败时清理，删除失败只记录日志
+pub(crate) fn remove_attachment(filepath: &str) {
+    if let Err(e) = fs::remove_file(filepath) {
+        log::error!("Failed to remove attachment {}: {:?}", filepath, e);
+    }
+}
+
 // 创建不带附件的 RFQ
 pub async fn create_rfq(
     pool: &MySqlPool,
     dto: CreateRfqDto,
     claims: &Claims,
 ) -> Result<u64, AppError> {
+    auth_utils::require_role(claims, OPERATOR_ROLES)?;
+    auth_utils::require_verified_email(claims)?;
     if claims.company_type != "BUYER" {
         return Err(AppError::BadRequest("Only buyers can create RFQs".to_string()));
     }
@@ -53,6 +139,8 @@ pub async fn create_rfq_with_attachment(
     claims: &Claims,
     mut payload: actix_multipart::Multipart,
 ) -> Result<u64, AppError> {
+    auth_utils::require_role(claims, OPERATOR_ROLES)?;
+    auth_utils::require_verified_email(claims)?;
     if claims.company_type != "BUYER" {
         return Err(AppError::BadRequest("Only buyers can create RFQs".to_string()));
     }
@@ -92,61 +180,9 @@ pub async fn create_rfq_with_attachment(
             }
             // 附件字段解析与校验
             "attachment" => {
-                if let Some(filename) = field
-                    .content_disposition()
-                    .expect("Missing Content-Disposition")
-                    .get_filename()
-                {
-                    let filename = filename.to_string();
-                    // 扩展名校验
-                    if let Some(ext) = filename.split('.').last() {
-                        let ext_lower = ext.to_lowercase();
-                        if !ALLOWED_EXTENSIONS.contains(&ext_lower.as_str()) {
-                            return Err(AppError::BadRequest(format!(
-                                "Unsupported attachment type: {}",
-                                ext
-                            )));
-                        }
-                    } else {
-                        return Err(AppError::BadRequest(
-                            "Attachment must have a file extension".to_string(),
-                        ));
-                    }
-
-                    // 生成唯一文件名并确定存储路径
-                    let unique_filename = format!("{}-{}", Uuid::new_v4(), filename);
-                    let filepath = format!("./uploads/{}", unique_filename);
-
+                if let Some((filename, filepath)) = save_attachment_field(&mut field, UPLOAD_DIR).await? {
                     original_filename = Some(filename);
-                    attachment_path = Some(filepath.clone());
-
-                    // 确保目录存在
-                    let dir = std::path::Path::new("./uploads");
-                    if !dir.exists() {
-                        fs::create_dir_all(dir)
-                            .map_err(|e| AppError::IoError(std::io::Error::new(e.kind(), e)))?;
-                    }
-
-                    // 创建文件
-                    let file_create_result =
-                        web::block(move || fs::File::create(&filepath)).await?;
-                    let mut f = file_create_result?;
-
-                    // 累加文件大小，限制单个文件体积
-                    let mut total_size: usize = 0;
-                    while let Some(chunk) = field.next().await {
-                        let data = chunk?;
-                        total_size += data.len();
-                        if total_size > MAX_UPLOAD_SIZE_BYTES {
-                            return Err(AppError::BadRequest(format!(
-                                "Attachment exceeds maximum size of {} bytes",
-                                MAX_UPLOAD_SIZE_BYTES
-                            )));
-                        }
-                        let write_result =
-                            web::block(move || f.write_all(&data).map(|_| f)).await?;
-                        f = write_result?;
-                    }
+                    attachment_path = Some(filepath);
                 }
             }
             _ => (),
@@ -274,6 +310,7 @@ pub async fn upload_attachment_for_rfq(
     rfq_id: i32,
     mut payload: actix_multipart::Multipart,
 ) -> Result<(), AppError> {
+    auth_utils::require_role(claims, OPERATOR_ROLES)?;
     // Security check remains the same
     let rfq_owner: (i32,) = sqlx::query_as("SELECT buyer_company_id FROM rfqs WHERE id = ?")
         .bind(rfq_id)
@@ -0,0 +1,280 @@
+// src/services/rma_service.rs
+
+use crate::{
+    errors::AppError,
+    models::{dispute::{CreateRmaDto, Rma, RmaDecisionDto}, user::{Claims, APPROVER_ROLES, OPERATOR_ROLES}},
+    services::{chat_server::ChatServer, notification_service, order_service},
+    utils::auth_utils,
+};
+use actix::Addr;
+use sqlx::{types::Decimal, MySqlConnection, MySqlPool};
+use std::str::FromStr;
+
+/// 采购方为有缺陷的批次申请退货(RMA)
+pub async fn create_rma(
+    pool: &MySqlPool,
+    chat_server: &Addr<ChatServer>,
+    order_id: i32,
+    dto: CreateRmaDto,
+    claims: &Claims,
+) -> Result<u64, AppError> {
+    auth_utils::require_role(claims, OPERATOR_ROLES)?;
+    if dto.return_quantity <= 0 {
+        return Err(AppError::BadRequest("Return quantity must be positive.".to_string()));
+    }
+    if dto.reason.trim().is_empty() {
+        return Err(AppError::BadRequest("A reason is required for a return.".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+    let (current_status, buyer_company_id, supplier_company_id) =
+        order_service::lock_order(&mut tx, order_id, claims).await?;
+
+    if claims.company_id != buyer_company_id {
+        return Err(AppError::BadRequest("Only the buyer can request a return.".to_string()));
+    }
+    if !matches!(current_status.as_str(), "SHIPPED" | "DELIVERED" | "COMPLETED" | "DISPUTED") {
+        return Err(AppError::BadRequest("Returns can only be requested for received goods.".to_string()));
+    }
+
+    // 退货数量不能超过已收货数量减去已申请退货的数量
+    let (received_total, returned_total): (i64, i64) = sqlx::query_as(
+        "SELECT
+            CAST((SELECT COALESCE(SUM(g.received_quantity), 0) FROM goods_receipts g JOIN order_shipments s ON g.shipment_id = s.id WHERE s.order_id = ?) AS SIGNED),
+            CAST((SELECT COALESCE(SUM(return_quantity), 0) FROM rmas WHERE order_id = ? AND status <> 'REJECTED') AS SIGNED)"
+    )
+        .bind(order_id)
+        .bind(order_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    if dto.return_quantity as i64 > received_total - returned_total {
+        return Err(AppError::BadRequest(format!(
+            "Return quantity exceeds the {} received units that are not already being returned.",
+            received_total - returned_total
+        )));
+    }
+
+    if let Some(shipment_id) = dto.shipment_id {
+        let shipment: Option<(i32,)> = sqlx::query_as("SELECT id FROM order_shipments WHERE id = ? AND order_id = ?")
+            .bind(shipment_id)
+            .bind(order_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if shipment.is_none() {
+            return Err(AppError::BadRequest("Shipment not found for this order.".to_string()));
+        }
+    }
+
+    let result = sqlx::query(
+        "INSERT INTO rmas (order_id, shipment_id, return_quantity, reason, created_by_user_id) VALUES (?, ?, ?, ?, ?)"
+    )
+        .bind(order_id)
+        .bind(dto.shipment_id)
+        .bind(dto.return_quantity)
+        .bind(&dto.reason)
+        .bind(claims.sub)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    if let Err(e) = notification_service::notify_company(
+        pool,
+        chat_server,
+        supplier_company_id,
+        format!("A return of {} units was requested on order #{}: {}", dto.return_quantity, order_id, dto.reason),
+        format!("/orders/{}", order_id),
+    ).await {
+        log::error!("Failed to send RMA notification: {:?}", e);
+    }
+
+    Ok(result.last_insert_id())
+}
+
+/// 获取订单的所有RMA
+pub async fn get_rmas_for_order(pool: &MySqlPool, order_id: i32, claims: &Claims) -> Result<Vec<Rma>, AppError> {
+    order_service::ensure_order_party(pool, order_id, claims).await?;
+
+    let rmas = sqlx::query_as("SELECT * FROM rmas WHERE order_id = ? ORDER BY created_at DESC")
+        .bind(order_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(rmas)
+}
+
+// 锁定RMA并确保操作者是该订单的供应商
+async fn lock_rma_for_supplier(
+    tx: &mut MySqlConnection,
+    order_id: i32,
+    rma_id: i32,
+    claims: &Claims,
+) -> Result<(Rma, i32), AppError> {
+    let (_, buyer_company_id, supplier_company_id) = order_service::lock_order(tx, order_id, claims).await?;
+    if claims.company_id != supplier_company_id {
+        return Err(AppError::BadRequest("Only the supplier can process returns.".to_string()));
+    }
+
+    let rma: Rma = sqlx::query_as("SELECT * FROM rmas WHERE id = ? AND order_id = ? FOR UPDATE")
+        .bind(rma_id)
+        .bind(order_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("RMA not found".to_string()))?;
+
+    Ok((rma, buyer_company_id))
+}
+
+// RMA 可退款上限：退货数量 × 单价，且不超过订单金额扣除其他RMA已约定的退款和未关联RMA的退款
+// 关联RMA的退款已受该RMA的 credit_amount 约束，不重复扣除
+async fn credit_limit(
+    tx: &mut MySqlConnection,
+    order_id: i32,
+    rma_id: i32,
+    return_quantity: i32,
+) -> Result<Decimal, AppError> {
+    let (unit_price, total_amount): (Decimal, Decimal) = sqlx::query_as(
+        "SELECT unit_price, total_amount FROM purchase_orders WHERE id = ?"
+    )
+        .bind(order_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    let (credited,): (Decimal,) = sqlx::query_as(
+        "SELECT COALESCE(SUM(credit_amount), 0) FROM rmas
+         WHERE order_id = ? AND id <> ? AND outcome = 'CREDIT' AND status IN ('APPROVED', 'CLOSED')"
+    )
+        .bind(order_id)
+        .bind(rma_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    let (refunded,): (Decimal,) = sqlx::query_as(
+        "SELECT COALESCE(SUM(amount), 0) FROM order_refunds WHERE order_id = ? AND rma_id IS NULL AND status <> 'FAILED'"
+    )
+        .bind(order_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    Ok(max_credit(return_quantity, unit_price, total_amount, credited + refunded))
+}
+
+fn max_credit(return_quantity: i32, unit_price: Decimal, order_total: Decimal, already_credited: Decimal) -> Decimal {
+    let returned_value = Decimal::from(return_quantity) * unit_price;
+    returned_value.min(order_total - already_credited).max(Decimal::ZERO)
+}
+
+/// 供应商同意或拒绝RMA。同意时需给出处理结果：退款(CREDIT)或补发(REPLACEMENT)
+pub async fn decide_rma(
+    pool: &MySqlPool,
+    chat_server: &Addr<ChatServer>,
+    order_id: i32,
+    rma_id: i32,
+    dto: RmaDecisionDto,
+    claims: &Claims,
+) -> Result<(), AppError> {
+    auth_utils::require_role(claims, APPROVER_ROLES)?;
+    let mut tx = pool.begin().await?;
+    let (rma, buyer_company_id) = lock_rma_for_supplier(&mut tx, order_id, rma_id, claims).await?;
+
+    if rma.status != "REQUESTED" {
+        return Err(AppError::BadRequest("This RMA has already been decided.".to_string()));
+    }
+
+    let (new_status, outcome, credit_amount) = if dto.approve {
+        match dto.outcome.as_deref() {
+            Some("CREDIT") => {
+                let amount = dto.credit_amount
+                    .filter(|a| *a > 0.0)
+                    .ok_or_else(|| AppError::BadRequest("A positive credit amount is required.".to_string()))?;
+                let amount = Decimal::from_str(&amount.to_string())
+                    .map_err(|_| AppError::BadRequest("Invalid credit amount format".to_string()))?;
+                let limit = credit_limit(&mut tx, order_id, rma_id, rma.return_quantity).await?;
+                if amount > limit {
+                    return Err(AppError::BadRequest(format!(
+                        "The credit cannot exceed {} for the returned quantity.",
+                        limit
+                    )));
+                }
+                ("APPROVED", Some("CREDIT"), Some(amount))
+            }
+            Some("REPLACEMENT") => ("APPROVED", Some("REPLACEMENT"), None),
+            _ => return Err(AppError::BadRequest("Outcome must be CREDIT or REPLACEMENT.".to_string())),
+        }
+    } else {
+        ("REJECTED", None, None)
+    };
+
+    sqlx::query("UPDATE rmas SET status = ?, outcome = ?, credit_amount = ?, supplier_note = ? WHERE id = ?")
+        .bind(new_status)
+        .bind(outcome)
+        .bind(credit_amount)
+        .bind(&dto.note)
+        .bind(rma_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    let message = match outcome {
+        Some(outcome) => format!("Your return request on order #{} was approved ({})", order_id, outcome),
+        None => format!("Your return request on order #{} was rejected", order_id),
+    };
+    if let Err(e) = notification_service::notify_company(
+        pool,
+        chat_server,
+        buyer_company_id,
+        message,
+        format!("/orders/{}", order_id),
+    ).await {
+        log::error!("Failed to send RMA decision notification: {:?}", e);
+    }
+
+    Ok(())
+}
+
+/// 供应商在收到退货并完成退款/补发后关闭RMA
+pub async fn close_rma(
+    pool: &MySqlPool,
+    chat_server: &Addr<ChatServer>,
+    order_id: i32,
+    rma_id: i32,
+    claims: &Claims,
+) -> Result<(), AppError> {
+    auth_utils::require_role(claims, OPERATOR_ROLES)?;
+    let mut tx = pool.begin().await?;
+    let (rma, buyer_company_id) = lock_rma_for_supplier(&mut tx, order_id, rma_id, claims).await?;
+
+    if rma.status != "APPROVED" {
+        return Err(AppError::BadRequest("Only approved RMAs can be closed.".to_string()));
+    }
+
+    sqlx::query("UPDATE rmas SET status = 'CLOSED' WHERE id = ?")
+        .bind(rma_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    if let Err(e) = notification_service::notify_company(
+        pool,
+        chat_server,
+        buyer_company_id,
+        format!("Return #{} on order #{} has been processed and closed", rma_id, order_id),
+        format!("/orders/{}", order_id),
+    ).await {
+        log::error!("Failed to send RMA closed notification: {:?}", e);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_max_credit() {
+        let price = Decimal::from(10);
+        // 受退货数量限制
+        assert_eq!(max_credit(3, price, Decimal::from(1000), Decimal::ZERO), Decimal::from(30));
+        // 受订单剩余可退金额限制
+        assert_eq!(max_credit(3, price, Decimal::from(100), Decimal::from(80)), Decimal::from(20));
+        // 已全部退完
+        assert_eq!(max_credit(3, price, Decimal::from(100), Decimal::from(120)), Decimal::ZERO);
+    }
+}
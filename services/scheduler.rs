@@ -0,0 +1,56 @@
+// src/services/scheduler.rs
+
+use crate::{
+    config::Config,
+    services::{chat_server::ChatServer, invoice_service, order_service, payment_provider::PaymentProvider, payment_service},
+};
+use actix::Addr;
+use actix_web::rt;
+use sqlx::MySqlPool;
+use std::{sync::Arc, time::Duration};
+
+// 后台定时任务的执行间隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// 启动后台定时任务
+pub fn start(pool: MySqlPool, chat_server: Addr<ChatServer>, config: Config, payment_provider: Arc<dyn PaymentProvider>) {
+    rt::spawn(async move {
+        let mut interval = rt::time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            // 1. 标记超过确认时限的订单
+            match order_service::flag_unconfirmed_orders(&pool, &chat_server, config.order_confirmation_sla_hours).await {
+                Ok(0) => {}
+                Ok(count) => log::info!("Flagged {} orders past the confirmation SLA.", count),
+                Err(e) => log::error!("Failed to check order confirmation SLA: {:?}", e),
+            }
+
+            // 2. 标记有延误风险和已延误的订单
+            match order_service::flag_delivery_risks(&pool, &chat_server, config.delivery_at_risk_days).await {
+                Ok(0) => {}
+                Ok(count) => log::info!("Flagged {} orders at risk of shipping late.", count),
+                Err(e) => log::error!("Failed to check order delivery risk: {:?}", e),
+            }
+
+            // 3. 标记逾期发票，并定期提醒采购方付款
+            match invoice_service::flag_overdue_invoices(&pool, &chat_server).await {
+                Ok(0) => {}
+                Ok(count) => log::info!("Flagged {} overdue invoices.", count),
+                Err(e) => log::error!("Failed to check overdue invoices: {:?}", e),
+            }
+            match invoice_service::send_overdue_reminders(&pool, &chat_server, config.invoice_reminder_interval_days).await {
+                Ok(0) => {}
+                Ok(count) => log::info!("Sent {} overdue invoice reminders.", count),
+                Err(e) => log::error!("Failed to send overdue invoice reminders: {:?}", e),
+            }
+
+            // 4. 补完中断的退款
+            match payment_service::reconcile_pending_refunds(&pool, payment_provider.as_ref()).await {
+                Ok(0) => {}
+                Ok(count) => log::info!("Completed {} pending refunds.", count),
+                Err(e) => log::error!("Failed to reconcile pending refunds: {:?}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,322 @@
+// src/services/shipment_service.rs
+
+use crate::{
+    errors::AppError,
+    models::{shipment::{GoodsReceiptDto, Shipment}, user::{Claims, OPERATOR_ROLES}},
+    services::{chat_server::ChatServer, notification_service, order_service, rfq_service},
+    utils::auth_utils,
+};
+use actix::Addr;
+use chrono::NaiveDate;
+use futures_util::stream::StreamExt;
+use sqlx::{MySqlConnection, MySqlPool};
+
+// 装箱单不放在公开的 ./uploads 目录下，只能通过带权限检查的下载接口获取
+const PACKING_LIST_UPLOAD_DIR: &str = "./private_uploads/shipments";
+
+const SHIPMENT_SELECT: &str =
+    "SELECT s.*, g.received_quantity, g.rejected_quantity, g.note as receipt_note, g.created_at as received_at
+     FROM order_shipments s LEFT JOIN goods_receipts g ON g.shipment_id = s.id";
+
+// 汇总订单已发货数量和已拒收数量：(shipped_total, rejected_total)
+pub(crate) async fn shipment_totals(conn: &mut MySqlConnection, order_id: i32) -> Result<(i64, i64), AppError> {
+    let totals: (i64, i64) = sqlx::query_as(
+        "SELECT CAST(COALESCE(SUM(s.quantity_shipped), 0) AS SIGNED), CAST(COALESCE(SUM(g.rejected_quantity), 0) AS SIGNED)
+         FROM order_shipments s LEFT JOIN goods_receipts g ON g.shipment_id = s.id WHERE s.order_id = ?"
+    )
+        .bind(order_id)
+        .fetch_one(&mut *conn)
+        .await?;
+    Ok(totals)
+}
+
+/// 供应商为订单登记一次发货（可分批），可附带装箱单
+/// 当有效发货数量（已发货 - 已拒收）达到订单数量时，订单自动进入 SHIPPED 状态
+pub async fn create_shipment(
+    pool: &MySqlPool,
+    chat_server: &Addr<ChatServer>,
+    order_id: i32,
+    claims: &Claims,
+    payload: actix_multipart::Multipart,
+) -> Result<u64, AppError> {
+    auth_utils::require_role(claims, OPERATOR_ROLES)?;
+
+    // 装箱单在解析表单时就已保存，之后任何一步失败都要删除，避免留下无主文件
+    let mut packing_list: Option<(String, String)> = None;
+    let result = record_shipment(pool, chat_server, order_id, claims, payload, &mut packing_list).await;
+    if let (Err(_), Some((_, path))) = (&result, &packing_list) {
+        rfq_service::remove_attachment(path);
+    }
+    result
+}
+
+async fn record_shipment(
+    pool: &MySqlPool,
+    chat_server: &Addr<ChatServer>,
+    order_id: i32,
+    claims: &Claims,
+    mut payload: actix_multipart::Multipart,
+    packing_list: &mut Option<(String, String)>,
+) -> Result<u64, AppError> {
+    let mut quantity = String::new();
+    let mut carrier = String::new();
+    let mut tracking_number = String::new();
+    let mut ship_date = String::new();
+
+    while let Some(field_result) = payload.next().await {
+        let mut field = field_result?;
+
+        let field_name = field
+            .content_disposition()
+            .and_then(|cd| cd.get_name().map(|s| s.to_string()))
+            .unwrap_or_default();
+
+        match field_name.as_str() {
+            "quantity_shipped" | "carrier" | "tracking_number" | "ship_date" => {
+                let mut data = Vec::new();
+                while let Some(chunk) = field.next().await {
+                    data.extend_from_slice(&chunk?);
+                }
+                let value = String::from_utf8(data)
+                    .map_err(|_| AppError::BadRequest("Invalid UTF-8 in form fields".to_string()))?;
+                match field_name.as_str() {
+                    "quantity_shipped" => quantity = value,
+                    "carrier" => carrier = value,
+                    "tracking_number" => tracking_number = value,
+                    "ship_date" => ship_date = value,
+                    _ => (),
+                }
+            }
+            "packing_list" => {
+                if packing_list.is_some() {
+                    return Err(AppError::BadRequest("Only one packing list can be attached.".to_string()));
+                }
+                *packing_list = rfq_service::save_attachment_field(&mut field, PACKING_LIST_UPLOAD_DIR).await?;
+            }
+            _ => (),
+        }
+    }
+
+    let quantity: i32 = quantity.trim().parse()
+        .map_err(|_| AppError::BadRequest("Invalid quantity".to_string()))?;
+    if quantity <= 0 {
+        return Err(AppError::BadRequest("Shipped quantity must be positive.".to_string()));
+    }
+    if carrier.trim().is_empty() {
+        return Err(AppError::BadRequest("Carrier is required.".to_string()));
+    }
+    let ship_date = NaiveDate::parse_from_str(ship_date.trim(), "%Y-%m-%d")
+        .map_err(|_| AppError::BadRequest("Invalid ship date, expected YYYY-MM-DD".to_string()))?;
+    let tracking_number = Some(tracking_number.trim().to_string()).filter(|t| !t.is_empty());
+
+    let mut tx = pool.begin().await?;
+    let (current_status, buyer_company_id, supplier_company_id) =
+        order_service::lock_order(&mut tx, order_id, claims).await?;
+
+    if claims.company_id != supplier_company_id {
+        return Err(AppError::BadRequest("Only the supplier can record shipments.".to_string()));
+    }
+    if !matches!(current_status.as_str(), "IN_PRODUCTION" | "SHIPPED") {
+        return Err(AppError::BadRequest("Shipments can only be recorded for orders in production.".to_string()));
+    }
+
+    let (order_quantity,): (i32,) = sqlx::query_as("SELECT quantity FROM purchase_orders WHERE id = ?")
+        .bind(order_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    let (shipped_total, rejected_total) = shipment_totals(&mut tx, order_id).await?;
+    let outstanding = order_quantity as i64 - (shipped_total - rejected_total);
+    if quantity as i64 > outstanding {
+        return Err(AppError::BadRequest(format!(
+            "Shipped quantity exceeds the outstanding quantity of {}.",
+            outstanding
+        )));
+    }
+
+    let (packing_list_filename, packing_list_path) = packing_list.as_ref().map(|(name, path)| (name, path)).unzip();
+    let result = sqlx::query(
+        "INSERT INTO order_shipments (order_id, quantity_shipped, carrier, tracking_number, ship_date, packing_list_filename, packing_list_path, created_by_user_id)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+        .bind(order_id)
+        .bind(quantity)
+        .bind(carrier.trim())
+        .bind(&tracking_number)
+        .bind(ship_date)
+        .bind(packing_list_filename)
+        .bind(packing_list_path)
+        .bind(claims.sub)
+        .execute(&mut *tx)
+        .await?;
+
+    // 全部数量发出后，订单进入 SHIPPED 状态
+    if current_status == "IN_PRODUCTION" && quantity as i64 == outstanding {
+        order_service::apply_status_change(
+            &mut tx,
+            order_id,
+            Some(&current_status),
+            "SHIPPED",
+            claims,
+            Some("All ordered quantity has been shipped".to_string()),
+        ).await?;
+
+        // 记录实际发货日期，以及是否在承诺（或预计）发货日期之前发货
+        sqlx::query(
+            "UPDATE purchase_orders SET shipped_date = ?, shipped_on_time = (? <= COALESCE(committed_ship_date, expected_ship_date)) WHERE id = ?"
+        )
+            .bind(ship_date)
+            .bind(ship_date)
+            .bind(order_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+
+    let message = match &tracking_number {
+        Some(tracking) => format!("{} units of order #{} were shipped via {} (tracking: {})", quantity, order_id, carrier.trim(), tracking),
+        None => format!("{} units of order #{} were shipped via {}", quantity, order_id, carrier.trim()),
+    };
+    if let Err(e) = notification_service::notify_company(
+        pool,
+        chat_server,
+        buyer_company_id,
+        message,
+        format!("/orders/{}", order_id),
+    ).await {
+        log::error!("Failed to send shipment notification: {:?}", e);
+    }
+
+    Ok(result.last_insert_id())
+}
+
+/// 获取订单的全部发货记录，只有订单双方可以查看
+pub async fn get_shipments_for_order(
+    pool: &MySqlPool,
+    order_id: i32,
+    claims: &Claims,
+) -> Result<Vec<Shipment>, AppError> {
+    order_service::ensure_order_party(pool, order_id, claims).await?;
+
+    let shipments = sqlx::query_as(&format!("{} WHERE s.order_id = ? ORDER BY s.ship_date ASC, s.id ASC", SHIPMENT_SELECT))
+        .bind(order_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(shipments)
+}
+
+/// 获取单条发货记录（用于下载装箱单），只有订单双方可以查看
+pub async fn get_shipment(
+    pool: &MySqlPool,
+    order_id: i32,
+    shipment_id: i32,
+    claims: &Claims,
+) -> Result<Shipment, AppError> {
+    order_service::ensure_order_party(pool, order_id, claims).await?;
+
+    sqlx::query_as(&format!("{} WHERE s.id = ? AND s.order_id = ?", SHIPMENT_SELECT))
+        .bind(shipment_id)
+        .bind(order_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("Shipment not found".to_string()))
+}
+
+/// 采购方对某次发货进行收货，记录实收和拒收数量
+/// 所有发货都完成收货且实收数量达到订单数量后，订单自动进入 DELIVERED 状态
+pub async fn record_goods_receipt(
+    pool: &MySqlPool,
+    chat_server: &Addr<ChatServer>,
+    order_id: i32,
+    shipment_id: i32,
+    dto: GoodsReceiptDto,
+    claims: &Claims,
+) -> Result<(), AppError> {
+    auth_utils::require_role(claims, OPERATOR_ROLES)?;
+    if dto.received_quantity < 0 || dto.rejected_quantity < 0 {
+        return Err(AppError::BadRequest("Quantities cannot be negative.".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+    let (current_status, buyer_company_id, supplier_company_id) =
+        order_service::lock_order(&mut tx, order_id, claims).await?;
+
+    if claims.company_id != buyer_company_id {
+        return Err(AppError::BadRequest("Only the buyer can record goods receipts.".to_string()));
+    }
+
+    let shipment: Shipment = sqlx::query_as(&format!("{} WHERE s.id = ? AND s.order_id = ?", SHIPMENT_SELECT))
+        .bind(shipment_id)
+        .bind(order_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("Shipment not found".to_string()))?;
+
+    if shipment.received_at.is_some() {
+        return Err(AppError::BadRequest("This shipment has already been received.".to_string()));
+    }
+    if dto.received_quantity + dto.rejected_quantity != shipment.quantity_shipped {
+        return Err(AppError::BadRequest(format!(
+            "Received and rejected quantities must add up to the shipped quantity of {}.",
+            shipment.quantity_shipped
+        )));
+    }
+
+    sqlx::query(
+        "INSERT INTO goods_receipts (shipment_id, received_quantity, rejected_quantity, note, received_by_user_id) VALUES (?, ?, ?, ?, ?)"
+    )
+        .bind(shipment_id)
+        .bind(dto.received_quantity)
+        .bind(dto.rejected_quantity)
+        .bind(&dto.note)
+        .bind(claims.sub)
+        .execute(&mut *tx)
+        .await?;
+
+    // 检查是否所有发货都已收货，且实收数量已满足订单
+    let (order_quantity, received_total, pending_receipts): (i32, i64, i64) = sqlx::query_as(
+        "SELECT po.quantity,
+                CAST(COALESCE(SUM(g.received_quantity), 0) AS SIGNED),
+                CAST(COUNT(s.id) - COUNT(g.id) AS SIGNED)
+         FROM purchase_orders po
+         LEFT JOIN order_shipments s ON s.order_id = po.id
+         LEFT JOIN goods_receipts g ON g.shipment_id = s.id
+         WHERE po.id = ? GROUP BY po.id, po.quantity"
+    )
+        .bind(order_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    if current_status == "SHIPPED" && pending_receipts == 0 && received_total >= order_quantity as i64 {
+        order_service::apply_status_change(
+            &mut tx,
+            order_id,
+            Some(&current_status),
+            "DELIVERED",
+            claims,
+            Some("All shipments have been received".to_string()),
+        ).await?;
+    }
+
+    tx.commit().await?;
+
+    let message = if dto.rejected_quantity > 0 {
+        format!(
+            "Shipment #{} of order #{} was received: {} accepted, {} rejected",
+            shipment_id, order_id, dto.received_quantity, dto.rejected_quantity
+        )
+    } else {
+        format!("Shipment #{} of order #{} was received in full", shipment_id, order_id)
+    };
+    if let Err(e) = notification_service::notify_company(
+        pool,
+        chat_server,
+        supplier_company_id,
+        message,
+        format!("/orders/{}", order_id),
+    ).await {
+        log::error!("Failed to send goods receipt notification: {:?}", e);
+    }
+
+    Ok(())
+}
@@ -3,6 +3,7 @@
 use crate::{
     errors::AppError,
     models::user::{ChangePasswordDto, Claims, User, UserProfileResponse},
+    services::auth_service,
     utils::auth_utils,
 };
 use sqlx::MySqlPool;
@@ -10,7 +11,7 @@ use sqlx::MySqlPool;
 pub async fn get_my_profile(pool: &MySqlPool, claims: &Claims) -> Result<UserProfileResponse, AppError> {
     // highlight-start
     let profile = sqlx::query_as(
-        "SELECT u.id, u.full_name, u.email, u.company_id, u.is_active, c.name as company_name
+        "SELECT u.id, u.full_name, u.email, u.company_id, u.role, u.is_active, u.email_verified_at, u.locked_until, c.name as company_name
          FROM users u
          JOIN companies c ON u.company_id = c.id
          WHERE u.id = ?"
@@ -31,7 +32,7 @@ pub async fn change_password(
     pool: &MySqlPool,
     claims: &Claims,
     dto: ChangePasswordDto,
-) -> Result<(), AppError> {
+) -> Result<String, AppError> {
     // 1. 获取用户当前的密码哈希
     let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
         .bind(claims.sub)
@@ -55,11 +56,18 @@ pub async fn change_password(
     let new_password_hash = auth_utils::hash_password(&dto.new_password)
         .map_err(|_| AppError::InternalServerError("Failed to hash new password".to_string()))?;
 
-    sqlx::query("UPDATE users SET password_hash = ? WHERE id = ?")
+    // 版本号加一并撤销其他设备上的会话，当前会话保留并签发新的访问令牌
+    let mut tx = pool.begin().await?;
+    sqlx::query("UPDATE users SET password_hash = ?, token_version = token_version + 1 WHERE id = ?")
         .bind(new_password_hash)
         .bind(claims.sub)
-        .execute(pool)
+        .execute(&mut *tx)
         .await?;
+    auth_service::revoke_sessions(&mut tx, claims.sub, Some(claims.sid)).await?;
+    tx.commit().await?;
 
-    Ok(())
+    let user = User { token_version: user.token_version + 1, ..user };
+    let token = auth_utils::create_jwt(&user, &claims.company_type, claims.sid)
+        .map_err(|_| AppError::InternalServerError("Failed to create token".to_string()))?;
+    Ok(token)
 }
\ No newline at end of file
@@ -1,7 +1,10 @@
 use bcrypt::{hash, verify, DEFAULT_COST};
 use chrono::{Duration, Utc};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
-use crate::models::user::Claims;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use crate::errors::AppError;
+use crate::models::user::{Claims, User};
 
 /// 哈希密码
 pub fn hash_password(password: &str) -> Result<String, bcrypt::BcryptError> {
@@ -13,27 +16,46 @@ pub fn verify_password(password: &str, hash_str: &str) -> Result<bool, bcrypt::B
     verify(password, hash_str)
 }
 
-/// 创建JWT
+/// 生成一次性令牌（邀请、重置密码等），64位十六进制字符串
+pub fn generate_token() -> String {
+    let bytes: [u8; 32] = rand::rng().random();
+    hex::encode(bytes)
+}
+
+/// 令牌只以哈希形式存库，数据库泄露时无法直接使用
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+// 访问令牌的有效期。过期后使用刷新令牌换取新的访问令牌
+pub const ACCESS_TOKEN_MINUTES: i64 = 15;
+
+/// 为某个登录会话创建JWT
 pub fn create_jwt(
-    user_id: i32,
-    company_id: i32,
+    user: &User,
     company_type: &str,
-    is_admin: bool, // <-- 新增参数
+    session_id: i32,
 ) -> Result<String, jsonwebtoken::errors::Error> {
     // 从环境变量获取JWT密钥
     let secret = std::env::var("JWT_SECRET").expect("JWT_SECRET must be set");
-    // 设置过期时间为7天后
     let expiration = Utc::now()
-        .checked_add_signed(Duration::days(7))
+        .checked_add_signed(Duration::minutes(ACCESS_TOKEN_MINUTES))
         .expect("Failed to create valid timestamp")
         .timestamp();
 
     let claims = Claims {
-        sub: user_id,
-        company_id,
+        sub: user.id,
+        company_id: user.company_id,
         company_type: company_type.to_string(),
-        is_admin,
+        role: user.role.clone(),
+        is_admin: user.is_admin,
+        ver: user.token_version,
+        sid: session_id,
         exp: expiration as usize,
+        email_verified: user.email_verified_at.is_some(),
+        mfa_enabled: user.totp_enabled_at.is_some(),
+        api_key_scopes: None,
+        ip_address: None,
     };
 
     // 编码JWT
@@ -55,6 +77,34 @@ pub fn validate_jwt(token: &str) -> Result<Claims, jsonwebtoken::errors::Error>
         .map(|data| data.claims)
 }
 
+/// 检查当前用户在公司内的角色是否允许执行该操作
+pub fn require_role(claims: &Claims, roles: &[&str]) -> Result<(), AppError> {
+    if !claims.has_role(roles) {
+        return Err(AppError::BadRequest(format!(
+            "Your role ({}) is not allowed to perform this action.",
+            claims.role
+        )));
+    }
+    Ok(())
+}
+
+/// 未验证邮箱的用户不能发布RFQ、报价等对外的业务操作
+pub fn require_verified_email(claims: &Claims) -> Result<(), AppError> {
+    if !claims.email_verified {
+        return Err(AppError::BadRequest("Please verify your email address first.".to_string()));
+    }
+    Ok(())
+}
+
+/// 公司规定能授予业务（接受报价、下单）的用户必须开启两步验证
+pub fn require_mfa(claims: &Claims) -> Result<(), AppError> {
+    if !claims.mfa_enabled {
+        return Err(AppError::BadRequest(
+            "Two-factor authentication is required for this action. Enable it in your account settings.".to_string(),
+        ));
+    }
+    Ok(())
+}
 
 // --- 新增：测试模块 ---
 // `#[cfg(test)]` 宏告诉Rust编译器，只有在运行 `cargo test` 命令时才编译和运行这段代码。
@@ -80,4 +130,41 @@ mod tests {
         let is_invalid = verify_password("wrongPassword", &hashed_password).unwrap();
         assert!(!is_invalid, "Wrong password should be invalid");
     }
+
+    #[test]
+    fn test_generate_and_hash_token() {
+        let token = generate_token();
+        assert_eq!(token.len(), 64);
+        assert_ne!(token, generate_token());
+        assert_eq!(hash_token(&token), hash_token(&token));
+        assert_ne!(hash_token(&token), token);
+    }
+
+    #[test]
+    fn test_require_role() {
+        use crate::models::user::{APPROVER_ROLES, OPERATOR_ROLES};
+
+        let claims = |role: &str| Claims {
+            sub: 1,
+            company_id: 1,
+            company_type: "BUYER".to_string(),
+            role: role.to_string(),
+            is_admin: false,
+            ver: 0,
+            sid: 1,
+            exp: 0,
+            email_verified: true,
+            mfa_enabled: false,
+            api_key_scopes: None,
+            ip_address: None,
+        };
+
+        assert!(require_role(&claims("OWNER"), OPERATOR_ROLES).is_ok());
+        assert!(require_role(&claims("OWNER"), APPROVER_ROLES).is_ok());
+        assert!(require_role(&claims("PURCHASER"), OPERATOR_ROLES).is_ok());
+        assert!(require_role(&claims("PURCHASER"), APPROVER_ROLES).is_err());
+        assert!(require_role(&claims("APPROVER"), APPROVER_ROLES).is_ok());
+        assert!(require_role(&claims("VIEWER"), OPERATOR_ROLES).is_err());
+        assert!(require_role(&claims("VIEWER"), APPROVER_ROLES).is_err());
+    }
 }
\ No newline at end of file
@@ -1 +1,2 @@
-pub mod auth_utils;
\ No newline at end of file
+pub mod auth_utils;
+pub mod totp;